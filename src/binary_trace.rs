@@ -0,0 +1,382 @@
+// A compact alternative to text logs.  Each instruction is stored as what changed since
+// the one before it:
+//
+//   flags       u8      which of A, X, Y, P, SP follow, whether PC follows, whether accesses follow
+//   cycles      varint  cycles since the previous instruction
+//   pc          u16     only when it isn't the previous PC plus that instruction's size
+//   bytes               the opcode and its operand, the size comes from the opcode table
+//   registers   u8      each register that changed, in A, X, Y, P, SP order
+//   accesses    varint  count, then a varint of (address << 1 | write) and the value for each
+//
+// after an 8 byte magic, a version and the CPU variant the opcodes are decoded for.
+use std::cell::RefCell;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::rc::Rc;
+use crate::bus::BusAccess;
+use crate::cpu::{ppu_position, Variant};
+use crate::instructions::factory::{disassemble, lookup};
+use crate::trace::{TraceRecord, TraceSink};
+
+const MAGIC: &[u8; 8] = b"NESTRACE";
+const VERSION: u8 = 1;
+
+const A_CHANGED: u8 = 0x01;
+const X_CHANGED: u8 = 0x02;
+const Y_CHANGED: u8 = 0x04;
+const P_CHANGED: u8 = 0x08;
+const SP_CHANGED: u8 = 0x10;
+const PC_JUMPED: u8 = 0x20;
+const HAS_ACCESSES: u8 = 0x40;
+
+fn variant_byte(variant: Variant) -> u8 {
+    match variant {
+        Variant::Ricoh2A03 => 0,
+        Variant::Nmos6502 => 1,
+        Variant::Cmos65C02 => 2
+    }
+}
+
+// What the next record is encoded against
+#[derive(Default)]
+struct Previous {
+    next_pc: u16,
+    registers: [u8; 5],     // A, X, Y, P, SP
+    cycles: u64
+}
+
+impl Previous {
+    fn update(&mut self, record: &TraceRecord) {
+        self.next_pc = record.pc.wrapping_add(record.bytes.len() as u16);
+        self.registers = registers(record);
+        self.cycles = record.cycles;
+    }
+}
+
+fn registers(record: &TraceRecord) -> [u8; 5] {
+    [record.a, record.x, record.y, record.p, record.sp]
+}
+
+fn write_varint(out: &mut dyn Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(input: &mut dyn Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(input)?;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(io::Error::new(ErrorKind::InvalidData, "varint is too long"))
+}
+
+fn read_u8(input: &mut dyn Read) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+// Records accesses through the shared log of a RecordingBus, if one is given
+pub struct BinaryTrace<W: Write> {
+    out: W,
+    accesses: Option<Rc<RefCell<Vec<BusAccess>>>>,
+    pending: Option<TraceRecord>,
+    previous: Option<Previous>
+}
+
+impl<W: Write> BinaryTrace<W> {
+    pub fn new(mut out: W, variant: Variant, accesses: Option<Rc<RefCell<Vec<BusAccess>>>>) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, variant_byte(variant)])?;
+
+        Ok(BinaryTrace { out, accesses, pending: None, previous: None })
+    }
+
+    // Flushes and hands back the output
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_record(&mut self, record: &TraceRecord, accesses: &[BusAccess]) -> io::Result<()> {
+        let first = self.previous.is_none();
+        let previous = self.previous.get_or_insert_with(Previous::default);
+
+        let mut flags = 0;
+        let current = registers(record);
+        let mut changed = Vec::new();
+        for (i, flag) in [A_CHANGED, X_CHANGED, Y_CHANGED, P_CHANGED, SP_CHANGED].iter().enumerate() {
+            if first || current[i] != previous.registers[i] {
+                flags |= flag;
+                changed.push(current[i]);
+            }
+        }
+        if first || record.pc != previous.next_pc {
+            flags |= PC_JUMPED;
+        }
+        if !accesses.is_empty() {
+            flags |= HAS_ACCESSES;
+        }
+
+        self.out.write_all(&[flags])?;
+        write_varint(&mut self.out, record.cycles - previous.cycles)?;
+        if flags & PC_JUMPED != 0 {
+            self.out.write_all(&record.pc.to_le_bytes())?;
+        }
+        self.out.write_all(&record.bytes)?;
+        self.out.write_all(&changed)?;
+
+        if !accesses.is_empty() {
+            write_varint(&mut self.out, accesses.len() as u64)?;
+            for access in accesses {
+                write_varint(&mut self.out, (access.address as u64) << 1 | access.write as u64)?;
+                self.out.write_all(&[access.value])?;
+            }
+        }
+
+        previous.update(record);
+        Ok(())
+    }
+}
+
+impl<W: Write> TraceSink for BinaryTrace<W> {
+    fn before(&mut self, record: &TraceRecord) -> io::Result<()> {
+        if let Some(accesses) = &self.accesses {
+            accesses.borrow_mut().clear();
+        }
+        self.pending = Some(record.clone());
+        Ok(())
+    }
+
+    fn after(&mut self) -> io::Result<()> {
+        let accesses = match &self.accesses {
+            Some(accesses) => accesses.borrow_mut().drain(..).collect(),
+            None => Vec::new()
+        };
+
+        match self.pending.take() {
+            Some(record) => self.write_record(&record, &accesses),
+            None => Ok(())
+        }
+    }
+}
+
+// One instruction read back from a binary trace
+pub struct BinaryRecord {
+    pub record: TraceRecord,
+    pub accesses: Vec<BusAccess>
+}
+
+pub struct BinaryTraceReader<R: Read> {
+    input: R,
+    variant: Variant,
+    previous: Previous
+}
+
+impl<R: Read> BinaryTraceReader<R> {
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut header = [0; 10];
+        input.read_exact(&mut header)?;
+        if &header[..8] != MAGIC || header[8] != VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a version 1 binary trace"));
+        }
+        let variant = match header[9] {
+            0 => Variant::Ricoh2A03,
+            1 => Variant::Nmos6502,
+            2 => Variant::Cmos65C02,
+            _ => return Err(io::Error::new(ErrorKind::InvalidData, "Unknown CPU variant"))
+        };
+
+        Ok(BinaryTraceReader { input, variant, previous: Previous::default() })
+    }
+
+    fn read_record(&mut self, flags: u8) -> io::Result<BinaryRecord> {
+        let input: &mut dyn Read = &mut self.input;
+        let cycles = self.previous.cycles + read_varint(input)?;
+        let pc = if flags & PC_JUMPED != 0 {
+            u16::from_le_bytes([read_u8(input)?, read_u8(input)?])
+        } else {
+            self.previous.next_pc
+        };
+
+        let opcode = read_u8(input)?;
+        let mut bytes = vec![opcode];
+        for _ in 1..lookup(self.variant, opcode).size() {
+            bytes.push(read_u8(input)?);
+        }
+
+        let mut registers = self.previous.registers;
+        for (i, flag) in [A_CHANGED, X_CHANGED, Y_CHANGED, P_CHANGED, SP_CHANGED].iter().enumerate() {
+            if flags & flag != 0 {
+                registers[i] = read_u8(input)?;
+            }
+        }
+
+        let mut accesses = Vec::new();
+        if flags & HAS_ACCESSES != 0 {
+            for _ in 0..read_varint(input)? {
+                let packed = read_varint(input)?;
+                accesses.push(BusAccess {
+                    address: (packed >> 1) as u16,
+                    value: read_u8(input)?,
+                    write: packed & 1 == 1
+                });
+            }
+        }
+
+        // Without memory values, so the same as the Display form
+        let instruction = disassemble(self.variant, &bytes);
+        let (scanline, dot) = ppu_position(cycles);
+        let record = TraceRecord {
+            pc,
+            bytes,
+            instruction: instruction.to_string(),
+            illegal: instruction.illegal(),
+            a: registers[0],
+            x: registers[1],
+            y: registers[2],
+            p: registers[3],
+            sp: registers[4],
+            scanline,
+            dot,
+            cycles
+        };
+        self.previous.update(&record);
+
+        Ok(BinaryRecord { record, accesses })
+    }
+}
+
+impl<R: Read> Iterator for BinaryTraceReader<R> {
+    type Item = io::Result<BinaryRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // A clean end of file can only come before the flags byte
+        let flags = match read_u8(&mut self.input) {
+            Ok(flags) => flags,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e))
+        };
+
+        Some(self.read_record(flags))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bus::{BusAccess, RecordingBus};
+    use crate::cpu::{CPU, Variant};
+    use crate::flat_ram::FlatRam;
+    use crate::trace::{LogOptions, Nestest, StopCondition, TraceFormatter};
+    use super::{BinaryTrace, BinaryTraceReader, read_varint, write_varint};
+
+    #[test]
+    fn varint_round_trip() {
+        // Given
+        let mut encoded = Vec::new();
+
+        // When
+        for &value in [0, 0x7F, 0x80, 0x3FFF, u64::MAX].iter() {
+            write_varint(&mut encoded, value).unwrap();
+        }
+
+        // Then
+        assert_eq!(1 + 1 + 2 + 2 + 10, encoded.len());
+        let mut input = encoded.as_slice();
+        for &value in [0, 0x7F, 0x80, 0x3FFF, u64::MAX].iter() {
+            assert_eq!(value, read_varint(&mut input).unwrap());
+        }
+    }
+
+    // LDX #$03, STX $10, DEX, BNE $0002, JAM
+    fn traced_program() -> Vec<u8> {
+        let mut ram = FlatRam::new();
+        ram.load(0x0000, &[0xA2, 0x03, 0x86, 0x10, 0xCA, 0xD0, 0xFB, 0x02]).unwrap();
+        let bus = RecordingBus::new(Box::new(ram));
+        let accesses = bus.accesses();
+        let mut cpu = CPU::new(Box::new(bus));
+
+        let mut trace = BinaryTrace::new(Vec::new(), Variant::Ricoh2A03, Some(accesses)).unwrap();
+        cpu.log_execution(&mut trace, &LogOptions::default()).unwrap();
+        trace.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        // Given
+        let encoded = traced_program();
+
+        // When
+        let records: Vec<_> = BinaryTraceReader::new(encoded.as_slice()).unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+
+        // Then
+        assert_eq!(11, records.len());
+
+        let stx = &records[1].record;
+        assert_eq!((0x0002, "STX $10".to_string(), 0x03, 9), (stx.pc, stx.instruction.clone(), stx.x, stx.cycles));
+        assert_eq!(vec![BusAccess { address: 0x0010, value: 0x03, write: true }], records[1].accesses);
+
+        let branch_back = &records[4].record;
+        assert_eq!((0x0002, 0x02), (branch_back.pc, branch_back.x));
+
+        let jam = &records[10].record;
+        assert_eq!((0x0007, true, 0x00), (jam.pc, jam.illegal, jam.x));
+    }
+
+    #[test]
+    fn smaller_than_text() {
+        // Given
+        let encoded = traced_program();
+
+        // When
+        let text: usize = BinaryTraceReader::new(encoded.as_slice()).unwrap()
+            .map(|r| Nestest{}.format(&r.unwrap().record).len() + 1)
+            .sum();
+
+        // Then
+        assert!(encoded.len() * 10 < text, "{} bytes against {}", encoded.len(), text);
+    }
+
+    #[test]
+    fn filtered_instructions_are_not_written() {
+        // Given
+        let mut ram = FlatRam::new();
+        ram.load(0x0000, &[0xEA, 0xEA, 0xEA]).unwrap();
+        let bus = RecordingBus::new(Box::new(ram));
+        let accesses = bus.accesses();
+        let mut cpu = CPU::new(Box::new(bus));
+        let mut trace = BinaryTrace::new(Vec::new(), Variant::Ricoh2A03, Some(accesses.clone())).unwrap();
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::Instructions(3)],
+            range: Some((0x0001, 0x0001)),
+            ..LogOptions::default()
+        };
+
+        // When
+        cpu.log_execution(&mut trace, &options).unwrap();
+
+        // Then
+        let encoded = trace.finish().unwrap();
+        let records: Vec<_> = BinaryTraceReader::new(encoded.as_slice()).unwrap().collect();
+        assert_eq!(1, records.len());
+        assert!(accesses.borrow().is_empty());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(BinaryTraceReader::new(&b"NESTRACX\x01\x00"[..]).is_err());
+    }
+}
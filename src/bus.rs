@@ -1,88 +1,297 @@
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use crate::mappers::Mapper;
+use crate::ppu::Ppu;
+use crate::ppu::frame::Frame;
+use crate::save::SaveFile;
+#[cfg(test)]
+use crate::mappers::nrom::NROM;
+
 // See:  https://bugzmanov.github.io/nes_ebook/chapter_4.html
 const RAM: u16 = 0x0000;
 const RAM_MIRRORS_END: u16 = 0x1FFF;
 const PPU_REGISTERS: u16 = 0x2000;
 const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
-const PRG_ROM: u16 = 0xC000;
-const PRG_ROM_END: u16 = 0xFFFF;
+const IO_REGISTERS: u16 = 0x4000;
+const OAM_DMA: u16 = 0x4014;
+const APU_STATUS: u16 = 0x4015;
+const IO_REGISTERS_END: u16 = 0x401F;
+const CARTRIDGE: u16 = 0x4020;
+const CARTRIDGE_END: u16 = 0xFFFF;
 
 // These are to handle mirroring
 fn ram_address(addr: u16) -> usize {
     (addr & 0x07FF) as usize
 }
 
-fn ppu_register_address(addr: u16) -> usize {
-    (addr & 0x2007) as usize
+// The APU and controllers aren't emulated yet, so their registers read as if nothing were
+// driving the bus:  open bus, which after an absolute read is the high byte of the address.
+// $4015 drives every bit but bit 5, and reads 0 with all channels silent.
+// See https://wiki.nesdev.org/w/index.php?title=Open_bus_behavior
+fn io_read(addr: u16) -> u8 {
+    match addr {
+        APU_STATUS => 0x00,
+        _ => (addr >> 8) as u8
+    }
 }
 
-fn rom_address(addr: u16) -> usize {
-    (addr - PRG_ROM) as usize
+// The CPU only sees memory through this trait, so the 6502 core can run on
+// memory maps other than the NES (see FlatRam)
+pub trait Bus {
+    fn read_mem8(&self, addr: u16) -> u8;
+    fn write_mem8(&mut self, addr: u16, data: u8);
+
+    // A read without side effects, for showing memory in traces
+    fn peek_mem8(&self, addr: u16) -> u8 {
+        self.read_mem8(addr)
+    }
+
+    // Called once the CPU has finished an instruction that took the given number of cycles
+    fn tick(&mut self, _cycles: u8) {}
+
+    // True while something on the bus is holding the CPU /IRQ line low
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // True while something on the bus (the PPU at vblank) is holding the CPU /NMI line low
+    fn nmi(&self) -> bool {
+        false
+    }
+
+    // True for addresses where it matters which cycle of an instruction an access is made
+    // on, so the CPU ticks up to that cycle first
+    fn timed(&self, _addr: u16) -> bool {
+        false
+    }
+
+    // The page last written to $4014, for the CPU's OAM DMA unit to copy.  Taking it
+    // clears it.
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        None
+    }
+
+    fn read_mem16(&self, addr: u16) -> u16 {
+        let bytes = [self.read_mem8(addr), self.read_mem8(addr.wrapping_add(1))];
+        u16::from_le_bytes(bytes)
+    }
+
+    #[cfg(test)]
+    fn write_mem16(&mut self, addr: u16, data: u16) {
+        let bytes: [u8; 2] = data.to_le_bytes();
+        self.write_mem8(addr, bytes[0]);
+        self.write_mem8(addr.wrapping_add(1), bytes[1]);
+    }
 }
 
-pub struct Bus {
+// Reading PPU registers has side effects and reaches CHR through the mapper, but reads
+// only get &self, hence the RefCells
+pub struct NesBus {
     cpu_vram: [u8; 0x0800],
-    prg_rom: [u8; 0x4000]
+    ppu: RefCell<Ppu>,
+    mapper: RefCell<Box<dyn Mapper>>,
+    oam_dma: Option<u8>,
+    save_file: Option<SaveFile>
 }
 
-impl Bus {
+impl NesBus {
+    #[cfg(test)]
     pub fn empty() -> Self {
-        Bus::new([0; 0x4000])
+        NesBus::new(Box::new(NROM::from_program(vec![0; 0x4000])))
     }
 
-    pub fn new(program: [u8; 0x4000]) -> Self {
-       Bus {
+    pub fn new(mapper: Box<dyn Mapper>) -> Self {
+       NesBus {
            // Address space is 0x0000-0x2000 but it is mirrored twice due to only
            // allowing for 11 bits in the address bus.
            // See https://bugzmanov.github.io/nes_ebook/chapter_4.html
            cpu_vram: [0; 0x0800],
-           prg_rom: program
+           ppu: RefCell::new(Ppu::new()),
+           mapper: RefCell::new(mapper),
+           oam_dma: None,
+           save_file: None
        }
     }
 
-    pub fn read_mem8(&self, addr: u16) -> u8 {
+    // The picture, updated as the PPU draws it
+    pub fn frame(&self) -> Rc<RefCell<Frame>> {
+        self.ppu.borrow().frame()
+    }
+
+    // Loads battery backed RAM from the save file, which is then kept up to date from tick()
+    // and when the Bus is dropped.  Boards without a battery have nothing to save.
+    pub fn attach_save_file(&mut self, mut save_file: SaveFile, trainer: Option<&[u8]>) -> io::Result<()> {
+        let mapper = self.mapper.get_mut();
+
+        if let Some(ram) = mapper.save_ram_mut() {
+            save_file.load(ram)?;
+
+            // A trainer is copied to $7000 at every power on, over whatever the battery kept
+            if let Some(trainer) = trainer {
+                for (i, &data) in trainer.iter().enumerate() {
+                    mapper.cpu_write(0x7000 + i as u16, data);
+                }
+                save_file.mark_saved(mapper.save_ram().unwrap_or_default());
+            }
+
+            self.save_file = Some(save_file);
+        }
+
+        Ok(())
+    }
+}
+
+impl Bus for NesBus {
+    fn read_mem8(&self, addr: u16) -> u8 {
         match addr {
             RAM ..= RAM_MIRRORS_END => self.cpu_vram[ram_address(addr)],
-            PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END => todo!("PPU not supported yet!"),
-            PRG_ROM ..= PRG_ROM_END => self.prg_rom[rom_address(addr)],
-            _ => {
-                // Todo:  something else here?
-                println!("Ignoring memory read at:  {:04X}", addr);
-                0
-            }
+            PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END => {
+                self.ppu.borrow_mut().read_register(addr, self.mapper.borrow_mut().as_mut())
+            },
+            IO_REGISTERS ..= IO_REGISTERS_END => io_read(addr),
+            CARTRIDGE ..= CARTRIDGE_END => self.mapper.borrow().cpu_read(addr)
         }
     }
 
-    pub fn write_mem8(&mut self, addr: u16, data: u8) {
+    fn write_mem8(&mut self, addr: u16, data: u8) {
         match addr {
             RAM ..= RAM_MIRRORS_END => self.cpu_vram[ram_address(addr)] = data,
-            PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END => todo!("PPU not supported yet!"),
-            _ => {
-                // Todo:  something else here?
-                println!("Ignoring memory write at:  {}", addr);
+            PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END => {
+                self.ppu.get_mut().write_register(addr, data, self.mapper.get_mut().as_mut())
+            },
+            OAM_DMA => self.oam_dma = Some(data),
+            IO_REGISTERS ..= IO_REGISTERS_END => {},
+            CARTRIDGE ..= CARTRIDGE_END => self.mapper.get_mut().cpu_write(addr, data)
+        }
+    }
+
+    // Only the PPU registers have read side effects
+    fn peek_mem8(&self, addr: u16) -> u8 {
+        match addr {
+            RAM ..= RAM_MIRRORS_END => self.cpu_vram[ram_address(addr)],
+            PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END => self.ppu.borrow().peek_register(addr),
+            IO_REGISTERS ..= IO_REGISTERS_END => io_read(addr),
+            CARTRIDGE ..= CARTRIDGE_END => self.mapper.borrow().cpu_read(addr)
+        }
+    }
+
+    // Cartridge hardware (e.g. the MMC3 scanline counter) can hold /IRQ low
+    fn irq(&self) -> bool {
+        self.mapper.borrow().irq()
+    }
+
+    fn nmi(&self) -> bool {
+        self.ppu.borrow().nmi()
+    }
+
+    // The PPU registers, so writes to the scroll registers land on the right dot
+    fn timed(&self, addr: u16) -> bool {
+        (PPU_REGISTERS ..= PPU_REGISTERS_MIRRORS_END).contains(&addr)
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        self.oam_dma.take()
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.ppu.get_mut().tick(cycles, self.mapper.get_mut().as_mut());
+        self.mapper.get_mut().tick(cycles);
+
+        if let (Some(save_file), Some(ram)) = (&mut self.save_file, self.mapper.get_mut().save_ram()) {
+            if let Err(e) = save_file.tick(cycles, ram) {
+                eprintln!("Could not write {}:  {}", save_file.path().display(), e);
             }
         }
     }
+}
 
-    pub fn read_mem16(&self, addr: u16) -> u16 {
-        let bytes = [self.read_mem8(addr), self.read_mem8(addr+1)];
-        u16::from_le_bytes(bytes)
+impl Drop for NesBus {
+    fn drop(&mut self) {
+        if let (Some(save_file), Some(ram)) = (&mut self.save_file, self.mapper.get_mut().save_ram()) {
+            if let Err(e) = save_file.flush(ram) {
+                eprintln!("Could not write {}:  {}", save_file.path().display(), e);
+            }
+        }
     }
+}
 
-    pub fn write_mem16(&mut self, addr: u16, data: u16) {
-        let bytes: [u8; 2] = data.to_le_bytes();
-        self.write_mem8(addr, bytes[0]);
-        self.write_mem8(addr+1, bytes[1]);
+// One read or write as it appeared on the bus
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BusAccess {
+    pub address: u16,
+    pub value: u8,
+    pub write: bool
+}
+
+// Wraps another bus and logs every access made through it, for comparing against
+// cycle-by-cycle test data or writing binary traces.  The CPU owns its bus, so the log
+// is shared with whoever wants to read it.
+pub struct RecordingBus {
+    bus: Box<dyn Bus>,
+    accesses: Rc<RefCell<Vec<BusAccess>>>
+}
+
+impl RecordingBus {
+    pub fn new(bus: Box<dyn Bus>) -> Self {
+        RecordingBus { bus, accesses: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    pub fn accesses(&self) -> Rc<RefCell<Vec<BusAccess>>> {
+        Rc::clone(&self.accesses)
+    }
+}
+
+impl Bus for RecordingBus {
+    fn read_mem8(&self, addr: u16) -> u8 {
+        let value = self.bus.read_mem8(addr);
+        self.accesses.borrow_mut().push(BusAccess { address: addr, value, write: false });
+        value
+    }
+
+    fn write_mem8(&mut self, addr: u16, data: u8) {
+        self.accesses.borrow_mut().push(BusAccess { address: addr, value: data, write: true });
+        self.bus.write_mem8(addr, data);
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.bus.tick(cycles);
+    }
+
+    // Not recorded, the CPU didn't make these accesses
+    fn peek_mem8(&self, addr: u16) -> u8 {
+        self.bus.peek_mem8(addr)
+    }
+
+    fn irq(&self) -> bool {
+        self.bus.irq()
+    }
+
+    fn nmi(&self) -> bool {
+        self.bus.nmi()
+    }
+
+    fn timed(&self, addr: u16) -> bool {
+        self.bus.timed(addr)
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        self.bus.take_oam_dma()
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::bus::Bus;
+    use std::fs;
+    use crate::bus::{Bus, BusAccess, NesBus, RecordingBus};
+    use crate::mappers::nrom::NROM;
+    use crate::rom::INesRom;
+    use crate::save::SaveFile;
 
     #[test]
     fn read_write_8bit_ram() {
         // Given
-        let mut bus = Bus::empty();
+        let mut bus = NesBus::empty();
         bus.write_mem8(0x0300, 0x1A);
 
         // Then
@@ -92,7 +301,7 @@ mod test {
     #[test]
     fn read_write_16bit_ram() {
         // Given
-        let mut bus = Bus::empty();
+        let mut bus = NesBus::empty();
         bus.write_mem16(0x0300, 0xFFE9);
 
         // Then
@@ -102,7 +311,7 @@ mod test {
     #[test]
     fn read_16bit_ram_little_endian() {
         // Given
-        let mut bus = Bus::empty();
+        let mut bus = NesBus::empty();
         bus.write_mem8(0x08A0, 0x10);
         bus.write_mem8(0x08A1, 0x28);
 
@@ -113,7 +322,7 @@ mod test {
     #[test]
     fn write_16bit_ram_little_endian() {
         // Given
-        let mut bus = Bus::empty();
+        let mut bus = NesBus::empty();
 
         // When
         bus.write_mem16(0x004A, 0xD82A);
@@ -123,4 +332,116 @@ mod test {
         assert_eq!(0xD8, bus.read_mem8(0x004B));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn cartridge_space_goes_to_mapper() {
+        // Given
+        let mut program = vec![0; 0x4000];
+        program[0x0123] = 0x4C;
+        let mut bus = NesBus::new(Box::new(NROM::from_program(program)));
+
+        // When
+        bus.write_mem8(0x6010, 0x77);
+
+        // Then
+        assert_eq!(0x4C, bus.read_mem8(0xC123));
+        assert_eq!(0x77, bus.read_mem8(0x6010));
+    }
+
+    #[test]
+    fn bus_mirrors_ppu_registers() {
+        // Given
+        let mut bus = NesBus::empty();
+
+        // When
+        bus.write_mem8(0x3FFE, 0x21);
+        bus.write_mem8(0x2FFE, 0x08);
+        bus.write_mem8(0x2007, 0x42);
+        bus.write_mem8(0x2006, 0x21);
+        bus.write_mem8(0x2006, 0x08);
+
+        // Then
+        bus.read_mem8(0x2007);
+        assert_eq!(0x42, bus.read_mem8(0x200F));
+    }
+
+    #[test]
+    fn io_registers_read_open_bus() {
+        // Given
+        let mut bus = NesBus::empty();
+
+        // When
+        bus.write_mem8(0x4016, 0x01);
+        bus.write_mem8(0x4015, 0x0F);
+
+        // Then
+        assert_eq!(0x40, bus.read_mem8(0x4016));
+        assert_eq!(0x40, bus.peek_mem8(0x4000));
+        assert_eq!(0x00, bus.read_mem8(0x4015));
+    }
+
+    #[test]
+    fn nametable_mirroring_from_header() {
+        // Given, a vertically mirrored NROM image
+        let mut contents = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        contents.extend(vec![0; 0x4000 + 0x2000]);
+        let mut bus = INesRom::new(contents).unwrap().to_bus().unwrap();
+
+        // When
+        bus.write_mem8(0x2006, 0x24);
+        bus.write_mem8(0x2006, 0x10);
+        bus.write_mem8(0x2007, 0x99);
+        bus.write_mem8(0x2006, 0x2C);
+        bus.write_mem8(0x2006, 0x10);
+        bus.read_mem8(0x2007);
+
+        // Then
+        assert_eq!(0x99, bus.read_mem8(0x2007));
+    }
+
+    #[test]
+    fn trainer_loads_over_save() {
+        // Given an iNES ROM with a battery and a trainer, and a save filling its PRG RAM
+        let dir = std::env::temp_dir().join(format!("nes_play_trainer_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("game.sav");
+        fs::write(&save_path, vec![0xAA; 0x2000]).unwrap();
+
+        let mut contents = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x06, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        contents.extend((0..512).map(|i| i as u8));
+        contents.extend(vec![0; 0x4000 + 0x2000]);
+        let rom = INesRom::new(contents).unwrap();
+        let mut bus = rom.to_bus().unwrap();
+
+        // When
+        let save_file = SaveFile::new(dir.join("game.nes").to_str().unwrap(), None, false);
+        bus.attach_save_file(save_file, rom.trainer.as_deref()).unwrap();
+
+        // Then
+        assert_eq!(0xAA, bus.read_mem8(0x6FFF));
+        assert_eq!(0x00, bus.read_mem8(0x7000));
+        assert_eq!(0xFF, bus.read_mem8(0x71FF));
+        assert_eq!(0xAA, bus.read_mem8(0x7200));
+
+        // The game hasn't written anything, so the save is left alone
+        drop(bus);
+        assert_eq!(vec![0xAA; 0x2000], fs::read(&save_path).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recording_logs_accesses() {
+        // Given
+        let mut bus = RecordingBus::new(Box::new(NesBus::empty()));
+        let accesses = bus.accesses();
+
+        // When
+        bus.write_mem8(0x0834, 0x56);
+        bus.read_mem8(0x0034);
+
+        // Then
+        assert_eq!(vec![
+            BusAccess { address: 0x0834, value: 0x56, write: true },
+            BusAccess { address: 0x0034, value: 0x56, write: false }
+        ], *accesses.borrow());
+    }
+}
@@ -1,11 +1,67 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
 use std::fs;
-use std::convert::TryInto;
 use std::fs::File;
-use crate::rom::{INes2Header, INesRom};
-use crate::instructions::factory::generate_instruction;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::binary_trace::{BinaryTrace, BinaryTraceReader};
+use crate::bus::{NesBus, RecordingBus};
+use crate::cpu::{CPU, Variant};
+use crate::flat_ram::FlatRam;
+use crate::instructions::factory::{disassemble, fetch, lookup};
+use crate::rom::INesRom;
+use crate::save::SaveFile;
+use crate::single_step::{OpcodeResults, TestCase};
+use crate::trace::{LogOptions, Nestest, TextTrace, TraceFormatter};
+use crate::trace_diff::first_difference;
 
 pub trait Command {
-    fn execute(&self);
+    fn execute(&self) -> Result<(), Box<dyn Error>>;
+}
+
+fn load_rom(filename: &str) -> Result<INesRom, Box<dyn Error>> {
+    let contents = fs::read(filename)
+        .map_err(|e| format!("Could not read {}:  {}", filename, e))?;
+
+    INesRom::new(contents)
+        .map_err(|e| format!("Could not load {}:  {}", filename, e).into())
+}
+
+// Battery backed RAM carries over between runs of the commands that play a game
+fn attach_save_file(bus: &mut NesBus, rom: &INesRom, rom_filename: &str, save_dir: Option<&str>, clean_save: bool)
+        -> Result<(), Box<dyn Error>> {
+    bus.attach_save_file(SaveFile::new(rom_filename, save_dir, clean_save), rom.trainer.as_deref())
+        .map_err(|e| format!("Could not load save file:  {}", e).into())
+}
+
+// CPU overrides shared by the commands that run a ROM
+#[derive(Default)]
+pub struct CpuOptions {
+    pub start_pc: Option<u16>,
+    pub start_cycle: Option<u64>,
+    pub dummy_accesses: bool,
+    pub magic: Option<u8>,
+    pub variant: Option<Variant>
+}
+
+impl CpuOptions {
+    fn apply(&self, cpu: &mut CPU) {
+        if let Some(start_pc) = self.start_pc {
+            cpu.program_counter = start_pc;
+        }
+        if let Some(start_cycle) = self.start_cycle {
+            cpu.cycles = start_cycle;
+        }
+        cpu.dummy_accesses = self.dummy_accesses;
+        if let Some(magic) = self.magic {
+            cpu.magic_constant = magic;
+        }
+        if let Some(variant) = self.variant {
+            cpu.variant = variant;
+        }
+    }
 }
 
 pub struct Info {
@@ -20,40 +76,456 @@ impl Info {
 }
 
 impl Command for Info {
-    fn execute(&self) {
-        let contents = fs::read(&self.rom_filename).expect("Could not read file");
-        let rom = INesRom::new(contents);
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let rom = load_rom(&self.rom_filename)?;
 
         println!("{}", rom.header);
+        Ok(())
     }
 }
 
+pub enum LogFormat {
+    Text(Box<dyn TraceFormatter>),
+    Binary      // See binary_trace.rs
+}
+
 pub struct Log {
     rom_filename: String,
-    log_filename: String
+    log_filename: Option<String>,   // stdout when missing
+    save_dir: Option<String>,
+    clean_save: bool,
+    format: LogFormat,
+    log_options: LogOptions,
+    cpu_options: CpuOptions
 }
 
 impl Log {
-    pub fn new(rom_file: &str, log_file: &str) -> Self {
+    pub fn new(rom_file: &str, log_file: Option<&str>, save_dir: Option<&str>, clean_save: bool,
+               format: LogFormat, log_options: LogOptions, cpu_options: CpuOptions) -> Self {
         Log {
             rom_filename: rom_file.parse().unwrap(),
-            log_filename: log_file.parse().unwrap()
+            log_filename: log_file.map(String::from),
+            save_dir: save_dir.map(String::from),
+            clean_save,
+            format,
+            log_options,
+            cpu_options
         }
     }
 }
 
 impl Command for Log {
-    fn execute(&self) {
-        let contents = fs::read(&self.rom_filename).expect("Could not read file");
-        let rom = INesRom::new(contents);
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let rom = load_rom(&self.rom_filename)?;
+
+        let mut bus = rom.to_bus()?;
+        attach_save_file(&mut bus, &rom, &self.rom_filename, self.save_dir.as_deref(), self.clean_save)?;
+
+        let reason = match &self.format {
+            LogFormat::Text(formatter) => {
+                let mut cpu = CPU::new(Box::new(bus));
+                self.cpu_options.apply(&mut cpu);
+                let mut log: Box<dyn Write> = match &self.log_filename {
+                    Some(filename) => Box::new(File::create(filename)
+                        .map_err(|e| format!("Could not create {}:  {}", filename, e))?),
+                    None => Box::new(io::stdout())
+                };
+
+                cpu.log_execution(&mut TextTrace::new(log.as_mut(), formatter.as_ref()), &self.log_options)?
+            },
+            LogFormat::Binary => {
+                let filename = self.log_filename.as_ref().ok_or("A binary trace needs a LOG file")?;
+                let bus = RecordingBus::new(Box::new(bus));
+                let accesses = bus.accesses();
+                let mut cpu = CPU::new(Box::new(bus));
+                self.cpu_options.apply(&mut cpu);
+                let log = File::create(filename)
+                    .map_err(|e| format!("Could not create {}:  {}", filename, e))?;
+
+                let mut trace = BinaryTrace::new(BufWriter::new(log), cpu.variant, Some(accesses))?;
+                let reason = cpu.log_execution(&mut trace, &self.log_options)?;
+                trace.finish()?;
+                reason
+            }
+        };
+        eprintln!("{}", reason);
+        Ok(())
+    }
+}
+
+pub struct Benchmark {
+    rom_filename: String,
+    instructions: u64,
+    cpu_options: CpuOptions
+}
+
+impl Benchmark {
+    pub fn new(rom_file: &str, instructions: u64, cpu_options: CpuOptions) -> Self {
+        Benchmark {
+            rom_filename: rom_file.parse().unwrap(),
+            instructions,
+            cpu_options
+        }
+    }
+
+    fn report(name: &str, count: u64, elapsed: Duration) -> f64 {
+        let per_second = count as f64 / elapsed.as_secs_f64();
+        println!("{:<14}{} instructions in {:.3}s ({:.0} instructions/s)",
+                 name, count, elapsed.as_secs_f64(), per_second);
+
+        per_second
+    }
+}
+
+impl Command for Benchmark {
+    // Runs the ROM twice, once decoding each step into a boxed Instruction (as every step
+    // did before the opcode table) and once through the table, and compares the two.
+    // There's no save file, so both runs start from the same state and the user's saves
+    // are left alone.
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let rom = load_rom(&self.rom_filename)?;
+
+        let mut cpu = rom.to_cpu()?;
+        self.cpu_options.apply(&mut cpu);
+        let mut count = 0;
+        let start = Instant::now();
+        while count < self.instructions && !cpu.halted() {
+            cpu.poll_interrupts();
+            let (opcode, arg) = fetch(&mut cpu);
+            let instruction = (lookup(cpu.variant, opcode).decode)(arg);
+            cpu.execute(instruction.as_ref());
+            count += 1;
+        }
+        let boxed = Benchmark::report("Boxed:", count, start.elapsed());
+
+        let mut cpu = rom.to_cpu()?;
+        self.cpu_options.apply(&mut cpu);
+        let mut count = 0;
+        let start = Instant::now();
+        while count < self.instructions && !cpu.halted() {
+            cpu.step();
+            count += 1;
+        }
+        let table = Benchmark::report("Opcode table:", count, start.elapsed());
+
+        println!("Speedup:      {:.2}x from the opcode table", table / boxed);
+        if cpu.halted() {
+            println!("CPU halted by JAM at ${:04X}", cpu.program_counter);
+        }
+        Ok(())
+    }
+}
+
+// Runs the ROM headless for a number of frames and saves the last one as a PPM image
+pub struct Screenshot {
+    rom_filename: String,
+    image_filename: String,
+    frames: u64,
+    save_dir: Option<String>,
+    clean_save: bool,
+    cpu_options: CpuOptions
+}
+
+impl Screenshot {
+    pub fn new(rom_file: &str, image_file: &str, frames: u64, save_dir: Option<&str>, clean_save: bool,
+               cpu_options: CpuOptions) -> Self {
+        Screenshot {
+            rom_filename: rom_file.parse().unwrap(),
+            image_filename: image_file.parse().unwrap(),
+            frames,
+            save_dir: save_dir.map(String::from),
+            clean_save,
+            cpu_options
+        }
+    }
+}
+
+impl Command for Screenshot {
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let rom = load_rom(&self.rom_filename)?;
+        let mut bus = rom.to_bus()?;
+        attach_save_file(&mut bus, &rom, &self.rom_filename, self.save_dir.as_deref(), self.clean_save)?;
+        let frame = bus.frame();
+        let mut cpu = CPU::new(Box::new(bus));
+        self.cpu_options.apply(&mut cpu);
+
+        while frame.borrow().count < self.frames && !cpu.halted() {
+            cpu.step();
+        }
+        if cpu.halted() {
+            eprintln!("CPU halted by JAM at ${:04X} in frame {}", cpu.program_counter, frame.borrow().count);
+        }
+
+        let mut image = BufWriter::new(File::create(&self.image_filename)
+            .map_err(|e| format!("Could not create {}:  {}", self.image_filename, e))?);
+        frame.borrow().write_ppm(&mut image)?;
+        image.flush()?;
+        Ok(())
+    }
+}
+
+// Runs a plain 6502 binary on 64KB of flat RAM, e.g. Klaus Dormann's functional
+// tests, until it traps in a branch or jump to itself.
+// See https://github.com/Klaus2m5/6502_65C02_functional_tests
+pub struct RunRaw {
+    binary_filename: String,
+    load_address: u16,
+    max_instructions: Option<u64>,
+    cpu_options: CpuOptions
+}
+
+impl RunRaw {
+    pub fn new(binary_file: &str, load_address: u16, max_instructions: Option<u64>,
+               cpu_options: CpuOptions) -> Self {
+        RunRaw {
+            binary_filename: binary_file.parse().unwrap(),
+            load_address,
+            max_instructions,
+            cpu_options
+        }
+    }
+}
+
+impl Command for RunRaw {
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let binary = fs::read(&self.binary_filename)
+            .map_err(|e| format!("Could not read {}:  {}", self.binary_filename, e))?;
+
+        let mut ram = FlatRam::new();
+        ram.load(self.load_address, &binary)
+            .map_err(|e| format!("Could not load {}:  {}", self.binary_filename, e))?;
+        let mut cpu = CPU::new(Box::new(ram));
+        self.cpu_options.apply(&mut cpu);
+
+        let mut count: u64 = 0;
+        loop {
+            if self.max_instructions.is_some_and(|max| count >= max) {
+                println!("Stopped after {} instructions", count);
+                break;
+            }
+
+            let pc = cpu.program_counter;
+            cpu.step();
+            count += 1;
+
+            if cpu.halted() {
+                println!("Halted by JAM at ${:04X}", cpu.program_counter);
+                break;
+            }
+            if cpu.program_counter == pc {
+                println!("Trapped at ${:04X}", pc);
+                break;
+            }
+        }
 
-        let mut cpu = rom.to_cpu();
-        // TODO:  Support Stdout if filename is missing?
-        let mut log = File::create(&self.log_filename);
+        println!("{} instructions, {} cycles", count, cpu.cycles);
+        println!("A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+                 cpu.accumulator, cpu.index_register_x, cpu.index_register_y,
+                 cpu.processor_status, cpu.stack_pointer);
+        Ok(())
+    }
+}
+
+pub struct SingleStep {
+    paths: Vec<String>,
+    check_bus: bool,
+    cpu_options: CpuOptions
+}
 
-        match log {
-            Result::Ok(l) => cpu.log_execution(Box::new(l)),
-            Result::Err(e) => eprint!("{}", e)
+impl SingleStep {
+    pub fn new(paths: Vec<String>, check_bus: bool, cpu_options: CpuOptions) -> Self {
+        SingleStep { paths, check_bus, cpu_options }
+    }
+
+    // Directories are expanded to the JSON files in them, one file per opcode
+    fn test_files(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut files = Vec::new();
+        for path in &self.paths {
+            let path = Path::new(path);
+            if path.is_dir() {
+                let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                    .collect();
+                entries.sort();
+                files.extend(entries);
+            } else {
+                files.push(path.to_path_buf());
+            }
         }
+
+        Ok(files)
     }
-}
\ No newline at end of file
+}
+
+impl Command for SingleStep {
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let mut results: BTreeMap<u8, OpcodeResults> = BTreeMap::new();
+
+        for file in self.test_files()? {
+            let contents = fs::read_to_string(&file)
+                .map_err(|e| format!("Could not read {}:  {}", file.display(), e))?;
+            let tests: Vec<TestCase> = serde_json::from_str(&contents)
+                .map_err(|e| format!("Could not parse {}:  {}", file.display(), e))?;
+
+            for test in &tests {
+                let result = test.run(|cpu| self.cpu_options.apply(cpu), self.check_bus);
+                results.entry(test.opcode()).or_default().record(test, result);
+            }
+        }
+
+        let variant = self.cpu_options.variant.unwrap_or(Variant::Ricoh2A03);
+        let mut failed = 0;
+        for (opcode, result) in &results {
+            println!("{:02X} {:<4} {:>6} passed {:>6} failed", opcode,
+                     lookup(variant, *opcode).mnemonic, result.passed, result.failed);
+            if let Some(failure) = &result.first_failure {
+                println!("        {}", failure);
+            }
+            failed += result.failed;
+        }
+
+        if failed > 0 {
+            return Err(format!("{} tests failed", failed).into());
+        }
+        Ok(())
+    }
+}
+
+pub struct DiffLog {
+    rom_filename: String,
+    golden_filename: String,
+    context: usize,
+    cpu_options: CpuOptions
+}
+
+impl DiffLog {
+    pub fn new(rom_file: &str, golden_file: &str, context: usize, cpu_options: CpuOptions) -> Self {
+        DiffLog {
+            rom_filename: rom_file.parse().unwrap(),
+            golden_filename: golden_file.parse().unwrap(),
+            context,
+            cpu_options
+        }
+    }
+}
+
+impl Command for DiffLog {
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let rom = load_rom(&self.rom_filename)?;
+        let golden = fs::read_to_string(&self.golden_filename)
+            .map_err(|e| format!("Could not read {}:  {}", self.golden_filename, e))?;
+
+        // No save file, so battery RAM starts out empty like it did for the golden log
+        let mut cpu = CPU::new(Box::new(rom.to_bus()?));
+        self.cpu_options.apply(&mut cpu);
+
+        let mut previous: VecDeque<String> = VecDeque::with_capacity(self.context);
+        for (number, expected) in golden.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            if cpu.halted() {
+                return Err(format!("CPU halted by JAM at ${:04X} before line {} of {}",
+                                   cpu.program_counter, number + 1, self.golden_filename).into());
+            }
+            let (record, fetched) = cpu.trace();
+            let line = Nestest{}.format(&record);
+
+            if let Some(difference) = first_difference(expected, &line) {
+                println!("Divergence at line {} of {}:  {}", number + 1, self.golden_filename, difference);
+                println!("Instruction:  {}", disassemble(cpu.variant, &record.bytes));
+                println!();
+                for matched in &previous {
+                    println!("  {}", matched);
+                }
+                println!("- {}", expected);
+                println!("+ {}", line);
+
+                return Err("Trace differs from the golden log".into());
+            }
+
+            cpu.run_fetched(fetched);
+            if previous.len() == self.context {
+                previous.pop_front();
+            }
+            if self.context > 0 {
+                previous.push_back(line);
+            }
+        }
+
+        println!("Matched all {} lines of {}", golden.lines().filter(|l| !l.trim().is_empty()).count(),
+                 self.golden_filename);
+        Ok(())
+    }
+}
+
+pub enum Query {
+    All,
+    FirstWrite(u16),
+    ProgramCounter(u16),
+    Cycles(u64, u64)    // Inclusive
+}
+
+// Answers questions from a binary trace without running the ROM again
+pub struct TraceQuery {
+    trace_filename: String,
+    query: Query,
+    formatter: Box<dyn TraceFormatter>
+}
+
+impl TraceQuery {
+    pub fn new(trace_file: &str, query: Query, formatter: Box<dyn TraceFormatter>) -> Self {
+        TraceQuery {
+            trace_filename: trace_file.parse().unwrap(),
+            query,
+            formatter
+        }
+    }
+}
+
+impl Command for TraceQuery {
+    fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let file = File::open(&self.trace_filename)
+            .map_err(|e| format!("Could not read {}:  {}", self.trace_filename, e))?;
+        let reader = BinaryTraceReader::new(BufReader::new(file))
+            .map_err(|e| format!("Could not read {}:  {}", self.trace_filename, e))?;
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+
+        let mut matches = 0;
+        for entry in reader {
+            let entry = entry.map_err(|e| format!("Could not read {}:  {}", self.trace_filename, e))?;
+            let record = &entry.record;
+
+            let matched = match self.query {
+                Query::All => true,
+                Query::ProgramCounter(pc) => record.pc == pc,
+                Query::Cycles(_, end) if record.cycles > end => break,
+                Query::Cycles(start, _) => record.cycles >= start,
+                Query::FirstWrite(address) => {
+                    match entry.accesses.iter().find(|a| a.write && a.address == address) {
+                        Some(access) => {
+                            writeln!(out, "{}", self.formatter.format(record))?;
+                            writeln!(out, "Wrote {:02X} to ${:04X} at cycle {}", access.value, address, record.cycles)?;
+                            return Ok(());
+                        },
+                        None => false
+                    }
+                }
+            };
+
+            if matched {
+                writeln!(out, "{}", self.formatter.format(record))?;
+                matches += 1;
+            }
+        }
+        out.flush()?;
+
+        match self.query {
+            Query::FirstWrite(address) => Err(format!("${:04X} is never written", address).into()),
+            Query::ProgramCounter(pc) => {
+                eprintln!("${:04X} ran {} times", pc, matches);
+                Ok(())
+            },
+            _ => Ok(())
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use std::fmt;
-use std::fmt::{Formatter, write};
-use std::io::Write;
+use std::fmt::Formatter;
+use std::io;
 use crate::bus::Bus;
-use crate::instructions::factory::generate_instruction;
+#[cfg(test)]
+use crate::bus::NesBus;
+#[cfg(test)]
+use crate::mappers::nrom::NROM;
+use crate::instructions::factory::{fetch, lookup};
+use crate::trace::{LogOptions, StopReason, TraceRecord, TraceSink};
 
 // http://wiki.nesdev.com/w/index.php/CPU_registers
 pub struct CPU {
@@ -12,30 +17,254 @@ pub struct CPU {
     pub index_register_x: u8,
     pub index_register_y: u8,
     pub processor_status: u8,  // http://wiki.nesdev.com/w/index.php/Status_flags
-    bus: Bus
+    pub cycles: u64,           // Total CPU cycles since power-up
+    bus: Box<dyn Bus>,
+
+    // http://wiki.nesdev.com/w/index.php/CPU_interrupts
+    nmi_line: bool,         // Last level seen on /NMI, for edge detection
+    bus_nmi: bool,          // Last level the bus (PPU) drove /NMI to
+    nmi_pending: bool,      // Latched falling edge, serviced after the current instruction
+    irq_line: bool,         // External /IRQ level (APU etc.), ORed with the mapper's
+    irq_masked: bool,       // I flag as seen at the last interrupt poll
+    ticked: u8,             // Cycles already ticked by the instruction in progress
+    halted: bool,           // Set by JAM, only cleared by reset
+
+    // Perform the dummy reads of indexed addressing and the double write of
+    // read-modify-write instructions, so register side effects match hardware.
+    // See http://wiki.nesdev.com/w/index.php/CPU_addressing_modes
+    pub dummy_accesses: bool,
+
+    pub variant: Variant,
+
+    // Chip-dependent constant ORed into A by the unstable ANE and LXA opcodes.
+    // See https://www.masswerk.at/6502/6502_instruction_set.html#ANE
+    pub magic_constant: u8
 }
 
 impl CPU {
+    #[cfg(test)]
     pub fn empty() -> Self {
-        let mut cpu = CPU::new([0; 0x4000]);
+        let mut cpu = CPU::new(Box::new(NesBus::empty()));
         cpu.processor_status = 0;   // Zero out the PS
 
         cpu
     }
 
-    pub fn new(program: [u8; 0x4000]) -> Self {
+    // A CPU fresh out of reset, with the NMI/Reset/IRQ vectors at $FFFA-$FFFF
+    #[cfg(test)]
+    pub fn with_vectors(nmi: u16, reset: u16, irq: u16) -> Self {
+        let mut program = vec![0; 0x4000];
+        for (offset, vector) in [nmi, reset, irq].iter().enumerate() {
+            let start = 0x3FFA + offset * 2;
+            program[start..start + 2].copy_from_slice(&vector.to_le_bytes());
+        }
+
+        CPU::new(Box::new(NesBus::new(Box::new(NROM::from_program(program)))))
+    }
+
+    pub fn new(bus: Box<dyn Bus>) -> Self {
         //http://wiki.nesdev.com/w/index.php/CPU_power_up_state
-        CPU {
-            program_counter: 0xC000,  // TODO:  This is standard?
-            stack_pointer: 0xFD,
+        let mut cpu = CPU {
+            program_counter: 0,
+            stack_pointer: 0,
             accumulator: 0,
             index_register_x: 0,
             index_register_y: 0,
-            processor_status: 0x24,  // This is from the nestest golden log...
-            bus: Bus::new(program)
+            processor_status: 0x20,
+            cycles: 0,
+            bus,
+            nmi_line: false,
+            bus_nmi: false,
+            nmi_pending: false,
+            irq_line: false,
+            irq_masked: true,
+            ticked: 0,
+            halted: false,
+            dummy_accesses: false,
+            variant: Variant::Ricoh2A03,
+            magic_constant: 0xEE
+        };
+        cpu.reset();
+
+        cpu
+    }
+
+    // http://wiki.nesdev.com/w/index.php/CPU_power_up_state#After_reset
+    // Reset runs the interrupt sequence with the stack writes suppressed,
+    // so only the stack pointer moves.  From power-up this leaves S at $FD,
+    // P at $24 and 7 cycles elapsed, matching the nestest golden log.
+    pub fn reset(&mut self) {
+        self.tick(7);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(3);
+        self.set_flag(StatusFlag::InterruptDisable, true);
+        self.program_counter = self.bus.read_mem16(0xFFFC);
+
+        self.nmi_pending = false;
+        self.irq_masked = true;
+        self.halted = false;
+    }
+
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // /NMI is edge triggered:  only a transition to asserted is latched
+    #[allow(dead_code)]  // The PPU drives /NMI through the bus, this is for tests
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    // /IRQ is level triggered and stays asserted until the source acknowledges it
+    #[allow(dead_code)]  // Driven by the APU once it is emulated
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq_line || self.bus.irq()
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        self.bus.tick(cycles);
+        self.ticked = self.ticked.wrapping_add(cycles);
+        self.cycles += cycles as u64;
+
+        let bus_nmi = self.bus.nmi();
+        if bus_nmi && !self.bus_nmi {
+            self.nmi_pending = true;
+        }
+        self.bus_nmi = bus_nmi;
+    }
+
+    // The PPU runs 3 dots per CPU cycle, 341 dots per scanline and 262
+    // scanlines per frame.  Until the PPU is emulated this is derived from
+    // the CPU cycle count, which is how the nestest log reports it.
+    // See http://wiki.nesdev.com/w/index.php/Cycle_reference_chart
+    pub fn ppu_position(&self) -> (u16, u16) {
+        ppu_position(self.cycles)
+    }
+
+    // Shared by BRK, IRQ and NMI.  Returns the cycles taken (always 7).
+    // See http://wiki.nesdev.com/w/index.php/CPU_interrupts#Interrupt_hijacking
+    pub fn interrupt(&mut self, vector: u16, break_flag: bool) -> u8 {
+        let [high_pc, low_pc] = self.program_counter.to_be_bytes();
+        self.push_stack(high_pc);
+        self.push_stack(low_pc);
+
+        let break_bits = if break_flag { 0b0011_0000 } else { 0b0010_0000 };
+        self.push_stack(self.processor_status | break_bits);
+        self.set_flag(StatusFlag::InterruptDisable, true);
+        if self.variant == Variant::Cmos65C02 {
+            self.set_flag(StatusFlag::Decimal, false);
+        }
+        self.irq_masked = true;
+
+        // An NMI arriving before the vector fetch takes over the sequence
+        self.tick(4);
+        let vector = if self.nmi_pending {
+            self.nmi_pending = false;
+            0xFFFA
+        } else {
+            vector
+        };
+        self.program_counter = self.bus.read_mem16(vector);
+
+        7
+    }
+
+    // Runs an instruction decoded into a box, how every step ran before the opcode table.
+    // The benchmark keeps it to compare against.
+    pub fn execute(&mut self, instruction: &dyn Instruction) -> u8 {
+        self.run(instruction.bytes()[0], |cpu| instruction.execute(cpu))
+    }
+
+    // Fetches and runs the next instruction through the opcode table, without
+    // allocating.  Interrupts are serviced first, so this returns the cycles for both.
+    pub fn step(&mut self) -> u8 {
+        let interrupt_cycles = self.poll_interrupts();
+        if self.halted {
+            return interrupt_cycles;
+        }
+
+        let fetched = fetch(self);
+        interrupt_cycles + self.run_fetched(fetched)
+    }
+
+    // Runs an instruction already fetched from PC, as returned by `fetch` or `trace`
+    pub fn run_fetched(&mut self, (opcode, arg): (u8, u16)) -> u8 {
+        let entry = lookup(self.variant, opcode);
+
+        let cycles = self.run(opcode, |cpu| (entry.execute)(cpu, arg));
+        debug_assert!(cycles >= entry.cycles, "{} took {} cycles, under its base {}", entry.mnemonic, cycles, entry.cycles);
+        cycles
+    }
+
+    // Runs a single instruction, ticking the bus for whatever cycles the
+    // instruction did not tick itself, then records the I flag the
+    // interrupt poll would have seen.  CLI, SEI and PLP change I after the
+    // poll, so their effect on IRQs is delayed by one instruction.
+    fn run(&mut self, opcode: u8, execute: impl FnOnce(&mut CPU) -> u8) -> u8 {
+        let masked_before = self.get_flag(StatusFlag::InterruptDisable);
+
+        self.ticked = 0;
+        let cycles = execute(self);
+        self.tick(cycles.saturating_sub(self.ticked));
+
+        self.irq_masked = match opcode {
+            0x58 | 0x78 | 0x28 => masked_before,
+            _ => self.get_flag(StatusFlag::InterruptDisable)
+        };
+
+        // The DMA stall counts towards self.cycles, but isn't part of the instruction
+        if let Some(page) = self.bus.take_oam_dma() {
+            self.oam_dma(page);
+        }
+
+        cycles
+    }
+
+    // The 2A03 copies a page to OAM through $2004 after a write to $4014, halting the CPU
+    // for 513 cycles:  one to wait for the write, then a read and a write per byte.  DMA
+    // reads only happen on even cycles, so starting on an odd one takes a cycle more.
+    // See https://wiki.nesdev.org/w/index.php?title=DMA#OAM_DMA
+    fn oam_dma(&mut self, page: u8) {
+        self.tick(1 + (self.cycles & 1) as u8);
+        for low in 0..=0xFF {
+            let value = self.bus.read_mem8(u16::from_be_bytes([page, low]));
+            self.tick(1);
+            self.bus.write_mem8(0x2004, value);
+            self.tick(1);
         }
     }
 
+    // Services a pending NMI or unmasked IRQ between instructions.
+    // Returns the cycles taken, or 0 if there was nothing to service.
+    pub fn poll_interrupts(&mut self) -> u8 {
+        let vector = if self.halted {
+            return 0;
+        } else if self.nmi_pending {
+            self.nmi_pending = false;
+            0xFFFA
+        } else if !self.irq_masked && self.irq_asserted() {
+            0xFFFE
+        } else {
+            return 0;
+        };
+
+        self.ticked = 0;
+        let cycles = self.interrupt(vector, false);
+        self.tick(cycles - self.ticked);
+
+        cycles
+    }
+
     // http://wiki.nesdev.com/w/index.php/Status_flags
     pub fn set_flag(&mut self, flag: StatusFlag, value: bool) {
         self.processor_status = match value {
@@ -54,80 +283,116 @@ impl CPU {
     //  * http://wiki.nesdev.com/w/index.php/CPU_addressing_modes
     //  * https://skilldrick.github.io/easy6502/#addressing
     pub fn mem_address(&self, mode: &AddressingMode) -> u16 {
-        match mode {
-            &AddressingMode::Absolute(address) => address,
-            &AddressingMode::AbsoluteX(base) => {
+        match *mode {
+            AddressingMode::Absolute(address) => address,
+            AddressingMode::AbsoluteX(base) => {
                 base.wrapping_add(self.index_register_x as u16)
             }
-            &AddressingMode::AbsoluteY(base) => {
+            AddressingMode::AbsoluteY(base) => {
                 base.wrapping_add(self.index_register_y as u16)
             }
-            &AddressingMode::IndirectX(base) => {
-                let initial = base.wrapping_add(self.index_register_x);
-                let bytes = [
-                    self.bus.read_mem8(initial as u16),
-                    self.bus.read_mem8((initial).wrapping_add(1) as u16)
-                ];
-                u16::from_le_bytes(bytes)
+            AddressingMode::IndirectX(base) => {
+                self.pointer(base.wrapping_add(self.index_register_x))
             }
-            &AddressingMode::IndirectY(address) => {
-                let bytes = [
-                    self.bus.read_mem8(address as u16),
-                    self.bus.read_mem8(address.wrapping_add(1) as u16)
-                ];
-                let value = u16::from_le_bytes(bytes);
-
-                value.wrapping_add(self.index_register_y as u16)
+            AddressingMode::IndirectY(address) => {
+                self.pointer(address).wrapping_add(self.index_register_y as u16)
             }
-            &AddressingMode::ZeroPage(address) => address as u16,
+            AddressingMode::ZeroPage(address) => address as u16,
             // http://6502.org/tutorials/6502opcodes.html#WRAP
-            &AddressingMode::ZeroPageX(base) => {
+            AddressingMode::ZeroPageX(base) => {
                 base.wrapping_add(self.index_register_x) as u16
             }
-            &AddressingMode::ZeroPageY(base) => {
+            AddressingMode::ZeroPageY(base) => {
                 base.wrapping_add(self.index_register_y) as u16
             }
-            &AddressingMode::Immediate(_) => panic!("Immediate Addressing Mode has no memory address"),
-            &AddressingMode::Accumulator => panic!("Accumulator Mode has no memory address"),
+            AddressingMode::ZeroPageIndirect(address) => self.pointer(address),
+            AddressingMode::Immediate(_) => panic!("Immediate Addressing Mode has no memory address"),
+            AddressingMode::Accumulator => panic!("Accumulator Mode has no memory address"),
         }
     }
 
+    // Zero page is always RAM, so the pointer is peeked and mem_address can be called
+    // as often as needed.  The pointer's real reads are made once, by read_pointer.
+    fn pointer(&self, address: u8) -> u16 {
+        let bytes = [
+            self.bus.peek_mem8(address as u16),
+            self.bus.peek_mem8(address.wrapping_add(1) as u16)
+        ];
+        u16::from_le_bytes(bytes)
+    }
+
     pub fn default_cycles(&self, mode: &AddressingMode) -> u8 {
-        let base_cycles = match mode {
-            &AddressingMode::Accumulator => 0,
-            &AddressingMode::Immediate(_) => 2,
-            &AddressingMode::ZeroPage(_) => 3,
-            &AddressingMode::ZeroPageX(_) => 4,
-            &AddressingMode::ZeroPageY(_) => 4,
-            &AddressingMode::Absolute(_) => 4,
-            &AddressingMode::AbsoluteX(base) => 4,
-            &AddressingMode::AbsoluteY(base) => 4,
-            &AddressingMode::IndirectX(_) => 6,
-            &AddressingMode::IndirectY(address) => 5,
+        let base_cycles = match *mode {
+            AddressingMode::Accumulator => 0,
+            AddressingMode::Immediate(_) => 2,
+            AddressingMode::ZeroPage(_) => 3,
+            AddressingMode::ZeroPageX(_) => 4,
+            AddressingMode::ZeroPageY(_) => 4,
+            AddressingMode::Absolute(_) => 4,
+            AddressingMode::AbsoluteX(_) => 4,
+            AddressingMode::AbsoluteY(_) => 4,
+            AddressingMode::IndirectX(_) => 6,
+            AddressingMode::IndirectY(_) => 5,
+            AddressingMode::ZeroPageIndirect(_) => 5,
         };
 
         base_cycles + self.extra_cycles(mode)
     }
 
     fn extra_cycles(&self, mode: &AddressingMode) -> u8 {
-        match mode {
-            &AddressingMode::AbsoluteX(_) | 
-            &AddressingMode::AbsoluteY(_) | 
-            &AddressingMode::IndirectY(_) => if self.mem_address(mode) > 0xFF { 1 } else { 0 },
-            _ => 0
+        self.page_crossed(mode) as u8
+    }
+
+    // Indexing carried into the high byte, costing loads an extra cycle
+    pub fn page_crossed(&self, mode: &AddressingMode) -> bool {
+        match *mode {
+            AddressingMode::AbsoluteX(_) |
+            AddressingMode::AbsoluteY(_) |
+            AddressingMode::IndirectY(_) => {
+                let base = self.indexing_base(mode);
+                base & 0xFF00 != self.mem_address(mode) & 0xFF00
+            },
+            _ => false
+        }
+    }
+
+    // The address before indexing is applied
+    fn indexing_base(&self, mode: &AddressingMode) -> u16 {
+        match *mode {
+            AddressingMode::AbsoluteX(base) | AddressingMode::AbsoluteY(base) => base,
+            AddressingMode::IndirectY(_) => {
+                self.mem_address(mode).wrapping_sub(self.index_register_y as u16)
+            },
+            _ => self.mem_address(mode)
+        }
+    }
+
+    // Stores never pay the page cross penalty, they always take the extra cycle
+    pub fn store_cycles(&self, mode: &AddressingMode) -> u8 {
+        match *mode {
+            AddressingMode::ZeroPage(_) => 3,
+            AddressingMode::ZeroPageX(_) => 4,
+            AddressingMode::ZeroPageY(_) => 4,
+            AddressingMode::Absolute(_) => 4,
+            AddressingMode::AbsoluteX(_) => 5,
+            AddressingMode::AbsoluteY(_) => 5,
+            AddressingMode::IndirectX(_) => 6,
+            AddressingMode::IndirectY(_) => 6,
+            AddressingMode::ZeroPageIndirect(_) => 5,
+            _ => panic!("Invalid addressing mode for store cycles")
         }
     }
 
     pub fn memory_cycles(&self, mode: &AddressingMode) -> u8 {
-        match mode {
-            &AddressingMode::Accumulator => 2,
-            &AddressingMode::ZeroPage(_) => 5,
-            &AddressingMode::ZeroPageX(_) => 6,
-            &AddressingMode::Absolute(_) => 6,
-            &AddressingMode::AbsoluteX(_) => 7,
-            &AddressingMode::AbsoluteY(_) => 7,
-            &AddressingMode::IndirectX(_) => 8,
-            &AddressingMode::IndirectY(_) => 8,
+        match *mode {
+            AddressingMode::Accumulator => 2,
+            AddressingMode::ZeroPage(_) => 5,
+            AddressingMode::ZeroPageX(_) => 6,
+            AddressingMode::Absolute(_) => 6,
+            AddressingMode::AbsoluteX(_) => 7,
+            AddressingMode::AbsoluteY(_) => 7,
+            AddressingMode::IndirectX(_) => 8,
+            AddressingMode::IndirectY(_) => 8,
             _ => panic!("Invalid addressing mode for memory cycles")
         }
     }
@@ -140,6 +405,15 @@ impl CPU {
         }
     }
 
+    // Like read, but without side effects on the bus
+    pub fn peek(&self, mode: &AddressingMode) -> u8 {
+        match mode {
+            &AddressingMode::Accumulator => self.accumulator,
+            &AddressingMode::Immediate(value) => value,
+            am => self.bus.peek_mem8(self.mem_address(am))
+        }
+    }
+
     pub fn write(&mut self, mode: &AddressingMode, value: u8) {
         match mode {
             &AddressingMode::Accumulator => self.accumulator = value,
@@ -148,14 +422,235 @@ impl CPU {
         }
     }
 
+    // Read for a load instruction.  Indexed modes only read the partially
+    // computed address when the index crosses a page.
+    pub fn load(&mut self, mode: &AddressingMode) -> u8 {
+        let cycle = self.default_cycles(mode).saturating_sub(1);
+        self.address_reads(mode, false, cycle);
+        self.catch_up(mode, cycle);
+        self.read(mode)
+    }
+
+    // Write for a store instruction, which always reads the partially computed address first
+    pub fn store(&mut self, mode: &AddressingMode, value: u8) {
+        let cycle = self.store_cycles(mode) - 1;
+        self.address_reads(mode, true, cycle);
+        self.catch_up(mode, cycle);
+        self.write(mode, value)
+    }
+
+    // Read-modify-write instructions read like a store...
+    pub fn read_modify(&mut self, mode: &AddressingMode) -> u8 {
+        let cycle = self.memory_cycles(mode).saturating_sub(3);
+        self.address_reads(mode, true, cycle);
+        self.catch_up(mode, cycle);
+        self.read(mode)
+    }
+
+    // ...and write the unmodified value back before the result
+    pub fn write_modified(&mut self, mode: &AddressingMode, original: u8, value: u8) {
+        if matches!(mode, AddressingMode::Accumulator) {
+            self.accumulator = value;
+            return;
+        }
+
+        let cycle = self.memory_cycles(mode) - 1;
+        if self.dummy_accesses {
+            self.catch_up(mode, cycle - 1);
+            self.write(mode, original);
+        }
+        self.catch_up(mode, cycle);
+        self.write(mode, value)
+    }
+
+    // The reads made working out the address, before the access itself.  (zp,X) reads
+    // the unindexed pointer before the real one, (zp),Y reads the pointer before fixing
+    // up the high byte.
+    fn address_reads(&mut self, mode: &AddressingMode, always: bool, cycle: u8) {
+        let pointer_first = !matches!(mode, AddressingMode::IndirectX(_));
+        if pointer_first {
+            self.read_pointer(mode);
+        }
+        if self.dummy_accesses {
+            self.dummy_read(mode, always, cycle);
+        }
+        if !pointer_first {
+            self.read_pointer(mode);
+        }
+    }
+
+    fn read_pointer(&mut self, mode: &AddressingMode) {
+        let address = match *mode {
+            AddressingMode::IndirectX(base) => base.wrapping_add(self.index_register_x),
+            AddressingMode::IndirectY(address) | AddressingMode::ZeroPageIndirect(address) => address,
+            _ => return
+        };
+
+        self.bus.read_mem8(address as u16);
+        self.bus.read_mem8(address.wrapping_add(1) as u16);
+    }
+
+    // Made the cycle before the access it precedes
+    // See http://wiki.nesdev.com/w/index.php/CPU_addressing_modes
+    fn dummy_read(&mut self, mode: &AddressingMode, always: bool, cycle: u8) {
+        let address = match *mode {
+            AddressingMode::ZeroPageX(base) |
+            AddressingMode::ZeroPageY(base) |
+            AddressingMode::IndirectX(base) => base as u16,
+            AddressingMode::AbsoluteX(_) |
+            AddressingMode::AbsoluteY(_) |
+            AddressingMode::IndirectY(_) if always || self.page_crossed(mode) => {
+                // High byte not yet fixed up from the carry
+                let base = self.indexing_base(mode);
+                (base & 0xFF00) | (self.mem_address(mode) & 0x00FF)
+            },
+            _ => return
+        };
+
+        self.catch_up_address(address, cycle - 1);
+        self.bus.read_mem8(address);
+    }
+
+    // The bus is normally ticked once an instruction is done, but some of it (the PPU)
+    // needs to see an access on the cycle it happens.  This ticks up to the given cycle
+    // of the instruction in progress before such an access.
+    fn catch_up(&mut self, mode: &AddressingMode, cycle: u8) {
+        if !matches!(mode, AddressingMode::Accumulator | AddressingMode::Immediate(_)) {
+            self.catch_up_address(self.mem_address(mode), cycle);
+        }
+    }
+
+    fn catch_up_address(&mut self, address: u16, cycle: u8) {
+        if cycle > self.ticked && self.bus.timed(address) {
+            self.tick(cycle - self.ticked);
+        }
+    }
+
+    // Shared by ADC and RRA.  Returns any extra cycles taken.
+    // The 2A03 keeps the D flag but has no BCD circuitry, other variants add in decimal.
+    // See http://www.6502.org/tutorials/decimal_mode.html
+    pub fn add_with_carry(&mut self, value: u8) -> u8 {
+        let accumulator = self.accumulator as u16;
+        let value = value as u16;
+        let carry = (self.processor_status & 0x01) as u16;
+        let binary = accumulator + value + carry;
+
+        if !self.decimal_mode() {
+            // Looking at logic implemented here:
+            // https://github.com/bfirsh/jsnes/blob/master/src/cpu.js
+            let overflow = (accumulator ^ value) & 0x80 == 0 && (accumulator ^ binary) & 0x80 != 0;
+            self.accumulator = binary as u8;
+            self.set_flag(StatusFlag::Carry, binary > 0xFF);
+            self.set_flag(StatusFlag::Zero, self.accumulator == 0);
+            self.set_flag(StatusFlag::Overflow, overflow);
+            self.set_flag(StatusFlag::Negative, self.accumulator > 0x7F);
+            return 0;
+        }
+
+        let mut low = (accumulator & 0x0F) + (value & 0x0F) + carry;
+        if low > 0x09 {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+        // N and V come from the sum before the high nibble is adjusted
+        let mut sum = (accumulator & 0xF0) + (value & 0xF0) + low;
+        let overflow = (accumulator ^ value) & 0x80 == 0 && (accumulator ^ sum) & 0x80 != 0;
+        let intermediate = sum as u8;
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+
+        self.accumulator = sum as u8;
+        self.set_flag(StatusFlag::Carry, sum > 0xFF);
+        self.set_flag(StatusFlag::Overflow, overflow);
+
+        if self.variant == Variant::Cmos65C02 {
+            // The 65C02 fixed N and Z, at the cost of a cycle
+            self.set_flag(StatusFlag::Zero, self.accumulator == 0);
+            self.set_flag(StatusFlag::Negative, self.accumulator > 0x7F);
+            1
+        } else {
+            self.set_flag(StatusFlag::Zero, binary as u8 == 0);
+            self.set_flag(StatusFlag::Negative, intermediate > 0x7F);
+            0
+        }
+    }
+
+    // Shared by SBC and ISC.  Returns any extra cycles taken.
+    // See http://www.6502.org/tutorials/decimal_mode.html
+    pub fn subtract_with_borrow(&mut self, value: u8) -> u8 {
+        let accumulator = self.accumulator;
+        let borrow = 1 - (self.processor_status & 0x01) as u16;
+        let binary = (accumulator as u16).wrapping_sub(value as u16).wrapping_sub(borrow);
+
+        // http://nesdev.com/6502_cpu.txt
+        // http://www.6502.org/tutorials/vflag.html
+        // See logic in:  https://github.com/bfirsh/jsnes/blob/master/src/cpu.js
+        let overflow = (accumulator ^ value) & 0x80 != 0 && (accumulator ^ binary as u8) & 0x80 != 0;
+        self.set_flag(StatusFlag::Carry, binary <= 0xFF);
+        self.set_flag(StatusFlag::Overflow, overflow);
+
+        if !self.decimal_mode() {
+            self.accumulator = binary as u8;
+            self.set_flag(StatusFlag::Zero, self.accumulator == 0);
+            self.set_flag(StatusFlag::Negative, self.accumulator > 0x7F);
+            return 0;
+        }
+
+        let borrow = borrow as i16;
+        let mut low = (accumulator & 0x0F) as i16 - (value & 0x0F) as i16 - borrow;
+
+        if self.variant == Variant::Cmos65C02 {
+            let mut result = accumulator as i16 - value as i16 - borrow;
+            if result < 0 {
+                result -= 0x60;
+            }
+            if low < 0 {
+                result -= 0x06;
+            }
+
+            self.accumulator = result as u8;
+            self.set_flag(StatusFlag::Zero, self.accumulator == 0);
+            self.set_flag(StatusFlag::Negative, self.accumulator > 0x7F);
+            1
+        } else {
+            // NMOS flags all come from the binary result
+            let mut high = (accumulator >> 4) as i16 - (value >> 4) as i16;
+            if low < 0 {
+                low -= 0x06;
+                high -= 1;
+            }
+            if high < 0 {
+                high -= 0x06;
+            }
+
+            self.accumulator = ((high << 4) | (low & 0x0F)) as u8;
+            self.set_flag(StatusFlag::Zero, binary as u8 == 0);
+            self.set_flag(StatusFlag::Negative, binary as u8 > 0x7F);
+            0
+        }
+    }
+
+    pub fn decimal_mode(&self) -> bool {
+        self.variant != Variant::Ricoh2A03 && self.get_flag(StatusFlag::Decimal)
+    }
+
+    pub fn branch_target(&self, relative: i8) -> u16 {
+        self.program_counter.wrapping_add(relative as u16)
+    }
+
+    // Taken branches cost one cycle, plus one more to a different page
+    pub fn branch(&mut self, relative: i8) -> u8 {
+        3 + self.set_pc(self.branch_target(relative))
+    }
+
     // See Stack Operations http://obelisk.me.uk/6502/instructions.html
     pub fn push_stack(&mut self, value: u8) {
         self.bus.write_mem8(self.stack_pointer as u16 | 0x0100, value);
-        self.stack_pointer -= 1;
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
     }
 
     pub fn pop_stack(&mut self) -> u8 {
-        self.stack_pointer += 1;
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
         self.bus.read_mem8(self.stack_pointer as u16 | 0x0100)
     }
 
@@ -163,6 +658,7 @@ impl CPU {
         self.bus.read_mem16(addr)
     }
 
+    #[cfg(test)]
     pub fn write_mem16(&mut self, addr: u16, data: u16) {
         self.bus.write_mem16(addr, data)
     }
@@ -179,46 +675,66 @@ impl CPU {
         }
     }
 
-    pub fn log_execution(&mut self, mut log: Box<dyn Write>) {
-        let mut cycle: usize = 4;  // TODO:  Make this configurable?
+    // Logs each instruction before running it, until one of the stop conditions is met or
+    // the CPU halts
+    pub fn log_execution(&mut self, sink: &mut dyn TraceSink, options: &LogOptions) -> io::Result<StopReason> {
+        let mut instructions: u64 = 0;
+        let mut triggered = options.trigger.is_none();
 
         loop {
-            let pc = self.program_counter;
-            let instruction = generate_instruction(self);
-
-            write!(log, "{:04X}  ", pc);
-            match &instruction {
-                Some(inst) => write!(log, "{:<8} ", inst.bytes_string()),
-                None => write!(log, "       ")
-            };
-
-            match &instruction {
-                Some(inst) => {
-                    if inst.illegal() {
-                        write!(log, "*{:<32}", inst.debug_string(&self))
-                    } else {
-                        write!(log, " {:<32}", inst.debug_string(&self))
-                    }
-                },
-                None => write!(log, "{:<25}", "No Instruction")
-            };
-            write!(log, "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} ",
-                   self.accumulator, self.index_register_x, self.index_register_y,
-                   self.processor_status, self.stack_pointer);
-            //write!(log, "PPU:  0, 0 ");  // TODO:  Figure this out too
-
-            match &instruction {
-                Some(inst) => {
-                    let new_cycles = inst.execute(self);
-                    cycle += new_cycles as usize;
-                },
-                None => return
-            };
-
-            //writeln!(log, "CYC:{}", cycle);
-            writeln!(log);
+            if let Some(reason) = options.stop_reason(self, instructions) {
+                return Ok(reason);
+            }
+
+            let (record, fetched) = self.trace();
+            let pc = record.pc;
+            triggered |= options.trigger == Some(pc);
+            let logged = triggered && options.in_range(pc);
+            if logged {
+                sink.before(&record)?;
+            }
+
+            self.run_fetched(fetched);
+            instructions += 1;
+            sink.after()?;
+
+            if self.halted {
+                return Ok(StopReason::Halted(self.program_counter));
+            }
+            if options.stop_on_loop && self.program_counter == pc {
+                return Ok(StopReason::InfiniteLoop(pc));
+            }
         }
     }
+
+    // Fetches the instruction at PC and records the state before it runs, without running
+    // it.  Any interrupt that is due is serviced first.  The record is formatted from the
+    // opcode table, so tracing doesn't allocate an instruction.
+    pub fn trace(&mut self) -> (TraceRecord, (u8, u16)) {
+        self.poll_interrupts();
+
+        let pc = self.program_counter;
+        let (opcode, arg) = fetch(self);
+        let entry = lookup(self.variant, opcode);
+        let (scanline, dot) = self.ppu_position();
+
+        let record = TraceRecord {
+            pc,
+            bytes: (entry.bytes)(arg),
+            instruction: (entry.debug_string)(self, arg),
+            illegal: (entry.illegal)(arg),
+            a: self.accumulator,
+            x: self.index_register_x,
+            y: self.index_register_y,
+            p: self.processor_status,
+            sp: self.stack_pointer,
+            scanline,
+            dot,
+            cycles: self.cycles
+        };
+
+        (record, (opcode, arg))
+    }
 }
 
 
@@ -246,6 +762,35 @@ impl StatusFlag {
 }
 
 
+// Also used to rebuild the PPU column from a binary trace
+pub fn ppu_position(cycles: u64) -> (u16, u16) {
+    let dots = cycles * 3;
+    let scanline = (dots / 341) % 262;
+    let dot = dots % 341;
+
+    (scanline as u16, dot as u16)
+}
+
+// Which 6502 the core behaves as
+// See http://wiki.nesdev.com/w/index.php/CPU
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Variant {
+    Ricoh2A03,  // NES:  NMOS 6502 with decimal mode disconnected
+    Nmos6502,
+    Cmos65C02   // Adds opcodes, fixes JMP ($xxFF), flags valid in decimal mode
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Ricoh2A03 => write!(f, "2A03"),
+            Variant::Nmos6502 => write!(f, "6502"),
+            Variant::Cmos65C02 => write!(f, "65C02"),
+        }
+    }
+}
+
+
 // AddressingMode is a strategy for retrieving a value from memory
 // See:  http://www.obelisk.me.uk/6502/addressing.html
 // See:  https://skilldrick.github.io/easy6502/#addressing
@@ -261,6 +806,7 @@ pub enum AddressingMode {
     ZeroPage(u8),
     ZeroPageX(u8),
     ZeroPageY(u8),
+    ZeroPageIndirect(u8),   // 65C02 only
 }
 
 impl fmt::Display for AddressingMode {
@@ -276,6 +822,7 @@ impl fmt::Display for AddressingMode {
             AddressingMode::ZeroPage(addr) => write!(f, "${:02X}", addr),
             AddressingMode::ZeroPageX(addr) => write!(f, "${:02X},X", addr),
             AddressingMode::ZeroPageY(addr) => write!(f, "${:02X},Y", addr),
+            AddressingMode::ZeroPageIndirect(addr) => write!(f, "(${:02X})", addr),
         }
     }
 }
@@ -284,26 +831,29 @@ impl AddressingMode {
     pub fn debug_string(&self, cpu: &CPU) -> String {
         match self {
             AddressingMode::Absolute(_) | AddressingMode::ZeroPage(_) => {
-                format!("{} = {:02X}", self, cpu.read(self))
+                format!("{} = {:02X}", self, cpu.peek(self))
             },
             AddressingMode::ZeroPageX(_) | AddressingMode::ZeroPageY(_) => {
-                format!("{} @ {:02X} = {:02X}", self, cpu.mem_address(self), cpu.read(self))
+                format!("{} @ {:02X} = {:02X}", self, cpu.mem_address(self), cpu.peek(self))
             },
             AddressingMode::AbsoluteX(_) | AddressingMode::AbsoluteY(_) => {
-                format!("{} @ {:04X} = {:02X}", self, cpu.mem_address(self), cpu.read(self))
+                format!("{} @ {:04X} = {:02X}", self, cpu.mem_address(self), cpu.peek(self))
             },
             AddressingMode::IndirectX(base) => {
                 let initial = base.wrapping_add(cpu.index_register_x);
-                format!("{} @ {:02X} = {:04X} = {:02X}", self, initial, cpu.mem_address(self) ,cpu.read(self))
+                format!("{} @ {:02X} = {:04X} = {:02X}", self, initial, cpu.mem_address(self) ,cpu.peek(self))
             },
             &AddressingMode::IndirectY(address) => {
                 let bytes = [
-                    cpu.bus.read_mem8(address as u16),
-                    cpu.bus.read_mem8(address.wrapping_add(1) as u16)
+                    cpu.bus.peek_mem8(address as u16),
+                    cpu.bus.peek_mem8(address.wrapping_add(1) as u16)
                 ];
                 let initial = u16::from_le_bytes(bytes);
 
-                format!("{} = {:04X} @ {:04X} = {:02X}", self, initial, cpu.mem_address(self) ,cpu.read(self))
+                format!("{} = {:04X} @ {:04X} = {:02X}", self, initial, cpu.mem_address(self) ,cpu.peek(self))
+            }
+            AddressingMode::ZeroPageIndirect(_) => {
+                format!("{} = {:04X} = {:02X}", self, cpu.mem_address(self), cpu.peek(self))
             }
             _ => self.to_string()
         }
@@ -325,15 +875,7 @@ pub trait Instruction: fmt::Display {
         vec![opcode, addr_bytes[0], addr_bytes[1]]
     }
 
-    fn bytes_string(&self) -> String {
-        self.bytes()
-            .iter()
-            .map(|b| format!("{:02X}", b))
-            .collect::<Vec<String>>()
-            .join(" ")
-    }
-
-    fn debug_string(&self, cpu: &CPU) -> String {
+    fn debug_string(&self, _cpu: &CPU) -> String {
         self.to_string()
     }
 
@@ -345,9 +887,15 @@ pub trait Instruction: fmt::Display {
 
 #[cfg(test)]
 mod test {
-    use super::CPU;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::{CPU, StatusFlag, Variant};
     use crate::cpu::AddressingMode::*;
-    use crate::cpu::StatusFlag;
+    use crate::instructions::factory::fetch;
+    use crate::bus::{NesBus, RecordingBus};
+    use crate::flat_ram::FlatRam;
+    use crate::mappers::Mapper;
+    use crate::rom::Mirroring;
 
     #[test]
     fn read_write_16bit_memory() {
@@ -797,4 +1345,429 @@ mod test {
         assert_eq!(0xFD, cpu.stack_pointer);
     }
 
+    #[test]
+    fn reset_loads_vector() {
+        // Given
+        let cpu = CPU::with_vectors(0x9000, 0x8123, 0xA000);
+
+        // Then
+        assert_eq!(0x8123, cpu.program_counter);
+        assert_eq!(0xFD, cpu.stack_pointer);
+        assert_eq!(0x24, cpu.processor_status);
+    }
+
+    #[test]
+    fn nmi_is_edge_triggered() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+
+        // When
+        cpu.set_nmi(true);
+
+        // Then
+        assert_eq!(7, cpu.poll_interrupts());
+        assert_eq!(0x9000, cpu.program_counter);
+
+        cpu.set_nmi(true);      // Still held, no new edge
+        assert_eq!(0, cpu.poll_interrupts());
+
+        cpu.set_nmi(false);
+        cpu.set_nmi(true);
+        assert_eq!(7, cpu.poll_interrupts());
+    }
+
+    #[test]
+    fn irq_masked_by_interrupt_disable() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+
+        // When
+        cpu.set_irq(true);
+
+        // Then
+        assert_eq!(0, cpu.poll_interrupts());
+        assert_eq!(0x8000, cpu.program_counter);
+    }
+
+    #[test]
+    fn cli_delays_irq_by_one_instruction() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+        cpu.bus.write_mem8(0x0200, 0x58);   // CLI
+        cpu.bus.write_mem8(0x0201, 0xEA);   // NOP
+        cpu.program_counter = 0x0200;
+        cpu.set_irq(true);
+
+        // When
+        let cli = fetch(&mut cpu);
+        cpu.run_fetched(cli);
+
+        // Then
+        assert_eq!(0, cpu.poll_interrupts());
+
+        let nop = fetch(&mut cpu);
+        cpu.run_fetched(nop);
+
+        assert_eq!(7, cpu.poll_interrupts());
+        assert_eq!(0xA000, cpu.program_counter);
+        assert_eq!(0x20, cpu.pop_stack());  // B flag clear for hardware interrupts
+        assert_eq!(0x02, cpu.pop_stack());
+        assert_eq!(0x02, cpu.pop_stack());
+    }
+
+    #[test]
+    fn reset_takes_seven_cycles() {
+        // Given
+        let cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+
+        // Then
+        assert_eq!(7, cpu.cycles);
+        assert_eq!((0, 21), cpu.ppu_position());
+    }
+
+    #[test]
+    fn ppu_position_wraps_scanlines_and_frames() {
+        // Given
+        let mut cpu = CPU::empty();
+
+        // When
+        cpu.cycles = 114;       // 342 dots
+
+        // Then
+        assert_eq!((1, 1), cpu.ppu_position());
+
+        cpu.cycles = 29781;     // 89343 dots, one past a full frame
+        assert_eq!((0, 1), cpu.ppu_position());
+    }
+
+    #[test]
+    fn instructions_and_interrupts_count_cycles() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+        cpu.bus.write_mem8(0x0200, 0xEA);   // NOP
+        cpu.program_counter = 0x0200;
+
+        // When
+        let nop = fetch(&mut cpu);
+        cpu.run_fetched(nop);
+        cpu.set_nmi(true);
+        cpu.poll_interrupts();
+
+        // Then
+        assert_eq!(7 + 2 + 7, cpu.cycles);
+    }
+
+    #[test]
+    fn page_cross_compares_base_and_effective_address() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x02;
+        cpu.index_register_y = 0x10;
+        cpu.write(&ZeroPage(0x40), 0xF8);
+        cpu.write(&ZeroPage(0x41), 0x03);
+
+        // Then
+        assert!(!cpu.page_crossed(&AbsoluteX(0x0300)));
+        assert!(cpu.page_crossed(&AbsoluteX(0x03FF)));
+        assert!(!cpu.page_crossed(&AbsoluteY(0x0400)));
+        assert!(cpu.page_crossed(&IndirectY(0x40)));
+        assert!(!cpu.page_crossed(&ZeroPageX(0xFF)));
+    }
+
+    #[test]
+    fn load_and_store_cycles() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x01;
+
+        // Then
+        assert_eq!(4, cpu.default_cycles(&AbsoluteX(0x0300)));
+        assert_eq!(5, cpu.default_cycles(&AbsoluteX(0x03FF)));
+        assert_eq!(5, cpu.store_cycles(&AbsoluteX(0x0300)));
+        assert_eq!(5, cpu.store_cycles(&AbsoluteX(0x03FF)));
+    }
+
+    #[test]
+    fn branch_cycles() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.program_counter = 0x0208;
+
+        // Then
+        assert_eq!(3, cpu.branch(0x10));
+        assert_eq!(0x0218, cpu.program_counter);
+        assert_eq!(4, cpu.branch(-0x20));
+        assert_eq!(0x01F8, cpu.program_counter);
+    }
+
+    #[test]
+    fn branch_wraps_address_space() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.program_counter = 0xFFF0;
+
+        // When
+        cpu.branch(0x20);
+
+        // Then
+        assert_eq!(0x0010, cpu.program_counter);
+    }
+
+    // Records every cartridge access, to observe dummy reads and writes
+    struct Recorder {
+        accesses: Rc<RefCell<Vec<(char, u16, u8)>>>,
+        ram: [u8; 0x2000]
+    }
+
+    impl Mapper for Recorder {
+        fn cpu_read(&self, addr: u16) -> u8 {
+            let value = if addr < 0x8000 { self.ram[addr as usize & 0x1FFF] } else { 0 };
+            self.accesses.borrow_mut().push(('R', addr, value));
+            value
+        }
+
+        fn cpu_write(&mut self, addr: u16, data: u8) {
+            self.accesses.borrow_mut().push(('W', addr, data));
+            self.ram[addr as usize & 0x1FFF] = data;
+        }
+
+        fn ppu_read(&mut self, _addr: u16) -> u8 { 0 }
+        fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+        fn mirroring(&self) -> Mirroring { Mirroring::Horizontal }
+    }
+
+    fn run_recorded(program: &[u8], setup: fn(&mut CPU)) -> Vec<(char, u16, u8)> {
+        let accesses = Rc::new(RefCell::new(Vec::new()));
+        let recorder = Recorder { accesses: accesses.clone(), ram: [0; 0x2000] };
+        let mut cpu = CPU::new(Box::new(NesBus::new(Box::new(recorder))));
+        cpu.dummy_accesses = true;
+        for (offset, byte) in program.iter().enumerate() {
+            cpu.bus.write_mem8(0x0200 + offset as u16, *byte);
+        }
+        cpu.program_counter = 0x0200;
+        setup(&mut cpu);
+
+        let instruction = fetch(&mut cpu);
+        accesses.borrow_mut().clear();
+        cpu.run_fetched(instruction);
+
+        let recorded = accesses.borrow().clone();
+        recorded
+    }
+
+    #[test]
+    fn load_dummy_read_only_on_page_cross() {
+        // LDA $60F0,X
+        assert_eq!(vec![('R', 0x6010, 0), ('R', 0x6110, 0)],
+                   run_recorded(&[0xBD, 0xF0, 0x60], |cpu| cpu.index_register_x = 0x20));
+        assert_eq!(vec![('R', 0x6020, 0)],
+                   run_recorded(&[0xBD, 0x00, 0x60], |cpu| cpu.index_register_x = 0x20));
+    }
+
+    #[test]
+    fn store_always_dummy_reads() {
+        // STA $6000,X
+        assert_eq!(vec![('R', 0x6001, 0), ('W', 0x6001, 0x42)],
+                   run_recorded(&[0x9D, 0x00, 0x60], |cpu| {
+                       cpu.index_register_x = 0x01;
+                       cpu.accumulator = 0x42;
+                   }));
+    }
+
+    #[test]
+    fn read_modify_write_writes_twice() {
+        // INC $6000
+        assert_eq!(vec![('R', 0x6000, 0), ('W', 0x6000, 0x00), ('W', 0x6000, 0x01)],
+                   run_recorded(&[0xEE, 0x00, 0x60], |_| {}));
+    }
+
+    // Like run_recorded, but on flat RAM so zero page and the opcode fetch show up too
+    fn run_on_recording_bus(program: &[u8], setup: fn(&mut CPU)) -> Vec<(char, u16, u8)> {
+        let mut ram = FlatRam::new();
+        ram.load(0x0200, program).unwrap();
+        let bus = RecordingBus::new(Box::new(ram));
+        let accesses = bus.accesses();
+        let mut cpu = CPU::new(Box::new(bus));
+        cpu.dummy_accesses = true;
+        cpu.program_counter = 0x0200;
+        setup(&mut cpu);
+        accesses.borrow_mut().clear();
+
+        cpu.step();
+
+        let recorded = accesses.borrow().iter()
+            .map(|a| (if a.write { 'W' } else { 'R' }, a.address, a.value))
+            .collect();
+        recorded
+    }
+
+    #[test]
+    fn indirect_y_reads_pointer_once() {
+        // LDA ($10),Y with $10 pointing at $02F0 and Y crossing into $0300
+        assert_eq!(vec![('R', 0x0200, 0xB1), ('R', 0x0201, 0x10), ('R', 0x0010, 0xF0), ('R', 0x0011, 0x02),
+                        ('R', 0x0200, 0xB1), ('R', 0x0300, 0x55)],
+                   run_on_recording_bus(&[0xB1, 0x10], |cpu| {
+                       cpu.bus.write_mem8(0x0010, 0xF0);
+                       cpu.bus.write_mem8(0x0011, 0x02);
+                       cpu.bus.write_mem8(0x0300, 0x55);
+                       cpu.index_register_y = 0x10;
+                   }));
+    }
+
+    #[test]
+    fn indirect_x_reads_pointer_once() {
+        // STA ($10,X) with X = 4 and $14 pointing at $0300
+        assert_eq!(vec![('R', 0x0200, 0x81), ('R', 0x0201, 0x10), ('R', 0x0010, 0x00), ('R', 0x0014, 0x00),
+                        ('R', 0x0015, 0x03), ('W', 0x0300, 0x55)],
+                   run_on_recording_bus(&[0x81, 0x10], |cpu| {
+                       cpu.bus.write_mem8(0x0014, 0x00);
+                       cpu.bus.write_mem8(0x0015, 0x03);
+                       cpu.index_register_x = 0x04;
+                       cpu.accumulator = 0x55;
+                   }));
+    }
+
+    fn decimal_cpu(variant: Variant, accumulator: u8, carry: bool) -> CPU {
+        let mut cpu = CPU::empty();
+        cpu.variant = variant;
+        cpu.accumulator = accumulator;
+        cpu.set_flag(StatusFlag::Decimal, true);
+        cpu.set_flag(StatusFlag::Carry, carry);
+        cpu
+    }
+
+    #[test]
+    fn ricoh_ignores_decimal_flag() {
+        // Given
+        let mut cpu = decimal_cpu(Variant::Ricoh2A03, 0x58, true);
+
+        // When
+        cpu.add_with_carry(0x46);
+
+        // Then
+        assert_eq!(0x9F, cpu.accumulator);
+    }
+
+    #[test]
+    fn decimal_add() {
+        // Given
+        let mut cpu = decimal_cpu(Variant::Nmos6502, 0x58, true);
+
+        // When
+        let cycles = cpu.add_with_carry(0x46);
+
+        // Then
+        assert_eq!(0x05, cpu.accumulator);
+        assert!(cpu.get_flag(StatusFlag::Carry));
+        assert_eq!(0, cycles);
+    }
+
+    #[test]
+    fn nmos_decimal_add_flags() {
+        // Given
+        let mut cpu = decimal_cpu(Variant::Nmos6502, 0x99, false);
+
+        // When
+        cpu.add_with_carry(0x01);
+
+        // Then
+        // Z comes from the binary sum and N from the sum before the high nibble is adjusted
+        assert_eq!(0x00, cpu.accumulator);
+        assert!(cpu.get_flag(StatusFlag::Carry));
+        assert!(!cpu.get_flag(StatusFlag::Zero));
+        assert!(cpu.get_flag(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn cmos_decimal_add_flags() {
+        // Given
+        let mut cpu = decimal_cpu(Variant::Cmos65C02, 0x99, false);
+
+        // When
+        let cycles = cpu.add_with_carry(0x01);
+
+        // Then
+        assert_eq!(0x00, cpu.accumulator);
+        assert!(cpu.get_flag(StatusFlag::Carry));
+        assert!(cpu.get_flag(StatusFlag::Zero));
+        assert!(!cpu.get_flag(StatusFlag::Negative));
+        assert_eq!(1, cycles);
+    }
+
+    #[test]
+    fn decimal_subtract() {
+        for &variant in [Variant::Nmos6502, Variant::Cmos65C02].iter() {
+            // Given
+            let mut cpu = decimal_cpu(variant, 0x40, true);
+
+            // When
+            cpu.subtract_with_borrow(0x13);
+
+            // Then
+            assert_eq!(0x27, cpu.accumulator, "{}", variant);
+            assert!(cpu.get_flag(StatusFlag::Carry), "{}", variant);
+        }
+    }
+
+    #[test]
+    fn decimal_subtract_borrows() {
+        for &variant in [Variant::Nmos6502, Variant::Cmos65C02].iter() {
+            // Given
+            let mut cpu = decimal_cpu(variant, 0x00, true);
+
+            // When
+            cpu.subtract_with_borrow(0x01);
+
+            // Then
+            assert_eq!(0x99, cpu.accumulator, "{}", variant);
+            assert!(!cpu.get_flag(StatusFlag::Carry), "{}", variant);
+        }
+    }
+
+    #[test]
+    fn cmos_interrupt_clears_decimal() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+        cpu.variant = Variant::Cmos65C02;
+        cpu.set_flag(StatusFlag::Decimal, true);
+
+        // When
+        cpu.interrupt(0xFFFE, true);
+
+        // Then
+        assert!(!cpu.get_flag(StatusFlag::Decimal));
+    }
+
+    #[test]
+    fn oam_dma_copies_page_and_stalls() {
+        // Given a page of RAM counting up, copied from OAM address $10
+        let mut cpu = CPU::empty();
+        for low in 0..=0xFF {
+            cpu.bus.write_mem8(0x0200 + low, low as u8);
+        }
+        cpu.bus.write_mem8(0x2003, 0x10);
+        let program = [0xA9, 0x02, 0x8D, 0x14, 0x40, 0xA5, 0x00, 0x8D, 0x14, 0x40];
+        for (offset, byte) in program.iter().enumerate() {
+            cpu.bus.write_mem8(0x0300 + offset as u16, *byte);
+        }
+        cpu.program_counter = 0x0300;
+
+        // When LDA #$02, STA $4014 finishes on an odd cycle
+        cpu.step();
+        cpu.step();
+
+        // Then
+        assert_eq!(7 + 2 + 4 + 514, cpu.cycles);
+        cpu.bus.write_mem8(0x2003, 0x11);
+        assert_eq!(0x01, cpu.bus.read_mem8(0x2004));
+        cpu.bus.write_mem8(0x2003, 0x0F);
+        assert_eq!(0xFF, cpu.bus.read_mem8(0x2004));
+
+        // When LDA $00, STA $4014 finishes on an even cycle
+        cpu.step();
+        cpu.step();
+
+        // Then
+        assert_eq!(527 + 3 + 4 + 513, cpu.cycles);
+    }
 }
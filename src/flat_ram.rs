@@ -0,0 +1,101 @@
+// 64KB of RAM with nothing mapped over it, for running plain 6502 binaries
+// rather than NES ROMs.
+use crate::bus::Bus;
+
+const MEMORY_SIZE: usize = 0x10000;
+
+pub struct FlatRam {
+    memory: Vec<u8>
+}
+
+impl FlatRam {
+    pub fn new() -> Self {
+        FlatRam { memory: vec![0; MEMORY_SIZE] }
+    }
+
+    // Copies data in starting at address, which must fit below $10000
+    pub fn load(&mut self, address: u16, data: &[u8]) -> Result<(), String> {
+        let start = address as usize;
+        let end = start + data.len();
+        if end > MEMORY_SIZE {
+            return Err(format!("{} bytes at ${:04X} runs past $FFFF", data.len(), address));
+        }
+
+        self.memory[start..end].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+impl Bus for FlatRam {
+    fn read_mem8(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+
+    fn write_mem8(&mut self, addr: u16, data: u8) {
+        self.memory[addr as usize] = data;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bus::Bus;
+    use crate::cpu::CPU;
+    use super::FlatRam;
+
+    #[test]
+    fn no_mirroring() {
+        // Given
+        let mut ram = FlatRam::new();
+
+        // When
+        ram.write_mem8(0x0000, 0x11);
+        ram.write_mem8(0x2000, 0x22);
+        ram.write_mem8(0xFFFF, 0x33);
+
+        // Then
+        assert_eq!(0x11, ram.read_mem8(0x0000));
+        assert_eq!(0x00, ram.read_mem8(0x0800));
+        assert_eq!(0x22, ram.read_mem8(0x2000));
+        assert_eq!(0x33, ram.read_mem8(0xFFFF));
+    }
+
+    #[test]
+    fn load_at_address() {
+        // Given
+        let mut ram = FlatRam::new();
+
+        // When
+        ram.load(0x0400, &[0xA9, 0x42]).unwrap();
+
+        // Then
+        assert_eq!(0xA9, ram.read_mem8(0x0400));
+        assert_eq!(0x42, ram.read_mem8(0x0401));
+    }
+
+    #[test]
+    fn load_past_end() {
+        // Given
+        let mut ram = FlatRam::new();
+
+        // Then
+        assert!(ram.load(0xFFFF, &[0x01, 0x02]).is_err());
+        assert!(ram.load(0xFFFE, &[0x01, 0x02]).is_ok());
+    }
+
+    #[test]
+    fn cpu_runs_from_reset_vector() {
+        // Given
+        let mut ram = FlatRam::new();
+        ram.load(0x0400, &[0xA9, 0x42, 0x8D, 0x00, 0x20]).unwrap();    // LDA #$42, STA $2000
+        ram.load(0xFFFC, &[0x00, 0x04]).unwrap();
+        let mut cpu = CPU::new(Box::new(ram));
+
+        // When
+        cpu.step();
+        cpu.step();
+
+        // Then
+        assert_eq!(0x42, cpu.accumulator);
+        assert_eq!(0x0405, cpu.program_counter);
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#ALR
+// AND followed by LSR A
+pub(super) struct ALR {
+    mode: AddressingMode
+}
+
+impl ALR {
+    pub fn new(mode: AddressingMode) -> Self {
+        ALR{ mode }
+    }
+}
+
+impl Display for ALR {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ALR {}", self.mode)
+    }
+}
+
+impl Instruction for ALR {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.accumulator & cpu.read(&self.mode);
+        cpu.accumulator = value >> 1;
+
+        cpu.set_flag(StatusFlag::Carry, value & 0x01 == 0x01);
+        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
+        cpu.set_flag(StatusFlag::Negative, false);
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![0x4B, val],
+            _ => panic!("Addressing mode not allowed for ALR")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::ALR;
+
+    #[test]
+    fn and_then_shift() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xFF;
+        cpu.processor_status = 0x80;
+
+        // When
+        ALR::new(Immediate(0x83)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x41, cpu.accumulator);
+        assert_eq!(0x01, cpu.processor_status);  // Carry from bit 0, negative cleared
+    }
+
+    #[test]
+    fn zero_result() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x02;
+
+        // When
+        ALR::new(Immediate(0x01)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x00, cpu.accumulator);
+        assert_eq!(0x02, cpu.processor_status);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#ANC
+// AND followed by copying the negative flag into carry.  Available as both
+// $0B and $2B, so the opcode is kept for the byte dump.
+pub(super) struct ANC {
+    opcode: u8,
+    mode: AddressingMode
+}
+
+impl ANC {
+    pub fn new(opcode: u8, mode: AddressingMode) -> Self {
+        ANC{ opcode, mode }
+    }
+}
+
+impl Display for ANC {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ANC {}", self.mode)
+    }
+}
+
+impl Instruction for ANC {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.accumulator &= cpu.read(&self.mode);
+
+        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
+        cpu.set_flag(StatusFlag::Carry, cpu.accumulator > 0x7F);
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![self.opcode, val],
+            _ => panic!("Addressing mode not allowed for ANC")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::ANC;
+
+    #[test]
+    fn negative_sets_carry() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xF0;
+
+        // When
+        ANC::new(0x0B, Immediate(0x81)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x80, cpu.accumulator);
+        assert_eq!(0x81, cpu.processor_status);  // Negative and carry
+    }
+
+    #[test]
+    fn positive_clears_carry() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x0F;
+        cpu.processor_status = 0x01;
+
+        // When
+        ANC::new(0x2B, Immediate(0xF0)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x00, cpu.accumulator);
+        assert_eq!(0x02, cpu.processor_status);  // Zero only
+    }
+
+    #[test]
+    fn bytes_keep_opcode() {
+        assert_eq!(vec![0x2B, 0x44], ANC::new(0x2B, Immediate(0x44)).bytes());
+    }
+}
@@ -20,7 +20,7 @@ impl Display for AND {
 
 impl Instruction for AND {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.accumulator &= cpu.read(&self.mode);
+        cpu.accumulator &= cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
         cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
@@ -38,12 +38,13 @@ impl Instruction for AND {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x39, addr),
             AddressingMode::IndirectX(addr) => vec![0x21, addr],
             AddressingMode::IndirectY(addr) => vec![0x31, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0x32, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for AND")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("AND {}", self.mode.debug_string(&cpu))
+        format!("AND {}", self.mode.debug_string(cpu))
     }
 }
 
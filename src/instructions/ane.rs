@@ -0,0 +1,83 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#ANE
+// Highly unstable:  A = (A OR magic) AND X AND operand, where the magic
+// constant depends on the chip and temperature.  See CPU::magic_constant.
+pub(super) struct ANE {
+    mode: AddressingMode
+}
+
+impl ANE {
+    pub fn new(mode: AddressingMode) -> Self {
+        ANE{ mode }
+    }
+}
+
+impl Display for ANE {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ANE {}", self.mode)
+    }
+}
+
+impl Instruction for ANE {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.accumulator = (cpu.accumulator | cpu.magic_constant)
+            & cpu.index_register_x
+            & cpu.read(&self.mode);
+
+        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![0x8B, val],
+            _ => panic!("Addressing mode not allowed for ANE")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::ANE;
+
+    #[test]
+    fn default_magic_constant() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x00;
+        cpu.index_register_x = 0xFF;
+
+        // When
+        ANE::new(Immediate(0xFF)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0xEE, cpu.accumulator);
+        assert_eq!(0x80, cpu.processor_status);
+    }
+
+    #[test]
+    fn configured_magic_constant() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.magic_constant = 0x00;
+        cpu.accumulator = 0x0F;
+        cpu.index_register_x = 0x3C;
+
+        // When
+        ANE::new(Immediate(0xFF)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x0C, cpu.accumulator);
+        assert_eq!(0x00, cpu.processor_status);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#ARR
+// AND followed by ROR A, but carry and overflow come from bits 6 and 5 of
+// the result rather than the shift.  In decimal mode the NMOS 6502 applies
+// a BCD fixup to the result instead, while the 2A03 has no decimal mode.
+pub(super) struct ARR {
+    mode: AddressingMode
+}
+
+impl ARR {
+    pub fn new(mode: AddressingMode) -> Self {
+        ARR{ mode }
+    }
+
+    // N and Z are still from the rotated value, V is bit 6 changing in the rotate, and
+    // each nibble of the rotated value is adjusted based on the nibble before rotating.
+    // See http://www.zimmers.net/anonftp/pub/cbm/documents/chipdata/64doc
+    fn decimal_fixup(cpu: &mut CPU, value: u8) {
+        cpu.set_flag(StatusFlag::Overflow, (value ^ cpu.accumulator) & 0x40 != 0);
+
+        let low = value & 0x0F;
+        if low + (low & 0x01) > 0x05 {
+            cpu.accumulator = (cpu.accumulator & 0xF0) | (cpu.accumulator.wrapping_add(0x06) & 0x0F);
+        }
+
+        let high = value >> 4;
+        let carry = high + (high & 0x01) > 0x05;
+        if carry {
+            cpu.accumulator = cpu.accumulator.wrapping_add(0x60);
+        }
+        cpu.set_flag(StatusFlag::Carry, carry);
+    }
+}
+
+impl Display for ARR {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ARR {}", self.mode)
+    }
+}
+
+impl Instruction for ARR {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.accumulator & cpu.read(&self.mode);
+        let carry_in = (cpu.processor_status & 0x01) << 7;
+        cpu.accumulator = (value >> 1) | carry_in;
+
+        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
+
+        if cpu.decimal_mode() {
+            ARR::decimal_fixup(cpu, value);
+        } else {
+            let bit6 = cpu.accumulator & 0x40 != 0;
+            let bit5 = cpu.accumulator & 0x20 != 0;
+            cpu.set_flag(StatusFlag::Carry, bit6);
+            cpu.set_flag(StatusFlag::Overflow, bit6 ^ bit5);
+        }
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![0x6B, val],
+            _ => panic!("Addressing mode not allowed for ARR")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction, StatusFlag, Variant};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::ARR;
+
+    #[test]
+    fn rotates_carry_in() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xFF;
+        cpu.processor_status = 0x01;
+
+        // When
+        ARR::new(Immediate(0xFF)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0xFF, cpu.accumulator);
+        assert_eq!(0x81, cpu.processor_status);  // Bits 6 and 5 set:  carry, no overflow
+    }
+
+    #[test]
+    fn overflow_from_bits_6_and_5() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xFF;
+
+        // When
+        ARR::new(Immediate(0x80)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x40, cpu.accumulator);
+        assert_eq!(0x41, cpu.processor_status);  // Carry and overflow
+    }
+
+    #[test]
+    fn zero_result() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x01;
+
+        // When
+        ARR::new(Immediate(0x01)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x00, cpu.accumulator);
+        assert_eq!(0x02, cpu.processor_status);
+    }
+
+    #[test]
+    fn nmos_decimal_fixup() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.variant = Variant::Nmos6502;
+        cpu.accumulator = 0xFF;
+        cpu.set_flag(StatusFlag::Decimal, true);
+
+        // When
+        ARR::new(Immediate(0x77)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x91, cpu.accumulator);      // $3B with both nibbles fixed up
+        assert_eq!(0x49, cpu.processor_status); // Decimal, overflow and carry
+    }
+
+    #[test]
+    fn ricoh_ignores_decimal_flag() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xFF;
+        cpu.set_flag(StatusFlag::Decimal, true);
+
+        // When
+        ARR::new(Immediate(0x77)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x3B, cpu.accumulator);
+        assert_eq!(0x48, cpu.processor_status); // Decimal and overflow
+    }
+}
@@ -20,10 +20,10 @@ impl Display for ASL {
 
 impl Instruction for ASL {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem_value = cpu.read(&self.mode);
+        let mem_value = cpu.read_modify(&self.mode);
         let shifted_value = mem_value << 1;
 
-        cpu.write(&self.mode, shifted_value);
+        cpu.write_modified(&self.mode, mem_value, shifted_value);
         cpu.set_flag(StatusFlag::Carry, mem_value > 0x7F);
         cpu.set_flag(StatusFlag::Zero, shifted_value == 0);
         cpu.set_flag(StatusFlag::Negative, shifted_value > 0x7F);
@@ -43,7 +43,7 @@ impl Instruction for ASL {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("ASL {}", self.mode.debug_string(&cpu))
+        format!("ASL {}", self.mode.debug_string(cpu))
     }
 }
 
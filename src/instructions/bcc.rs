@@ -21,8 +21,7 @@ impl Display for BCC {
 impl Instruction for BCC {
     fn execute(&self, cpu: &mut CPU) -> u8 {
         if !cpu.get_flag(StatusFlag::Carry) {
-            let new_pc = ((cpu.program_counter as i16) + (self.relative as i16)) as u16;
-            3 + cpu.set_pc(new_pc)
+            cpu.branch(self.relative)
         } else {
             2
         }
@@ -33,7 +32,7 @@ impl Instruction for BCC {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        let new_pc = ((cpu.program_counter as i16) + (self.relative as i16)) as u16;
+        let new_pc = cpu.branch_target(self.relative);
         format!("BCC ${:04X}", new_pc)
     }
 }
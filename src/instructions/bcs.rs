@@ -1,4 +1,4 @@
-use std::fmt::{Display, format, Formatter};
+use std::fmt::{Display, Formatter};
 use crate::cpu::{Instruction, CPU, StatusFlag};
 
 // http://www.obelisk.me.uk/6502/reference.html#BCS
@@ -21,8 +21,7 @@ impl Display for BCS {
 impl Instruction for BCS {
     fn execute(&self, cpu: &mut CPU) -> u8 {
         if cpu.get_flag(StatusFlag::Carry) {
-            let new_pc = ((cpu.program_counter as i16) + (self.relative as i16)) as u16;
-            3 + cpu.set_pc(new_pc)
+            cpu.branch(self.relative)
         } else {
             2
         }
@@ -33,7 +32,7 @@ impl Instruction for BCS {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        let new_pc = ((cpu.program_counter as i16) + (self.relative as i16)) as u16;
+        let new_pc = cpu.branch_target(self.relative);
         format!("BCS ${:04X}", new_pc)
     }
 }
@@ -20,11 +20,15 @@ impl Display for BIT {
 
 impl Instruction for BIT {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let operand = cpu.read(&self.mode);
+        let operand = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator & operand == 0);
-        cpu.set_flag(StatusFlag::Overflow, operand & 0x40 == 0x40);  // if 6th bit is set
-        cpu.set_flag(StatusFlag::Negative, operand > 0x7F);          // if 7th bit is set
+
+        // The 65C02's BIT #imm only touches Z
+        if !matches!(self.mode, AddressingMode::Immediate(_)) {
+            cpu.set_flag(StatusFlag::Overflow, operand & 0x40 == 0x40);  // if 6th bit is set
+            cpu.set_flag(StatusFlag::Negative, operand > 0x7F);          // if 7th bit is set
+        }
 
         cpu.default_cycles(&self.mode)
     }
@@ -33,18 +37,23 @@ impl Instruction for BIT {
         match self.mode {
             AddressingMode::ZeroPage(addr) => vec![0x24, addr],
             AddressingMode::Absolute(addr) => self.bytes_for_opcode(0x2C, addr),
+            // 65C02 only
+            AddressingMode::Immediate(val) => vec![0x89, val],
+            AddressingMode::ZeroPageX(addr) => vec![0x34, addr],
+            AddressingMode::AbsoluteX(addr) => self.bytes_for_opcode(0x3C, addr),
             _ => panic!("Addressing Mode not allowed for BIT!")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("BIT {}", self.mode.debug_string(&cpu))
+        format!("BIT {}", self.mode.debug_string(cpu))
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::cpu::{CPU, Instruction, AddressingMode::ZeroPage, AddressingMode};
+    use crate::cpu::AddressingMode::Immediate;
     use super::BIT;
 
     #[test]
@@ -96,4 +105,18 @@ mod test {
         // Then
         assert_eq!(vec![0x2C, 0x0A, 0xDC], bit.bytes());
     }
+
+    #[test]
+    fn immediate_only_sets_zero() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x0F;
+        cpu.processor_status = 0b11000000;       // overflow and negative set
+
+        // When
+        BIT::new(Immediate(0x30)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0b11000010, cpu.processor_status);
+    }
 }
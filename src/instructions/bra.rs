@@ -0,0 +1,60 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct BRA {
+    relative: i8
+}
+
+impl BRA {
+    pub fn new(relative: i8) -> Self {
+        BRA{ relative }
+    }
+}
+
+impl Display for BRA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BRA ${:02X}", self.relative)
+    }
+}
+
+impl Instruction for BRA {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.branch(self.relative)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0x80, self.relative as u8]
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        let new_pc = cpu.branch_target(self.relative);
+        format!("BRA ${:04X}", new_pc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use super::BRA;
+
+    #[test]
+    fn always_branches() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.program_counter = 0x0210;
+
+        // When
+        let cycles = BRA::new(-0x08).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x0208, cpu.program_counter);
+        assert_eq!(3, cycles);
+    }
+
+    #[test]
+    fn bytes_representation() {
+        assert_eq!(vec![0x80, 0xF8], BRA::new(-0x08).bytes());
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU};
+
+// http://www.obelisk.me.uk/6502/reference.html#BRK
+// BRK is a 2 byte instruction:  the byte after the opcode is skipped, so the
+// return address pushed is PC + 2.
+// See http://wiki.nesdev.com/w/index.php/CPU_interrupts#BRK
+pub(super) struct BRK {}
+
+impl Display for BRK {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BRK")
+    }
+}
+
+impl Instruction for BRK {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        // The opcode has already been consumed, skip the padding byte
+        cpu.program_counter = cpu.program_counter.wrapping_add(1);
+
+        cpu.interrupt(0xFFFE, true)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0x00]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use super::BRK;
+
+    #[test]
+    fn pushes_return_address_and_status() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.program_counter = 0x0401;   // Just past the opcode at $0400
+        cpu.stack_pointer = 0xFD;
+        cpu.processor_status = 0x03;
+
+        // When
+        let cycles = BRK{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(7, cycles);
+        assert_eq!(0xFA, cpu.stack_pointer);
+        assert_eq!(0x33, cpu.pop_stack());      // B and bit 5 set on the stack copy
+        assert_eq!(0x02, cpu.pop_stack());
+        assert_eq!(0x04, cpu.pop_stack());
+        assert_eq!(0x07, cpu.processor_status); // Interrupts now disabled
+    }
+
+    #[test]
+    fn jumps_through_irq_vector() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+        cpu.program_counter = 0x0401;
+
+        // When
+        BRK{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0xA000, cpu.program_counter);
+    }
+
+    #[test]
+    fn nmi_hijacks_vector() {
+        // Given
+        let mut cpu = CPU::with_vectors(0x9000, 0x8000, 0xA000);
+        cpu.program_counter = 0x0401;
+        cpu.set_nmi(true);
+
+        // When
+        BRK{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x9000, cpu.program_counter);
+        assert_eq!(0, cpu.poll_interrupts());   // NMI was consumed
+    }
+}
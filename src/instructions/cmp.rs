@@ -20,7 +20,7 @@ impl Display for CMP {
 
 impl Instruction for CMP {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem = cpu.read(&self.mode);
+        let mem = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Carry, cpu.accumulator >= mem);
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator == mem);
@@ -39,13 +39,14 @@ impl Instruction for CMP {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0xD9, addr),
             AddressingMode::IndirectX(addr) => vec![0xC1, addr],
             AddressingMode::IndirectY(addr) => vec![0xD1, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0xD2, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for CMP")
         }
 
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("CMP {}", self.mode.debug_string(&cpu))
+        format!("CMP {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -104,7 +105,7 @@ mod test {
         cpu.processor_status = 0xE4;
 
         // When
-        CMP::new(Immediate(0xFF)).execute((&mut cpu));
+        CMP::new(Immediate(0xFF)).execute(&mut cpu);
 
         // Then
         assert_eq!(0x67, cpu.processor_status);
@@ -118,7 +119,7 @@ mod test {
         cpu.processor_status = 0x25;
 
         // When
-        CMP::new(Immediate(0x41)).execute((&mut cpu));
+        CMP::new(Immediate(0x41)).execute(&mut cpu);
 
         // Then
         assert_eq!(0x40, cpu.accumulator);
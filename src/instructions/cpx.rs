@@ -20,7 +20,7 @@ impl Display for CPX {
 
 impl Instruction for CPX {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem = cpu.read(&self.mode);
+        let mem = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Carry, cpu.index_register_x >= mem);
         cpu.set_flag(StatusFlag::Zero, cpu.index_register_x == mem);
@@ -39,7 +39,7 @@ impl Instruction for CPX {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("CPX {}", self.mode.debug_string(&cpu))
+        format!("CPX {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -20,7 +20,7 @@ impl Display for CPY {
 
 impl Instruction for CPY {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem = cpu.read(&self.mode);
+        let mem = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Carry, cpu.index_register_y >= mem);
         cpu.set_flag(StatusFlag::Zero, cpu.index_register_y == mem);
@@ -39,7 +39,7 @@ impl Instruction for CPY {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("CPY {}", self.mode.debug_string(&cpu))
+        format!("CPY {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -20,8 +20,9 @@ impl Display for DCP {
 
 impl Instruction for DCP {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let (val, _) = cpu.read(&self.mode).overflowing_sub(1);
-        cpu.write(&self.mode, val);
+        let original = cpu.read_modify(&self.mode);
+        let (val, _) = original.overflowing_sub(1);
+        cpu.write_modified(&self.mode, original, val);
 
         cpu.set_flag(StatusFlag::Carry, cpu.accumulator >= val);
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator == val);
@@ -44,7 +45,7 @@ impl Instruction for DCP {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("DCP {}", self.mode.debug_string(&cpu))
+        format!("DCP {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
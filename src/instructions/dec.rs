@@ -20,8 +20,9 @@ impl Display for DEC {
 
 impl Instruction for DEC {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let (val, _) = cpu.read(&self.mode).overflowing_sub(1);
-        cpu.write(&self.mode, val);
+        let original = cpu.read_modify(&self.mode);
+        let (val, _) = original.overflowing_sub(1);
+        cpu.write_modified(&self.mode, original, val);
 
         cpu.set_flag(StatusFlag::Zero,  val == 0);
         cpu.set_flag(StatusFlag::Negative, val > 0x7F);
@@ -31,6 +32,7 @@ impl Instruction for DEC {
 
     fn bytes(&self) -> Vec<u8> {
         match self.mode {
+            AddressingMode::Accumulator => vec![0x3A],   // 65C02 only
             AddressingMode::ZeroPage(addr) => vec![0xC6, addr],
             AddressingMode::ZeroPageX(addr) => vec![0xD6, addr],
             AddressingMode::Absolute(addr) => self.bytes_for_opcode(0xCE, addr),
@@ -40,7 +42,7 @@ impl Instruction for DEC {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("DEC {}", self.mode.debug_string(&cpu))
+        format!("DEC {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -20,7 +20,7 @@ impl Display for EOR {
 
 impl Instruction for EOR {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let memory_value= cpu.read(&self.mode);
+        let memory_value= cpu.load(&self.mode);
         cpu.accumulator ^= memory_value;
 
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
@@ -39,12 +39,13 @@ impl Instruction for EOR {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x59, addr),
             AddressingMode::IndirectX(addr) => vec![0x41, addr],
             AddressingMode::IndirectY(addr) => vec![0x51, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0x52, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for EOR")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("EOR {}", self.mode.debug_string(&cpu))
+        format!("EOR {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -1,6 +1,5 @@
-use std::fmt::{Display, Formatter};
-use crate::cpu::AddressingMode::{Accumulator, Immediate, ZeroPage, ZeroPageX, IndirectX, IndirectY, Absolute, AbsoluteX, AbsoluteY, ZeroPageY};
-use crate::cpu::{CPU, Instruction};
+use crate::cpu::AddressingMode::{Accumulator, Immediate, ZeroPage, ZeroPageX, IndirectX, IndirectY, Absolute, AbsoluteX, AbsoluteY, ZeroPageY, ZeroPageIndirect};
+use crate::cpu::{CPU, Instruction, Variant};
 use crate::instructions::adc::ADC;
 use crate::instructions::and::AND;
 use crate::instructions::asl::ASL;
@@ -10,6 +9,7 @@ use crate::instructions::beq::BEQ;
 use crate::instructions::bit::BIT;
 use crate::instructions::bmi::BMI;
 use crate::instructions::bne::BNE;
+use crate::instructions::brk::BRK;
 use crate::instructions::bpl::BPL;
 use crate::instructions::bvc::BVC;
 use crate::instructions::bvs::BVS;
@@ -36,7 +36,7 @@ use crate::instructions::lda::LDA;
 use crate::instructions::ldx::LDX;
 use crate::instructions::ldy::LDY;
 use crate::instructions::lsr::LSR;
-use crate::instructions::nop::{NOP, IllegalNOP};
+use crate::instructions::nop::{NOP, IllegalNOP, ReservedNOP};
 use crate::instructions::ora::ORA;
 use crate::instructions::pha::PHA;
 use crate::instructions::php::PHP;
@@ -64,274 +64,632 @@ use crate::instructions::tsx::TSX;
 use crate::instructions::txa::TXA;
 use crate::instructions::txs::TXS;
 use crate::instructions::tya::TYA;
+use crate::instructions::alr::ALR;
+use crate::instructions::anc::ANC;
+use crate::instructions::ane::ANE;
+use crate::instructions::arr::ARR;
+use crate::instructions::jam::JAM;
+use crate::instructions::las::LAS;
+use crate::instructions::lxa::LXA;
+use crate::instructions::sbx::SBX;
+use crate::instructions::sha::SHA;
+use crate::instructions::shx::SHX;
+use crate::instructions::shy::SHY;
+use crate::instructions::tas::TAS;
+use crate::instructions::bra::BRA;
+use crate::instructions::phx::PHX;
+use crate::instructions::phy::PHY;
+use crate::instructions::plx::PLX;
+use crate::instructions::ply::PLY;
+use crate::instructions::stz::STZ;
+use crate::instructions::trb::TRB;
+use crate::instructions::tsb::TSB;
 
 
-pub fn generate_instruction(cpu: &mut CPU) -> Option<Box<dyn Instruction>> {
-    let opcode = cpu.read(&Absolute(cpu.program_counter));
-    let inst_size = instruction_size(opcode);
-
-    let instruction = match inst_size {
-        1 => generate_1byte_instruction(opcode),
-        2 => generate_2byte_instruction(opcode, cpu.read(&Absolute(cpu.program_counter + 1))),
-        3 => generate_3byte_instruction(opcode, cpu.read_mem16(cpu.program_counter + 1)),
-        _ => panic!("Invalid instruction size!")
-    };
+// Every opcode is described once, in OPCODES.  Running or tracing an instruction calls
+// one of the entry's functions, which build the instruction on the stack, so neither
+// allocates an instruction.  `decode` boxes the same instruction for disassembly,
+// and for the benchmark to compare against.
+// See https://www.masswerk.at/6502/6502_instruction_set.html
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub operand: Operand,
+    pub cycles: u8,     // Base cycles, before page cross, branch and decimal penalties
+    pub execute: fn(&mut CPU, u16) -> u8,
+    pub bytes: fn(u16) -> Vec<u8>,
+    pub debug_string: fn(&CPU, u16) -> String,
+    pub illegal: fn(u16) -> bool,
+    pub decode: fn(u16) -> Box<dyn Instruction>
+}
 
-    cpu.program_counter += inst_size as u16;
+impl Opcode {
+    pub fn size(&self) -> u8 {
+        self.operand.size()
+    }
+}
 
-    return Some(instruction);
+// The operand an opcode takes, without its value
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operand {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    ZeroPageIndirect,
+    AbsoluteIndirectX,
+    Relative
 }
 
-// See https://www.masswerk.at/6502/6502_instruction_set.html#layout
-// for the table explaining this implementation
-fn instruction_size(opcode: u8) -> u8 {
-    let a = (opcode >> 5) & 0x07;
-    let b = (opcode >> 2) & 0x07;
-    let c = opcode & 0x03;
-
-    match (a,b,c) {
-        (_, 1, _) => 2,
-        (_, 3, _) => 3,
-        (_, 4, _) => 2,
-        (_, 5, _) => 2,
-        (_, 7, _) => 3,
-        (0, 0, 0) => 1,
-        (1, 0, 0) => 3,
-        (2, 0, 0) => 1,
-        (3, 0, 0) => 1,
-        (_, 0, _) => 2,
-        (_, 2, 1) => 2,
-        (_, 2, 3) => 2,
-        (_, 2, _) => 1,
-        (_, 6, 1) => 3,
-        (_, 6, 3) => 3,
-        (_, 6, _) => 1,
-        _ => panic!("Error finding size for opcode: {:02X}", opcode)
+impl Operand {
+    pub fn size(&self) -> u8 {
+        match self {
+            Operand::Implied | Operand::Accumulator => 1,
+            Operand::Absolute | Operand::AbsoluteX | Operand::AbsoluteY |
+            Operand::Indirect | Operand::AbsoluteIndirectX => 3,
+            _ => 2
+        }
     }
 }
 
-fn generate_1byte_instruction(opcode: u8) -> Box<dyn Instruction> {
-    match opcode {
-        0x0A => Box::new(ASL::new(Accumulator)),
-        0x18 => Box::new(CLC{}),
-        0x38 => Box::new(SEC{}),
-        0x58 => Box::new(CLI{}),
-        0x78 => Box::new(SEI{}),
-        0xB8 => Box::new(CLV{}),
-        0xD8 => Box::new(CLD{}),
-        0xF8 => Box::new(SED{}),
-        0x4A => Box::new(LSR::new(Accumulator)),
-        0xEA => Box::new(NOP{}),
-        0xAA => Box::new(TAX{}),
-        0x8A => Box::new(TXA{}),
-        0xCA => Box::new(DEX{}),
-        0xE8 => Box::new(INX{}),
-        0xA8 => Box::new(TAY{}),
-        0x98 => Box::new(TYA{}),
-        0x88 => Box::new(DEY{}),
-        0xC8 => Box::new(INY{}),
-        0x2A => Box::new(ROL::new(Accumulator)),
-        0x6A => Box::new(ROR::new(Accumulator)),
-        0x40 => Box::new(RTI{}),
-        0x60 => Box::new(RTS{}),
-        0x9A => Box::new(TXS{}),
-        0xBA => Box::new(TSX{}),
-        0x48 => Box::new(PHA{}),
-        0x68 => Box::new(PLA{}),
-        0x08 => Box::new(PHP{}),
-        0x28 => Box::new(PLP{}),
-        _ => Box::new(IllegalNOP::new(opcode, None))
+// The table entry the given variant runs for an opcode
+pub fn lookup(variant: Variant, opcode: u8) -> &'static Opcode {
+    match variant {
+        Variant::Cmos65C02 => CMOS_OPCODES[opcode as usize].as_ref()
+            .unwrap_or(&OPCODES[opcode as usize]),
+        _ => &OPCODES[opcode as usize]
     }
 }
 
-fn generate_2byte_instruction(opcode: u8, arg: u8) -> Box<dyn Instruction> {
-    match opcode {
-        0x69 => Box::new(ADC::new(Immediate(arg))),
-        0x65 => Box::new(ADC::new(ZeroPage(arg))),
-        0x75 => Box::new(ADC::new(ZeroPageX(arg))),
-        0x61 => Box::new(ADC::new(IndirectX(arg))),
-        0x71 => Box::new(ADC::new(IndirectY(arg))),
-        0x29 => Box::new(AND::new(Immediate(arg))),
-        0x25 => Box::new(AND::new(ZeroPage(arg))),
-        0x35 => Box::new(AND::new(ZeroPageX(arg))),
-        0x21 => Box::new(AND::new(IndirectX(arg))),
-        0x31 => Box::new(AND::new(IndirectY(arg))),
-        0x06 => Box::new(ASL::new(ZeroPage(arg))),
-        0x16 => Box::new(ASL::new(ZeroPageX(arg))),
-        0x24 => Box::new(BIT::new(ZeroPage(arg))),
-        0x10 => Box::new(BPL::new(arg as i8)),
-        0x30 => Box::new(BMI::new(arg as i8)),
-        0x50 => Box::new(BVC::new(arg as i8)),
-        0x70 => Box::new(BVS::new(arg as i8)),
-        0x90 => Box::new(BCC::new(arg as i8)),
-        0xB0 => Box::new(BCS::new(arg as i8)),
-        0xD0 => Box::new(BNE::new(arg as i8)),
-        0xF0 => Box::new(BEQ::new(arg as i8)),
-        0xC9 => Box::new(CMP::new(Immediate(arg))),
-        0xC5 => Box::new(CMP::new(ZeroPage(arg))),
-        0xD5 => Box::new(CMP::new(ZeroPageX(arg))),
-        0xC1 => Box::new(CMP::new(IndirectX(arg))),
-        0xD1 => Box::new(CMP::new(IndirectY(arg))),
-        0xE0 => Box::new(CPX::new(Immediate(arg))),
-        0xE4 => Box::new(CPX::new(ZeroPage(arg))),
-        0xC0 => Box::new(CPY::new(Immediate(arg))),
-        0xC4 => Box::new(CPY::new(ZeroPage(arg))),
-        0xC6 => Box::new(DEC::new(ZeroPage(arg))),
-        0xD6 => Box::new(DEC::new(ZeroPageX(arg))),
-        0x49 => Box::new(EOR::new(Immediate(arg))),
-        0x45 => Box::new(EOR::new(ZeroPage(arg))),
-        0x55 => Box::new(EOR::new(ZeroPageX(arg))),
-        0x41 => Box::new(EOR::new(IndirectX(arg))),
-        0x51 => Box::new(EOR::new(IndirectY(arg))),
-        0xE6 => Box::new(INC::new(ZeroPage(arg))),
-        0xF6 => Box::new(INC::new(ZeroPageX(arg))),
-        0xA9 => Box::new(LDA::new(Immediate(arg))),
-        0xA5 => Box::new(LDA::new(ZeroPage(arg))),
-        0xB5 => Box::new(LDA::new(ZeroPageX(arg))),
-        0xA1 => Box::new(LDA::new(IndirectX(arg))),
-        0xB1 => Box::new(LDA::new(IndirectY(arg))),
-        0xA2 => Box::new(LDX::new(Immediate(arg))),
-        0xA6 => Box::new(LDX::new(ZeroPage(arg))),
-        0xB6 => Box::new(LDX::new(ZeroPageY(arg))),
-        0xA0 => Box::new(LDY::new(Immediate(arg))),
-        0xA4 => Box::new(LDY::new(ZeroPage(arg))),
-        0xB4 => Box::new(LDY::new(ZeroPageX(arg))),
-        0x46 => Box::new(LSR::new(ZeroPage(arg))),
-        0x56 => Box::new(LSR::new(ZeroPageX(arg))),
-        0x09 => Box::new(ORA::new(Immediate(arg))),
-        0x05 => Box::new(ORA::new(ZeroPage(arg))),
-        0x15 => Box::new(ORA::new(ZeroPageX(arg))),
-        0x01 => Box::new(ORA::new(IndirectX(arg))),
-        0x11 => Box::new(ORA::new(IndirectY(arg))),
-        0x26 => Box::new(ROL::new(ZeroPage(arg))),
-        0x36 => Box::new(ROL::new(ZeroPageX(arg))),
-        0x66 => Box::new(ROR::new(ZeroPage(arg))),
-        0x76 => Box::new(ROR::new(ZeroPageX(arg))),
-        0xE9 => Box::new(SBC::new(Immediate(arg))),
-        0xE5 => Box::new(SBC::new(ZeroPage(arg))),
-        0xF5 => Box::new(SBC::new(ZeroPageX(arg))),
-        0xE1 => Box::new(SBC::new(IndirectX(arg))),
-        0xF1 => Box::new(SBC::new(IndirectY(arg))),
-        0xEB => Box::new(SBC::new_illegal(Immediate(arg))),
-        0x85 => Box::new(STA::new(ZeroPage(arg))),
-        0x95 => Box::new(STA::new(ZeroPageX(arg))),
-        0x81 => Box::new(STA::new(IndirectX(arg))),
-        0x91 => Box::new(STA::new(IndirectY(arg))),
-        0x86 => Box::new(STX::new(ZeroPage(arg))),
-        0x96 => Box::new(STX::new(ZeroPageY(arg))),
-        0x84 => Box::new(STY::new(ZeroPage(arg))),
-        0x94 => Box::new(STY::new(ZeroPageX(arg))),
-        0xA7 => Box::new(LAX::new(ZeroPage(arg))),
-        0xB7 => Box::new(LAX::new(ZeroPageY(arg))),
-        0xA3 => Box::new(LAX::new(IndirectX(arg))),
-        0xB3 => Box::new(LAX::new(IndirectY(arg))),
-        0x87 => Box::new(SAX::new(ZeroPage(arg))),
-        0x97 => Box::new(SAX::new(ZeroPageY(arg))),
-        0x83 => Box::new(SAX::new(IndirectX(arg))),
-        0xC7 => Box::new(DCP::new(ZeroPage(arg))),
-        0xD7 => Box::new(DCP::new(ZeroPageX(arg))),
-        0xC3 => Box::new(DCP::new(IndirectX(arg))),
-        0xD3 => Box::new(DCP::new(IndirectY(arg))),
-        0xE7 => Box::new(ISC::new(ZeroPage(arg))),
-        0xF7 => Box::new(ISC::new(ZeroPageX(arg))),
-        0xE3 => Box::new(ISC::new(IndirectX(arg))),
-        0xF3 => Box::new(ISC::new(IndirectY(arg))),
-        0x07 => Box::new(SLO::new(ZeroPage(arg))),
-        0x17 => Box::new(SLO::new(ZeroPageX(arg))),
-        0x03 => Box::new(SLO::new(IndirectX(arg))),
-        0x13 => Box::new(SLO::new(IndirectY(arg))),
-        0x27 => Box::new(RLA::new(ZeroPage(arg))),
-        0x37 => Box::new(RLA::new(ZeroPageX(arg))),
-        0x23 => Box::new(RLA::new(IndirectX(arg))),
-        0x33 => Box::new(RLA::new(IndirectY(arg))),
-        0x47 => Box::new(SRE::new(ZeroPage(arg))),
-        0x57 => Box::new(SRE::new(ZeroPageX(arg))),
-        0x43 => Box::new(SRE::new(IndirectX(arg))),
-        0x53 => Box::new(SRE::new(IndirectY(arg))),
-        0x67 => Box::new(RRA::new(ZeroPage(arg))),
-        0x77 => Box::new(RRA::new(ZeroPageX(arg))),
-        0x63 => Box::new(RRA::new(IndirectX(arg))),
-        0x73 => Box::new(RRA::new(IndirectY(arg))),
-        0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 => Box::new(IllegalNOP::new(opcode, Some(Immediate(arg)))),
-        0x04 | 0x44 | 0x64 => Box::new(IllegalNOP::new(opcode, Some(ZeroPage(arg)))),
-        0x14 | 0x34 | 0x54 | 0x74 | 0xD4 | 0xF4 => Box::new(IllegalNOP::new(opcode, Some(ZeroPageX(arg)))),
-        _ => panic!("Unknown opcode:  {:02X}", opcode)
-    }
+// Reads the opcode and its operand at PC and moves PC past them
+pub fn fetch(cpu: &mut CPU) -> (u8, u16) {
+    let pc = cpu.program_counter;
+    let opcode = cpu.read(&Absolute(pc));
+    let size = lookup(cpu.variant, opcode).size();
+
+    let arg = match size {
+        1 => 0,
+        2 => cpu.read(&Absolute(pc.wrapping_add(1))) as u16,
+        _ => cpu.read_mem16(pc.wrapping_add(1))
+    };
+    cpu.program_counter = pc.wrapping_add(size as u16);
+
+    (opcode, arg)
 }
 
-fn generate_3byte_instruction(opcode: u8, arg: u16) -> Box<dyn Instruction> {
-    match opcode {
-        0x6D => Box::new(ADC::new(Absolute(arg))),
-        0x7D => Box::new(ADC::new(AbsoluteX(arg))),
-        0x79 => Box::new(ADC::new(AbsoluteY(arg))),
-        0x2D => Box::new(AND::new(Absolute(arg))),
-        0x3D => Box::new(AND::new(AbsoluteX(arg))),
-        0x39 => Box::new(AND::new(AbsoluteY(arg))),
-        0x0E => Box::new(ASL::new(Absolute(arg))),
-        0x1E => Box::new(ASL::new(AbsoluteX(arg))),
-        0x2C => Box::new(BIT::new(Absolute(arg))),
-        0xCD => Box::new(CMP::new(Absolute(arg))),
-        0xDD => Box::new(CMP::new(AbsoluteX(arg))),
-        0xD9 => Box::new(CMP::new(AbsoluteY(arg))),
-        0xEC => Box::new(CPX::new(Absolute(arg))),
-        0xCC => Box::new(CPY::new(Absolute(arg))),
-        0xCE => Box::new(DEC::new(Absolute(arg))),
-        0xDE => Box::new(DEC::new(AbsoluteX(arg))),
-        0x4D => Box::new(EOR::new(Absolute(arg))),
-        0x5D => Box::new(EOR::new(AbsoluteX(arg))),
-        0x59 => Box::new(EOR::new(AbsoluteY(arg))),
-        0xEE => Box::new(INC::new(Absolute(arg))),
-        0xFE => Box::new(INC::new(AbsoluteX(arg))),
-        0x4C => Box::new(JMP::new(JumpAddressMode::Absolute(arg))),
-        0x6C => Box::new(JMP::new(JumpAddressMode::Indirect(arg))),
-        0x20 => Box::new(JSR::new(arg)),
-        0xAD => Box::new(LDA::new(Absolute(arg))),
-        0xBD => Box::new(LDA::new(AbsoluteX(arg))),
-        0xB9 => Box::new(LDA::new(AbsoluteY(arg))),
-        0xAE => Box::new(LDX::new(Absolute(arg))),
-        0xBE => Box::new(LDX::new(AbsoluteY(arg))),
-        0xAC => Box::new(LDY::new(Absolute(arg))),
-        0xBC => Box::new(LDY::new(AbsoluteX(arg))),
-        0x4E => Box::new(LSR::new(Absolute(arg))),
-        0x5E => Box::new(LSR::new(AbsoluteX(arg))),
-        0x0D => Box::new(ORA::new(Absolute(arg))),
-        0x1D => Box::new(ORA::new(AbsoluteX(arg))),
-        0x19 => Box::new(ORA::new(AbsoluteY(arg))),
-        0x2E => Box::new(ROL::new(Absolute(arg))),
-        0x3E => Box::new(ROL::new(AbsoluteX(arg))),
-        0x6E => Box::new(ROR::new(Absolute(arg))),
-        0x7E => Box::new(ROR::new(AbsoluteX(arg))),
-        0xED => Box::new(SBC::new(Absolute(arg))),
-        0xFD => Box::new(SBC::new(AbsoluteX(arg))),
-        0xF9 => Box::new(SBC::new(AbsoluteY(arg))),
-        0x8D => Box::new(STA::new(Absolute(arg))),
-        0x9D => Box::new(STA::new(AbsoluteX(arg))),
-        0x99 => Box::new(STA::new(AbsoluteY(arg))),
-        0x8E => Box::new(STX::new(Absolute(arg))),
-        0x8C => Box::new(STY::new(Absolute(arg))),
-        0xAF => Box::new(LAX::new(Absolute(arg))),
-        0xBF => Box::new(LAX::new(AbsoluteY(arg))),
-        0x8F => Box::new(SAX::new(Absolute(arg))),
-        0xCF => Box::new(DCP::new(Absolute(arg))),
-        0xDF => Box::new(DCP::new(AbsoluteX(arg))),
-        0xDB => Box::new(DCP::new(AbsoluteY(arg))),
-        0xEF => Box::new(ISC::new(Absolute(arg))),
-        0xFF => Box::new(ISC::new(AbsoluteX(arg))),
-        0xFB => Box::new(ISC::new(AbsoluteY(arg))),
-        0x0F => Box::new(SLO::new(Absolute(arg))),
-        0x1F => Box::new(SLO::new(AbsoluteX(arg))),
-        0x1B => Box::new(SLO::new(AbsoluteY(arg))),
-        0x2F => Box::new(RLA::new(Absolute(arg))),
-        0x3F => Box::new(RLA::new(AbsoluteX(arg))),
-        0x3B => Box::new(RLA::new(AbsoluteY(arg))),
-        0x4F => Box::new(SRE::new(Absolute(arg))),
-        0x5F => Box::new(SRE::new(AbsoluteX(arg))),
-        0x5B => Box::new(SRE::new(AbsoluteY(arg))),
-        0x6F => Box::new(RRA::new(Absolute(arg))),
-        0x7F => Box::new(RRA::new(AbsoluteX(arg))),
-        0x7B => Box::new(RRA::new(AbsoluteY(arg))),
-        0x0C => Box::new(IllegalNOP::new(0x0C, Some(Absolute(arg)))),
-        0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => Box::new(IllegalNOP::new(opcode, Some(AbsoluteX(arg)))),
-        _ => panic!("Unknown opcode: {:02X}", opcode)
+// Decodes raw instruction bytes, e.g. from a binary trace
+pub fn disassemble(variant: Variant, bytes: &[u8]) -> Box<dyn Instruction> {
+    let arg = match bytes.len() {
+        2 => bytes[1] as u16,
+        3 => u16::from_le_bytes([bytes[1], bytes[2]]),
+        _ => 0
+    };
+
+    (lookup(variant, bytes[0]).decode)(arg)
+}
+
+// The instruction expression is written once and used for every function pointer
+macro_rules! op {
+    ($mnemonic:expr, $operand:ident, $cycles:expr, |$arg:pat| $instruction:expr) => {
+        Opcode {
+            mnemonic: $mnemonic,
+            operand: Operand::$operand,
+            cycles: $cycles,
+            execute: |cpu, $arg| $instruction.execute(cpu),
+            bytes: |$arg| $instruction.bytes(),
+            debug_string: |cpu, $arg| $instruction.debug_string(cpu),
+            illegal: |$arg| $instruction.illegal(),
+            decode: |$arg| Box::new($instruction)
+        }
+    };
+}
+
+// Cycle counts from https://www.masswerk.at/6502/6502_instruction_set.html
+// JAM counts the two cycles before the CPU locks up.
+pub static OPCODES: [Opcode; 256] = [
+    /* 00 */ op!("BRK", Implied, 7, |_| BRK{}),
+    /* 01 */ op!("ORA", IndirectX, 6, |arg| ORA::new(IndirectX(arg as u8))),
+    /* 02 */ op!("JAM", Implied, 2, |_| JAM::new(0x02)),
+    /* 03 */ op!("SLO", IndirectX, 8, |arg| SLO::new(IndirectX(arg as u8))),
+    /* 04 */ op!("NOP", ZeroPage, 3, |arg| IllegalNOP::new(0x04, Some(ZeroPage(arg as u8)))),
+    /* 05 */ op!("ORA", ZeroPage, 3, |arg| ORA::new(ZeroPage(arg as u8))),
+    /* 06 */ op!("ASL", ZeroPage, 5, |arg| ASL::new(ZeroPage(arg as u8))),
+    /* 07 */ op!("SLO", ZeroPage, 5, |arg| SLO::new(ZeroPage(arg as u8))),
+    /* 08 */ op!("PHP", Implied, 3, |_| PHP{}),
+    /* 09 */ op!("ORA", Immediate, 2, |arg| ORA::new(Immediate(arg as u8))),
+    /* 0A */ op!("ASL", Accumulator, 2, |_| ASL::new(Accumulator)),
+    /* 0B */ op!("ANC", Immediate, 2, |arg| ANC::new(0x0B, Immediate(arg as u8))),
+    /* 0C */ op!("NOP", Absolute, 4, |arg| IllegalNOP::new(0x0C, Some(Absolute(arg)))),
+    /* 0D */ op!("ORA", Absolute, 4, |arg| ORA::new(Absolute(arg))),
+    /* 0E */ op!("ASL", Absolute, 6, |arg| ASL::new(Absolute(arg))),
+    /* 0F */ op!("SLO", Absolute, 6, |arg| SLO::new(Absolute(arg))),
+    /* 10 */ op!("BPL", Relative, 2, |arg| BPL::new(arg as i8)),
+    /* 11 */ op!("ORA", IndirectY, 5, |arg| ORA::new(IndirectY(arg as u8))),
+    /* 12 */ op!("JAM", Implied, 2, |_| JAM::new(0x12)),
+    /* 13 */ op!("SLO", IndirectY, 8, |arg| SLO::new(IndirectY(arg as u8))),
+    /* 14 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0x14, Some(ZeroPageX(arg as u8)))),
+    /* 15 */ op!("ORA", ZeroPageX, 4, |arg| ORA::new(ZeroPageX(arg as u8))),
+    /* 16 */ op!("ASL", ZeroPageX, 6, |arg| ASL::new(ZeroPageX(arg as u8))),
+    /* 17 */ op!("SLO", ZeroPageX, 6, |arg| SLO::new(ZeroPageX(arg as u8))),
+    /* 18 */ op!("CLC", Implied, 2, |_| CLC{}),
+    /* 19 */ op!("ORA", AbsoluteY, 4, |arg| ORA::new(AbsoluteY(arg))),
+    /* 1A */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0x1A, None)),
+    /* 1B */ op!("SLO", AbsoluteY, 7, |arg| SLO::new(AbsoluteY(arg))),
+    /* 1C */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0x1C, Some(AbsoluteX(arg)))),
+    /* 1D */ op!("ORA", AbsoluteX, 4, |arg| ORA::new(AbsoluteX(arg))),
+    /* 1E */ op!("ASL", AbsoluteX, 7, |arg| ASL::new(AbsoluteX(arg))),
+    /* 1F */ op!("SLO", AbsoluteX, 7, |arg| SLO::new(AbsoluteX(arg))),
+    /* 20 */ op!("JSR", Absolute, 6, |arg| JSR::new(arg)),
+    /* 21 */ op!("AND", IndirectX, 6, |arg| AND::new(IndirectX(arg as u8))),
+    /* 22 */ op!("JAM", Implied, 2, |_| JAM::new(0x22)),
+    /* 23 */ op!("RLA", IndirectX, 8, |arg| RLA::new(IndirectX(arg as u8))),
+    /* 24 */ op!("BIT", ZeroPage, 3, |arg| BIT::new(ZeroPage(arg as u8))),
+    /* 25 */ op!("AND", ZeroPage, 3, |arg| AND::new(ZeroPage(arg as u8))),
+    /* 26 */ op!("ROL", ZeroPage, 5, |arg| ROL::new(ZeroPage(arg as u8))),
+    /* 27 */ op!("RLA", ZeroPage, 5, |arg| RLA::new(ZeroPage(arg as u8))),
+    /* 28 */ op!("PLP", Implied, 4, |_| PLP{}),
+    /* 29 */ op!("AND", Immediate, 2, |arg| AND::new(Immediate(arg as u8))),
+    /* 2A */ op!("ROL", Accumulator, 2, |_| ROL::new(Accumulator)),
+    /* 2B */ op!("ANC", Immediate, 2, |arg| ANC::new(0x2B, Immediate(arg as u8))),
+    /* 2C */ op!("BIT", Absolute, 4, |arg| BIT::new(Absolute(arg))),
+    /* 2D */ op!("AND", Absolute, 4, |arg| AND::new(Absolute(arg))),
+    /* 2E */ op!("ROL", Absolute, 6, |arg| ROL::new(Absolute(arg))),
+    /* 2F */ op!("RLA", Absolute, 6, |arg| RLA::new(Absolute(arg))),
+    /* 30 */ op!("BMI", Relative, 2, |arg| BMI::new(arg as i8)),
+    /* 31 */ op!("AND", IndirectY, 5, |arg| AND::new(IndirectY(arg as u8))),
+    /* 32 */ op!("JAM", Implied, 2, |_| JAM::new(0x32)),
+    /* 33 */ op!("RLA", IndirectY, 8, |arg| RLA::new(IndirectY(arg as u8))),
+    /* 34 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0x34, Some(ZeroPageX(arg as u8)))),
+    /* 35 */ op!("AND", ZeroPageX, 4, |arg| AND::new(ZeroPageX(arg as u8))),
+    /* 36 */ op!("ROL", ZeroPageX, 6, |arg| ROL::new(ZeroPageX(arg as u8))),
+    /* 37 */ op!("RLA", ZeroPageX, 6, |arg| RLA::new(ZeroPageX(arg as u8))),
+    /* 38 */ op!("SEC", Implied, 2, |_| SEC{}),
+    /* 39 */ op!("AND", AbsoluteY, 4, |arg| AND::new(AbsoluteY(arg))),
+    /* 3A */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0x3A, None)),
+    /* 3B */ op!("RLA", AbsoluteY, 7, |arg| RLA::new(AbsoluteY(arg))),
+    /* 3C */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0x3C, Some(AbsoluteX(arg)))),
+    /* 3D */ op!("AND", AbsoluteX, 4, |arg| AND::new(AbsoluteX(arg))),
+    /* 3E */ op!("ROL", AbsoluteX, 7, |arg| ROL::new(AbsoluteX(arg))),
+    /* 3F */ op!("RLA", AbsoluteX, 7, |arg| RLA::new(AbsoluteX(arg))),
+    /* 40 */ op!("RTI", Implied, 6, |_| RTI{}),
+    /* 41 */ op!("EOR", IndirectX, 6, |arg| EOR::new(IndirectX(arg as u8))),
+    /* 42 */ op!("JAM", Implied, 2, |_| JAM::new(0x42)),
+    /* 43 */ op!("SRE", IndirectX, 8, |arg| SRE::new(IndirectX(arg as u8))),
+    /* 44 */ op!("NOP", ZeroPage, 3, |arg| IllegalNOP::new(0x44, Some(ZeroPage(arg as u8)))),
+    /* 45 */ op!("EOR", ZeroPage, 3, |arg| EOR::new(ZeroPage(arg as u8))),
+    /* 46 */ op!("LSR", ZeroPage, 5, |arg| LSR::new(ZeroPage(arg as u8))),
+    /* 47 */ op!("SRE", ZeroPage, 5, |arg| SRE::new(ZeroPage(arg as u8))),
+    /* 48 */ op!("PHA", Implied, 3, |_| PHA{}),
+    /* 49 */ op!("EOR", Immediate, 2, |arg| EOR::new(Immediate(arg as u8))),
+    /* 4A */ op!("LSR", Accumulator, 2, |_| LSR::new(Accumulator)),
+    /* 4B */ op!("ALR", Immediate, 2, |arg| ALR::new(Immediate(arg as u8))),
+    /* 4C */ op!("JMP", Absolute, 3, |arg| JMP::new(JumpAddressMode::Absolute(arg))),
+    /* 4D */ op!("EOR", Absolute, 4, |arg| EOR::new(Absolute(arg))),
+    /* 4E */ op!("LSR", Absolute, 6, |arg| LSR::new(Absolute(arg))),
+    /* 4F */ op!("SRE", Absolute, 6, |arg| SRE::new(Absolute(arg))),
+    /* 50 */ op!("BVC", Relative, 2, |arg| BVC::new(arg as i8)),
+    /* 51 */ op!("EOR", IndirectY, 5, |arg| EOR::new(IndirectY(arg as u8))),
+    /* 52 */ op!("JAM", Implied, 2, |_| JAM::new(0x52)),
+    /* 53 */ op!("SRE", IndirectY, 8, |arg| SRE::new(IndirectY(arg as u8))),
+    /* 54 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0x54, Some(ZeroPageX(arg as u8)))),
+    /* 55 */ op!("EOR", ZeroPageX, 4, |arg| EOR::new(ZeroPageX(arg as u8))),
+    /* 56 */ op!("LSR", ZeroPageX, 6, |arg| LSR::new(ZeroPageX(arg as u8))),
+    /* 57 */ op!("SRE", ZeroPageX, 6, |arg| SRE::new(ZeroPageX(arg as u8))),
+    /* 58 */ op!("CLI", Implied, 2, |_| CLI{}),
+    /* 59 */ op!("EOR", AbsoluteY, 4, |arg| EOR::new(AbsoluteY(arg))),
+    /* 5A */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0x5A, None)),
+    /* 5B */ op!("SRE", AbsoluteY, 7, |arg| SRE::new(AbsoluteY(arg))),
+    /* 5C */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0x5C, Some(AbsoluteX(arg)))),
+    /* 5D */ op!("EOR", AbsoluteX, 4, |arg| EOR::new(AbsoluteX(arg))),
+    /* 5E */ op!("LSR", AbsoluteX, 7, |arg| LSR::new(AbsoluteX(arg))),
+    /* 5F */ op!("SRE", AbsoluteX, 7, |arg| SRE::new(AbsoluteX(arg))),
+    /* 60 */ op!("RTS", Implied, 6, |_| RTS{}),
+    /* 61 */ op!("ADC", IndirectX, 6, |arg| ADC::new(IndirectX(arg as u8))),
+    /* 62 */ op!("JAM", Implied, 2, |_| JAM::new(0x62)),
+    /* 63 */ op!("RRA", IndirectX, 8, |arg| RRA::new(IndirectX(arg as u8))),
+    /* 64 */ op!("NOP", ZeroPage, 3, |arg| IllegalNOP::new(0x64, Some(ZeroPage(arg as u8)))),
+    /* 65 */ op!("ADC", ZeroPage, 3, |arg| ADC::new(ZeroPage(arg as u8))),
+    /* 66 */ op!("ROR", ZeroPage, 5, |arg| ROR::new(ZeroPage(arg as u8))),
+    /* 67 */ op!("RRA", ZeroPage, 5, |arg| RRA::new(ZeroPage(arg as u8))),
+    /* 68 */ op!("PLA", Implied, 4, |_| PLA{}),
+    /* 69 */ op!("ADC", Immediate, 2, |arg| ADC::new(Immediate(arg as u8))),
+    /* 6A */ op!("ROR", Accumulator, 2, |_| ROR::new(Accumulator)),
+    /* 6B */ op!("ARR", Immediate, 2, |arg| ARR::new(Immediate(arg as u8))),
+    /* 6C */ op!("JMP", Indirect, 5, |arg| JMP::new(JumpAddressMode::Indirect(arg))),
+    /* 6D */ op!("ADC", Absolute, 4, |arg| ADC::new(Absolute(arg))),
+    /* 6E */ op!("ROR", Absolute, 6, |arg| ROR::new(Absolute(arg))),
+    /* 6F */ op!("RRA", Absolute, 6, |arg| RRA::new(Absolute(arg))),
+    /* 70 */ op!("BVS", Relative, 2, |arg| BVS::new(arg as i8)),
+    /* 71 */ op!("ADC", IndirectY, 5, |arg| ADC::new(IndirectY(arg as u8))),
+    /* 72 */ op!("JAM", Implied, 2, |_| JAM::new(0x72)),
+    /* 73 */ op!("RRA", IndirectY, 8, |arg| RRA::new(IndirectY(arg as u8))),
+    /* 74 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0x74, Some(ZeroPageX(arg as u8)))),
+    /* 75 */ op!("ADC", ZeroPageX, 4, |arg| ADC::new(ZeroPageX(arg as u8))),
+    /* 76 */ op!("ROR", ZeroPageX, 6, |arg| ROR::new(ZeroPageX(arg as u8))),
+    /* 77 */ op!("RRA", ZeroPageX, 6, |arg| RRA::new(ZeroPageX(arg as u8))),
+    /* 78 */ op!("SEI", Implied, 2, |_| SEI{}),
+    /* 79 */ op!("ADC", AbsoluteY, 4, |arg| ADC::new(AbsoluteY(arg))),
+    /* 7A */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0x7A, None)),
+    /* 7B */ op!("RRA", AbsoluteY, 7, |arg| RRA::new(AbsoluteY(arg))),
+    /* 7C */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0x7C, Some(AbsoluteX(arg)))),
+    /* 7D */ op!("ADC", AbsoluteX, 4, |arg| ADC::new(AbsoluteX(arg))),
+    /* 7E */ op!("ROR", AbsoluteX, 7, |arg| ROR::new(AbsoluteX(arg))),
+    /* 7F */ op!("RRA", AbsoluteX, 7, |arg| RRA::new(AbsoluteX(arg))),
+    /* 80 */ op!("NOP", Immediate, 2, |arg| IllegalNOP::new(0x80, Some(Immediate(arg as u8)))),
+    /* 81 */ op!("STA", IndirectX, 6, |arg| STA::new(IndirectX(arg as u8))),
+    /* 82 */ op!("NOP", Immediate, 2, |arg| IllegalNOP::new(0x82, Some(Immediate(arg as u8)))),
+    /* 83 */ op!("SAX", IndirectX, 6, |arg| SAX::new(IndirectX(arg as u8))),
+    /* 84 */ op!("STY", ZeroPage, 3, |arg| STY::new(ZeroPage(arg as u8))),
+    /* 85 */ op!("STA", ZeroPage, 3, |arg| STA::new(ZeroPage(arg as u8))),
+    /* 86 */ op!("STX", ZeroPage, 3, |arg| STX::new(ZeroPage(arg as u8))),
+    /* 87 */ op!("SAX", ZeroPage, 3, |arg| SAX::new(ZeroPage(arg as u8))),
+    /* 88 */ op!("DEY", Implied, 2, |_| DEY{}),
+    /* 89 */ op!("NOP", Immediate, 2, |arg| IllegalNOP::new(0x89, Some(Immediate(arg as u8)))),
+    /* 8A */ op!("TXA", Implied, 2, |_| TXA{}),
+    /* 8B */ op!("ANE", Immediate, 2, |arg| ANE::new(Immediate(arg as u8))),
+    /* 8C */ op!("STY", Absolute, 4, |arg| STY::new(Absolute(arg))),
+    /* 8D */ op!("STA", Absolute, 4, |arg| STA::new(Absolute(arg))),
+    /* 8E */ op!("STX", Absolute, 4, |arg| STX::new(Absolute(arg))),
+    /* 8F */ op!("SAX", Absolute, 4, |arg| SAX::new(Absolute(arg))),
+    /* 90 */ op!("BCC", Relative, 2, |arg| BCC::new(arg as i8)),
+    /* 91 */ op!("STA", IndirectY, 6, |arg| STA::new(IndirectY(arg as u8))),
+    /* 92 */ op!("JAM", Implied, 2, |_| JAM::new(0x92)),
+    /* 93 */ op!("SHA", IndirectY, 6, |arg| SHA::new(IndirectY(arg as u8))),
+    /* 94 */ op!("STY", ZeroPageX, 4, |arg| STY::new(ZeroPageX(arg as u8))),
+    /* 95 */ op!("STA", ZeroPageX, 4, |arg| STA::new(ZeroPageX(arg as u8))),
+    /* 96 */ op!("STX", ZeroPageY, 4, |arg| STX::new(ZeroPageY(arg as u8))),
+    /* 97 */ op!("SAX", ZeroPageY, 4, |arg| SAX::new(ZeroPageY(arg as u8))),
+    /* 98 */ op!("TYA", Implied, 2, |_| TYA{}),
+    /* 99 */ op!("STA", AbsoluteY, 5, |arg| STA::new(AbsoluteY(arg))),
+    /* 9A */ op!("TXS", Implied, 2, |_| TXS{}),
+    /* 9B */ op!("TAS", AbsoluteY, 5, |arg| TAS::new(AbsoluteY(arg))),
+    /* 9C */ op!("SHY", AbsoluteX, 5, |arg| SHY::new(AbsoluteX(arg))),
+    /* 9D */ op!("STA", AbsoluteX, 5, |arg| STA::new(AbsoluteX(arg))),
+    /* 9E */ op!("SHX", AbsoluteY, 5, |arg| SHX::new(AbsoluteY(arg))),
+    /* 9F */ op!("SHA", AbsoluteY, 5, |arg| SHA::new(AbsoluteY(arg))),
+    /* A0 */ op!("LDY", Immediate, 2, |arg| LDY::new(Immediate(arg as u8))),
+    /* A1 */ op!("LDA", IndirectX, 6, |arg| LDA::new(IndirectX(arg as u8))),
+    /* A2 */ op!("LDX", Immediate, 2, |arg| LDX::new(Immediate(arg as u8))),
+    /* A3 */ op!("LAX", IndirectX, 6, |arg| LAX::new(IndirectX(arg as u8))),
+    /* A4 */ op!("LDY", ZeroPage, 3, |arg| LDY::new(ZeroPage(arg as u8))),
+    /* A5 */ op!("LDA", ZeroPage, 3, |arg| LDA::new(ZeroPage(arg as u8))),
+    /* A6 */ op!("LDX", ZeroPage, 3, |arg| LDX::new(ZeroPage(arg as u8))),
+    /* A7 */ op!("LAX", ZeroPage, 3, |arg| LAX::new(ZeroPage(arg as u8))),
+    /* A8 */ op!("TAY", Implied, 2, |_| TAY{}),
+    /* A9 */ op!("LDA", Immediate, 2, |arg| LDA::new(Immediate(arg as u8))),
+    /* AA */ op!("TAX", Implied, 2, |_| TAX{}),
+    /* AB */ op!("LXA", Immediate, 2, |arg| LXA::new(Immediate(arg as u8))),
+    /* AC */ op!("LDY", Absolute, 4, |arg| LDY::new(Absolute(arg))),
+    /* AD */ op!("LDA", Absolute, 4, |arg| LDA::new(Absolute(arg))),
+    /* AE */ op!("LDX", Absolute, 4, |arg| LDX::new(Absolute(arg))),
+    /* AF */ op!("LAX", Absolute, 4, |arg| LAX::new(Absolute(arg))),
+    /* B0 */ op!("BCS", Relative, 2, |arg| BCS::new(arg as i8)),
+    /* B1 */ op!("LDA", IndirectY, 5, |arg| LDA::new(IndirectY(arg as u8))),
+    /* B2 */ op!("JAM", Implied, 2, |_| JAM::new(0xB2)),
+    /* B3 */ op!("LAX", IndirectY, 5, |arg| LAX::new(IndirectY(arg as u8))),
+    /* B4 */ op!("LDY", ZeroPageX, 4, |arg| LDY::new(ZeroPageX(arg as u8))),
+    /* B5 */ op!("LDA", ZeroPageX, 4, |arg| LDA::new(ZeroPageX(arg as u8))),
+    /* B6 */ op!("LDX", ZeroPageY, 4, |arg| LDX::new(ZeroPageY(arg as u8))),
+    /* B7 */ op!("LAX", ZeroPageY, 4, |arg| LAX::new(ZeroPageY(arg as u8))),
+    /* B8 */ op!("CLV", Implied, 2, |_| CLV{}),
+    /* B9 */ op!("LDA", AbsoluteY, 4, |arg| LDA::new(AbsoluteY(arg))),
+    /* BA */ op!("TSX", Implied, 2, |_| TSX{}),
+    /* BB */ op!("LAS", AbsoluteY, 4, |arg| LAS::new(AbsoluteY(arg))),
+    /* BC */ op!("LDY", AbsoluteX, 4, |arg| LDY::new(AbsoluteX(arg))),
+    /* BD */ op!("LDA", AbsoluteX, 4, |arg| LDA::new(AbsoluteX(arg))),
+    /* BE */ op!("LDX", AbsoluteY, 4, |arg| LDX::new(AbsoluteY(arg))),
+    /* BF */ op!("LAX", AbsoluteY, 4, |arg| LAX::new(AbsoluteY(arg))),
+    /* C0 */ op!("CPY", Immediate, 2, |arg| CPY::new(Immediate(arg as u8))),
+    /* C1 */ op!("CMP", IndirectX, 6, |arg| CMP::new(IndirectX(arg as u8))),
+    /* C2 */ op!("NOP", Immediate, 2, |arg| IllegalNOP::new(0xC2, Some(Immediate(arg as u8)))),
+    /* C3 */ op!("DCP", IndirectX, 8, |arg| DCP::new(IndirectX(arg as u8))),
+    /* C4 */ op!("CPY", ZeroPage, 3, |arg| CPY::new(ZeroPage(arg as u8))),
+    /* C5 */ op!("CMP", ZeroPage, 3, |arg| CMP::new(ZeroPage(arg as u8))),
+    /* C6 */ op!("DEC", ZeroPage, 5, |arg| DEC::new(ZeroPage(arg as u8))),
+    /* C7 */ op!("DCP", ZeroPage, 5, |arg| DCP::new(ZeroPage(arg as u8))),
+    /* C8 */ op!("INY", Implied, 2, |_| INY{}),
+    /* C9 */ op!("CMP", Immediate, 2, |arg| CMP::new(Immediate(arg as u8))),
+    /* CA */ op!("DEX", Implied, 2, |_| DEX{}),
+    /* CB */ op!("SBX", Immediate, 2, |arg| SBX::new(Immediate(arg as u8))),
+    /* CC */ op!("CPY", Absolute, 4, |arg| CPY::new(Absolute(arg))),
+    /* CD */ op!("CMP", Absolute, 4, |arg| CMP::new(Absolute(arg))),
+    /* CE */ op!("DEC", Absolute, 6, |arg| DEC::new(Absolute(arg))),
+    /* CF */ op!("DCP", Absolute, 6, |arg| DCP::new(Absolute(arg))),
+    /* D0 */ op!("BNE", Relative, 2, |arg| BNE::new(arg as i8)),
+    /* D1 */ op!("CMP", IndirectY, 5, |arg| CMP::new(IndirectY(arg as u8))),
+    /* D2 */ op!("JAM", Implied, 2, |_| JAM::new(0xD2)),
+    /* D3 */ op!("DCP", IndirectY, 8, |arg| DCP::new(IndirectY(arg as u8))),
+    /* D4 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0xD4, Some(ZeroPageX(arg as u8)))),
+    /* D5 */ op!("CMP", ZeroPageX, 4, |arg| CMP::new(ZeroPageX(arg as u8))),
+    /* D6 */ op!("DEC", ZeroPageX, 6, |arg| DEC::new(ZeroPageX(arg as u8))),
+    /* D7 */ op!("DCP", ZeroPageX, 6, |arg| DCP::new(ZeroPageX(arg as u8))),
+    /* D8 */ op!("CLD", Implied, 2, |_| CLD{}),
+    /* D9 */ op!("CMP", AbsoluteY, 4, |arg| CMP::new(AbsoluteY(arg))),
+    /* DA */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0xDA, None)),
+    /* DB */ op!("DCP", AbsoluteY, 7, |arg| DCP::new(AbsoluteY(arg))),
+    /* DC */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0xDC, Some(AbsoluteX(arg)))),
+    /* DD */ op!("CMP", AbsoluteX, 4, |arg| CMP::new(AbsoluteX(arg))),
+    /* DE */ op!("DEC", AbsoluteX, 7, |arg| DEC::new(AbsoluteX(arg))),
+    /* DF */ op!("DCP", AbsoluteX, 7, |arg| DCP::new(AbsoluteX(arg))),
+    /* E0 */ op!("CPX", Immediate, 2, |arg| CPX::new(Immediate(arg as u8))),
+    /* E1 */ op!("SBC", IndirectX, 6, |arg| SBC::new(IndirectX(arg as u8))),
+    /* E2 */ op!("NOP", Immediate, 2, |arg| IllegalNOP::new(0xE2, Some(Immediate(arg as u8)))),
+    /* E3 */ op!("ISC", IndirectX, 8, |arg| ISC::new(IndirectX(arg as u8))),
+    /* E4 */ op!("CPX", ZeroPage, 3, |arg| CPX::new(ZeroPage(arg as u8))),
+    /* E5 */ op!("SBC", ZeroPage, 3, |arg| SBC::new(ZeroPage(arg as u8))),
+    /* E6 */ op!("INC", ZeroPage, 5, |arg| INC::new(ZeroPage(arg as u8))),
+    /* E7 */ op!("ISC", ZeroPage, 5, |arg| ISC::new(ZeroPage(arg as u8))),
+    /* E8 */ op!("INX", Implied, 2, |_| INX{}),
+    /* E9 */ op!("SBC", Immediate, 2, |arg| SBC::new(Immediate(arg as u8))),
+    /* EA */ op!("NOP", Implied, 2, |_| NOP{}),
+    /* EB */ op!("SBC", Immediate, 2, |arg| SBC::new_illegal(Immediate(arg as u8))),
+    /* EC */ op!("CPX", Absolute, 4, |arg| CPX::new(Absolute(arg))),
+    /* ED */ op!("SBC", Absolute, 4, |arg| SBC::new(Absolute(arg))),
+    /* EE */ op!("INC", Absolute, 6, |arg| INC::new(Absolute(arg))),
+    /* EF */ op!("ISC", Absolute, 6, |arg| ISC::new(Absolute(arg))),
+    /* F0 */ op!("BEQ", Relative, 2, |arg| BEQ::new(arg as i8)),
+    /* F1 */ op!("SBC", IndirectY, 5, |arg| SBC::new(IndirectY(arg as u8))),
+    /* F2 */ op!("JAM", Implied, 2, |_| JAM::new(0xF2)),
+    /* F3 */ op!("ISC", IndirectY, 8, |arg| ISC::new(IndirectY(arg as u8))),
+    /* F4 */ op!("NOP", ZeroPageX, 4, |arg| IllegalNOP::new(0xF4, Some(ZeroPageX(arg as u8)))),
+    /* F5 */ op!("SBC", ZeroPageX, 4, |arg| SBC::new(ZeroPageX(arg as u8))),
+    /* F6 */ op!("INC", ZeroPageX, 6, |arg| INC::new(ZeroPageX(arg as u8))),
+    /* F7 */ op!("ISC", ZeroPageX, 6, |arg| ISC::new(ZeroPageX(arg as u8))),
+    /* F8 */ op!("SED", Implied, 2, |_| SED{}),
+    /* F9 */ op!("SBC", AbsoluteY, 4, |arg| SBC::new(AbsoluteY(arg))),
+    /* FA */ op!("NOP", Implied, 2, |_| IllegalNOP::new(0xFA, None)),
+    /* FB */ op!("ISC", AbsoluteY, 7, |arg| ISC::new(AbsoluteY(arg))),
+    /* FC */ op!("NOP", AbsoluteX, 4, |arg| IllegalNOP::new(0xFC, Some(AbsoluteX(arg)))),
+    /* FD */ op!("SBC", AbsoluteX, 4, |arg| SBC::new(AbsoluteX(arg))),
+    /* FE */ op!("INC", AbsoluteX, 7, |arg| INC::new(AbsoluteX(arg))),
+    /* FF */ op!("ISC", AbsoluteX, 7, |arg| ISC::new(AbsoluteX(arg))),
+];
+
+// The 65C02 only differs from the NMOS table in these entries.  This is the original
+// 65C02, so columns 7 and F are NOPs rather than the Rockwell and WDC bit instructions.
+// Cycle counts from http://6502.org/tutorials/65c02opcodes.html
+static CMOS_OPCODES: [Option<Opcode>; 256] = {
+    const NONE: Option<Opcode> = None;
+    let mut table = [NONE; 256];
+
+    table[0x02] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0x02, arg, 2, 2)));
+    table[0x03] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x03, arg, 1, 1)));
+    table[0x04] = Some(op!("TSB", ZeroPage, 5, |arg| TSB::new(ZeroPage(arg as u8))));
+    table[0x07] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x07, arg, 1, 1)));
+    table[0x0B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x0B, arg, 1, 1)));
+    table[0x0C] = Some(op!("TSB", Absolute, 6, |arg| TSB::new(Absolute(arg))));
+    table[0x0F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x0F, arg, 1, 1)));
+    table[0x12] = Some(op!("ORA", ZeroPageIndirect, 5, |arg| ORA::new(ZeroPageIndirect(arg as u8))));
+    table[0x13] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x13, arg, 1, 1)));
+    table[0x14] = Some(op!("TRB", ZeroPage, 5, |arg| TRB::new(ZeroPage(arg as u8))));
+    table[0x17] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x17, arg, 1, 1)));
+    table[0x1A] = Some(op!("INC", Accumulator, 2, |_| INC::new(Accumulator)));
+    table[0x1B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x1B, arg, 1, 1)));
+    table[0x1C] = Some(op!("TRB", Absolute, 6, |arg| TRB::new(Absolute(arg))));
+    table[0x1F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x1F, arg, 1, 1)));
+    table[0x22] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0x22, arg, 2, 2)));
+    table[0x23] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x23, arg, 1, 1)));
+    table[0x27] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x27, arg, 1, 1)));
+    table[0x2B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x2B, arg, 1, 1)));
+    table[0x2F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x2F, arg, 1, 1)));
+    table[0x32] = Some(op!("AND", ZeroPageIndirect, 5, |arg| AND::new(ZeroPageIndirect(arg as u8))));
+    table[0x33] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x33, arg, 1, 1)));
+    table[0x34] = Some(op!("BIT", ZeroPageX, 4, |arg| BIT::new(ZeroPageX(arg as u8))));
+    table[0x37] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x37, arg, 1, 1)));
+    table[0x3A] = Some(op!("DEC", Accumulator, 2, |_| DEC::new(Accumulator)));
+    table[0x3B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x3B, arg, 1, 1)));
+    table[0x3C] = Some(op!("BIT", AbsoluteX, 4, |arg| BIT::new(AbsoluteX(arg))));
+    table[0x3F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x3F, arg, 1, 1)));
+    table[0x42] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0x42, arg, 2, 2)));
+    table[0x43] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x43, arg, 1, 1)));
+    table[0x44] = Some(op!("NOP", Immediate, 3, |arg| ReservedNOP::new(0x44, arg, 2, 3)));
+    table[0x47] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x47, arg, 1, 1)));
+    table[0x4B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x4B, arg, 1, 1)));
+    table[0x4F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x4F, arg, 1, 1)));
+    table[0x52] = Some(op!("EOR", ZeroPageIndirect, 5, |arg| EOR::new(ZeroPageIndirect(arg as u8))));
+    table[0x53] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x53, arg, 1, 1)));
+    table[0x54] = Some(op!("NOP", Immediate, 4, |arg| ReservedNOP::new(0x54, arg, 2, 4)));
+    table[0x57] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x57, arg, 1, 1)));
+    table[0x5A] = Some(op!("PHY", Implied, 3, |_| PHY{}));
+    table[0x5B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x5B, arg, 1, 1)));
+    table[0x5C] = Some(op!("NOP", Absolute, 8, |arg| ReservedNOP::new(0x5C, arg, 3, 8)));
+    table[0x5F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x5F, arg, 1, 1)));
+    table[0x62] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0x62, arg, 2, 2)));
+    table[0x63] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x63, arg, 1, 1)));
+    table[0x64] = Some(op!("STZ", ZeroPage, 3, |arg| STZ::new(ZeroPage(arg as u8))));
+    table[0x67] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x67, arg, 1, 1)));
+    table[0x6B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x6B, arg, 1, 1)));
+    table[0x6C] = Some(op!("JMP", Indirect, 6, |arg| JMP::new(JumpAddressMode::Indirect(arg))));
+    table[0x6F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x6F, arg, 1, 1)));
+    table[0x72] = Some(op!("ADC", ZeroPageIndirect, 5, |arg| ADC::new(ZeroPageIndirect(arg as u8))));
+    table[0x73] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x73, arg, 1, 1)));
+    table[0x74] = Some(op!("STZ", ZeroPageX, 4, |arg| STZ::new(ZeroPageX(arg as u8))));
+    table[0x77] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x77, arg, 1, 1)));
+    table[0x7A] = Some(op!("PLY", Implied, 4, |_| PLY{}));
+    table[0x7B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x7B, arg, 1, 1)));
+    table[0x7C] = Some(op!("JMP", AbsoluteIndirectX, 6, |arg| JMP::new(JumpAddressMode::IndirectX(arg))));
+    table[0x7F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x7F, arg, 1, 1)));
+    table[0x80] = Some(op!("BRA", Relative, 3, |arg| BRA::new(arg as i8)));
+    table[0x82] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0x82, arg, 2, 2)));
+    table[0x83] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x83, arg, 1, 1)));
+    table[0x87] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x87, arg, 1, 1)));
+    table[0x89] = Some(op!("BIT", Immediate, 2, |arg| BIT::new(Immediate(arg as u8))));
+    table[0x8B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x8B, arg, 1, 1)));
+    table[0x8F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x8F, arg, 1, 1)));
+    table[0x92] = Some(op!("STA", ZeroPageIndirect, 5, |arg| STA::new(ZeroPageIndirect(arg as u8))));
+    table[0x93] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x93, arg, 1, 1)));
+    table[0x97] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x97, arg, 1, 1)));
+    table[0x9B] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x9B, arg, 1, 1)));
+    table[0x9C] = Some(op!("STZ", Absolute, 4, |arg| STZ::new(Absolute(arg))));
+    table[0x9E] = Some(op!("STZ", AbsoluteX, 5, |arg| STZ::new(AbsoluteX(arg))));
+    table[0x9F] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0x9F, arg, 1, 1)));
+    table[0xA3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xA3, arg, 1, 1)));
+    table[0xA7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xA7, arg, 1, 1)));
+    table[0xAB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xAB, arg, 1, 1)));
+    table[0xAF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xAF, arg, 1, 1)));
+    table[0xB2] = Some(op!("LDA", ZeroPageIndirect, 5, |arg| LDA::new(ZeroPageIndirect(arg as u8))));
+    table[0xB3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xB3, arg, 1, 1)));
+    table[0xB7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xB7, arg, 1, 1)));
+    table[0xBB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xBB, arg, 1, 1)));
+    table[0xBF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xBF, arg, 1, 1)));
+    table[0xC2] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0xC2, arg, 2, 2)));
+    table[0xC3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xC3, arg, 1, 1)));
+    table[0xC7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xC7, arg, 1, 1)));
+    table[0xCB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xCB, arg, 1, 1)));
+    table[0xCF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xCF, arg, 1, 1)));
+    table[0xD2] = Some(op!("CMP", ZeroPageIndirect, 5, |arg| CMP::new(ZeroPageIndirect(arg as u8))));
+    table[0xD3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xD3, arg, 1, 1)));
+    table[0xD4] = Some(op!("NOP", Immediate, 4, |arg| ReservedNOP::new(0xD4, arg, 2, 4)));
+    table[0xD7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xD7, arg, 1, 1)));
+    table[0xDA] = Some(op!("PHX", Implied, 3, |_| PHX{}));
+    table[0xDB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xDB, arg, 1, 1)));
+    table[0xDC] = Some(op!("NOP", Absolute, 4, |arg| ReservedNOP::new(0xDC, arg, 3, 4)));
+    table[0xDF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xDF, arg, 1, 1)));
+    table[0xE2] = Some(op!("NOP", Immediate, 2, |arg| ReservedNOP::new(0xE2, arg, 2, 2)));
+    table[0xE3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xE3, arg, 1, 1)));
+    table[0xE7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xE7, arg, 1, 1)));
+    table[0xEB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xEB, arg, 1, 1)));
+    table[0xEF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xEF, arg, 1, 1)));
+    table[0xF2] = Some(op!("SBC", ZeroPageIndirect, 5, |arg| SBC::new(ZeroPageIndirect(arg as u8))));
+    table[0xF3] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xF3, arg, 1, 1)));
+    table[0xF4] = Some(op!("NOP", Immediate, 4, |arg| ReservedNOP::new(0xF4, arg, 2, 4)));
+    table[0xF7] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xF7, arg, 1, 1)));
+    table[0xFA] = Some(op!("PLX", Implied, 4, |_| PLX{}));
+    table[0xFB] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xFB, arg, 1, 1)));
+    table[0xFC] = Some(op!("NOP", Absolute, 4, |arg| ReservedNOP::new(0xFC, arg, 3, 4)));
+    table[0xFF] = Some(op!("NOP", Implied, 1, |arg| ReservedNOP::new(0xFF, arg, 1, 1)));
+
+    table
+};
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Variant};
+    use super::{fetch, lookup, Opcode, OPCODES, Operand};
+
+    // Runs the entry from an empty CPU, where nothing crosses a page, less the cycle a
+    // taken branch adds.  BRA always branches, so its base includes it.
+    fn base_cycles(entry: &Opcode, cpu: &mut CPU, arg: u16) -> u8 {
+        let pc = cpu.program_counter;
+        let cycles = (entry.execute)(cpu, arg);
+        let taken = entry.operand == Operand::Relative && entry.mnemonic != "BRA" && cpu.program_counter != pc;
+
+        cycles - taken as u8
+    }
+
+    #[test]
+    fn every_opcode_decodes() {
+        for opcode in 0..=0xFF {
+            // Given
+            let mut cpu = CPU::empty();
+            cpu.write_mem16(0x0200, opcode);
+            cpu.write_mem16(0x0201, 0x0010);
+            cpu.program_counter = 0x0200;
+
+            // When
+            let (_, arg) = fetch(&mut cpu);
+
+            // Then
+            let entry = &OPCODES[opcode as usize];
+            let instruction = (entry.decode)(arg);
+            let bytes = instruction.bytes();
+            assert_eq!(opcode as u8, bytes[0], "opcode {:02X}", opcode);
+            assert_eq!(entry.size() as usize, bytes.len(), "opcode {:02X}", opcode);
+            assert_eq!(0x0200 + bytes.len() as u16, cpu.program_counter, "opcode {:02X}", opcode);
+            assert!(instruction.to_string().trim_start_matches('*').starts_with(entry.mnemonic), "opcode {:02X}", opcode);
+            assert_eq!(base_cycles(entry, &mut cpu, arg), entry.cycles, "opcode {:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn jam_is_single_byte() {
+        for &opcode in [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2].iter() {
+            assert_eq!(1, OPCODES[opcode].size());
+        }
+        assert_eq!(2, OPCODES[0xA2].size());  // LDX #imm shares the column
+        assert_eq!(2, OPCODES[0x82].size());  // NOP #imm
+    }
+
+    #[test]
+    fn table_entries() {
+        assert_eq!("LDA", OPCODES[0xBD].mnemonic);
+        assert_eq!(Operand::AbsoluteX, OPCODES[0xBD].operand);
+        assert_eq!(4, OPCODES[0xBD].cycles);
+        assert_eq!(Operand::Indirect, OPCODES[0x6C].operand);
+        assert_eq!(Operand::Relative, OPCODES[0xD0].operand);
+        assert_eq!(7, OPCODES[0x00].cycles);
+    }
+
+    #[test]
+    fn executes_without_boxing() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x01;
+        cpu.write_mem16(0x0011, 0x7F);
+
+        // When
+        let cycles = (OPCODES[0xB5].execute)(&mut cpu, 0x10);     // LDA $10,X
+
+        // Then
+        assert_eq!(0x7F, cpu.accumulator);
+        assert_eq!(4, cycles);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn traces_without_boxing() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x01;
+        cpu.write_mem16(0x0011, 0x7F);
+
+        // Then
+        assert_eq!("LDA $10,X @ 11 = 7F", (OPCODES[0xB5].debug_string)(&cpu, 0x10));
+        assert_eq!("ISB $10 = 00", (OPCODES[0xE7].debug_string)(&cpu, 0x10));
+        assert!(!(OPCODES[0xA5].illegal)(0x10));
+        assert!((OPCODES[0xA7].illegal)(0x10));      // LAX
+    }
+
+    #[test]
+    fn cmos_opcodes_decode() {
+        for opcode in 0..=0xFF {
+            // Given
+            let mut cpu = CPU::empty();
+            cpu.variant = Variant::Cmos65C02;
+            cpu.write_mem16(0x0200, opcode);
+            cpu.write_mem16(0x0201, 0x0010);
+            cpu.program_counter = 0x0200;
+
+            // When
+            let (_, arg) = fetch(&mut cpu);
+
+            // Then
+            let entry = lookup(Variant::Cmos65C02, opcode as u8);
+            let instruction = (entry.decode)(arg);
+            let bytes = instruction.bytes();
+            assert_eq!(opcode as u8, bytes[0], "opcode {:02X}", opcode);
+            assert_eq!(entry.size() as usize, bytes.len(), "opcode {:02X}", opcode);
+            assert!(instruction.to_string().starts_with(entry.mnemonic), "opcode {:02X}", opcode);
+
+            assert_eq!(base_cycles(entry, &mut cpu, arg), entry.cycles, "opcode {:02X}", opcode);
+
+            // Every unstable NMOS opcode is replaced
+            assert!(!instruction.illegal() || entry.mnemonic == "NOP", "opcode {:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn cmos_table_entries() {
+        assert_eq!("BRA", lookup(Variant::Cmos65C02, 0x80).mnemonic);
+        assert_eq!(Operand::ZeroPageIndirect, lookup(Variant::Cmos65C02, 0xB2).operand);
+        assert_eq!(6, lookup(Variant::Cmos65C02, 0x6C).cycles);
+        assert_eq!("JAM", lookup(Variant::Nmos6502, 0x02).mnemonic);
+        assert_eq!("LDA", lookup(Variant::Cmos65C02, 0xBD).mnemonic);
+    }
+}
@@ -20,13 +20,15 @@ impl Display for INC {
 
 impl Instruction for INC {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let (val, _) = cpu.read(&self.mode).overflowing_add(1);
-        cpu.write(&self.mode, val);
+        let original = cpu.read_modify(&self.mode);
+        let (val, _) = original.overflowing_add(1);
+        cpu.write_modified(&self.mode, original, val);
 
         cpu.set_flag(StatusFlag::Zero,  val == 0);
         cpu.set_flag(StatusFlag::Negative, val > 0x7F);
 
         match self.mode {
+            AddressingMode::Accumulator => 2,
             AddressingMode::ZeroPage(_) => 5,
             AddressingMode::ZeroPageX(_) => 6,
             AddressingMode::Absolute(_) => 6,
@@ -37,6 +39,7 @@ impl Instruction for INC {
 
     fn bytes(&self) -> Vec<u8> {
         match self.mode {
+            AddressingMode::Accumulator => vec![0x1A],   // 65C02 only
             AddressingMode::ZeroPage(addr) => vec![0xE6, addr],
             AddressingMode::ZeroPageX(addr) => vec![0xF6, addr],
             AddressingMode::Absolute(addr) => self.bytes_for_opcode(0xEE, addr),
@@ -46,7 +49,7 @@ impl Instruction for INC {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("INC {}", self.mode.debug_string(&cpu))
+        format!("INC {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter};
-use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+use crate::cpu::{AddressingMode, CPU, Instruction};
 
 // https://www.masswerk.at/6502/6502_instruction_set.html#ISC
 pub(super) struct ISC {
@@ -20,31 +20,13 @@ impl Display for ISC {
 
 impl Instruction for ISC {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let (val, _) = cpu.read(&self.mode).overflowing_add(1);
-        cpu.write(&self.mode, val);
-
-        let memory_value= val as u16;
-        let existing_carry = (cpu.processor_status & 0x01) as u16;
-
-        let diff = (cpu.accumulator as u16)
-            .wrapping_sub(memory_value)
-            .wrapping_sub(1 - existing_carry);
-
-        let carry = diff <= 0x00FF;
-        // See logic in:  https://github.com/bfirsh/jsnes/blob/master/src/cpu.js
-        let overflow = (cpu.accumulator ^ val) & 0x80 != 0 &&
-            (cpu.accumulator ^ diff as u8) & 0x80 != 0;
-
-        cpu.accumulator = diff as u8;
-        cpu.set_flag(StatusFlag::Carry, carry);
-        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
-        cpu.set_flag(StatusFlag::Overflow, overflow);
-        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
-
-        match &self.mode {
-            AddressingMode::IndirectY(_) => 4,
-            m => cpu.memory_cycles(m)
-        }
+        let original = cpu.read_modify(&self.mode);
+        let (val, _) = original.overflowing_add(1);
+        cpu.write_modified(&self.mode, original, val);
+
+        cpu.subtract_with_borrow(val);
+
+        cpu.memory_cycles(&self.mode)
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -62,7 +44,7 @@ impl Instruction for ISC {
 
     fn debug_string(&self, cpu: &CPU) -> String {
         // TODO:  ISC, ISB, INS are used interchangeably - this lines up with the reference logs
-        format!("ISB {}", self.mode.debug_string(&cpu))
+        format!("ISB {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
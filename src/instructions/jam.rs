@@ -0,0 +1,71 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{CPU, Instruction};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#JAM
+// Locks up the CPU until reset.  The program counter is left on the JAM
+// opcode so the run loop can report where it stopped.
+pub(super) struct JAM {
+    opcode: u8
+}
+
+impl JAM {
+    pub fn new(opcode: u8) -> Self {
+        JAM{ opcode }
+    }
+}
+
+impl Display for JAM {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JAM")
+    }
+}
+
+impl Instruction for JAM {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.program_counter = cpu.program_counter.wrapping_sub(1);
+        cpu.halt();
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![self.opcode]
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use super::JAM;
+
+    #[test]
+    fn halts_on_opcode() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.program_counter = 0x0401;   // Just past the opcode at $0400
+
+        // When
+        JAM::new(0x02).execute(&mut cpu);
+
+        // Then
+        assert!(cpu.halted());
+        assert_eq!(0x0400, cpu.program_counter);
+    }
+
+    #[test]
+    fn reset_clears_halt() {
+        // Given
+        let mut cpu = CPU::empty();
+        JAM::new(0xF2).execute(&mut cpu);
+
+        // When
+        cpu.reset();
+
+        // Then
+        assert!(!cpu.halted());
+    }
+}
@@ -1,10 +1,11 @@
 use std::fmt::{Display, Formatter};
-use crate::cpu::{CPU, Instruction};
+use crate::cpu::{CPU, Instruction, Variant};
 use crate::cpu::AddressingMode::Absolute;
 
 pub(super) enum JumpAddressMode {
     Absolute(u16),
-    Indirect(u16)
+    Indirect(u16),
+    IndirectX(u16)  // 65C02 only
 }
 
 impl Display for JumpAddressMode {
@@ -12,6 +13,7 @@ impl Display for JumpAddressMode {
         match self {
             JumpAddressMode::Absolute(addr) => write!(f, "${:04X}", addr),
             JumpAddressMode::Indirect(addr) => write!(f, "(${:04X})", addr),
+            JumpAddressMode::IndirectX(addr) => write!(f, "(${:04X},X)", addr),
         }
     }
 }
@@ -26,9 +28,14 @@ impl JMP {
         JMP{ mode }
     }
 
-    fn target_address(&self, cpu: &CPU) -> u16 {
-        match self.mode {
-            JumpAddressMode::Absolute(target) => target,
+    // The vector is read through `read`, so the log can peek it without the reads
+    // showing up on the bus
+    fn target_address(&self, cpu: &CPU, read: impl Fn(u16) -> u8) -> u16 {
+        let (low, high) = match self.mode {
+            JumpAddressMode::Absolute(target) => return target,
+            JumpAddressMode::Indirect(address) if cpu.variant == Variant::Cmos65C02 => {
+                (address, address.wrapping_add(1))
+            }
             JumpAddressMode::Indirect(address) => {
                 // See here for explanation:
                 // http://www.6502.org/tutorials/6502opcodes.html#JMP
@@ -38,13 +45,15 @@ impl JMP {
                     address_parts[1].wrapping_add(1)
                 ]);
 
-                let bytes = [
-                    cpu.read(&Absolute(address)),
-                    cpu.read(&Absolute(high_byte_address))
-                ];
-                u16::from_le_bytes(bytes)
+                (address, high_byte_address)
             }
-        }
+            JumpAddressMode::IndirectX(address) => {
+                let address = address.wrapping_add(cpu.index_register_x as u16);
+                (address, address.wrapping_add(1))
+            }
+        };
+
+        u16::from_le_bytes([read(low), read(high)])
     }
 }
 
@@ -57,11 +66,14 @@ impl Display for JMP {
 impl Instruction for JMP {
 
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.program_counter = self.target_address(&cpu);
+        cpu.program_counter = self.target_address(cpu, |address| cpu.read(&Absolute(address)));
 
         match self.mode {
             JumpAddressMode::Absolute(_) => 3,
+            // The 65C02 spends a cycle fixing the page wrap
+            JumpAddressMode::Indirect(_) if cpu.variant == Variant::Cmos65C02 => 6,
             JumpAddressMode::Indirect(_) => 5,
+            JumpAddressMode::IndirectX(_) => 6,
         }
     }
 
@@ -69,20 +81,23 @@ impl Instruction for JMP {
         match self.mode {
             JumpAddressMode::Absolute(addr) => self.bytes_for_opcode(0x4C, addr),
             JumpAddressMode::Indirect(addr) => self.bytes_for_opcode(0x6C, addr),
+            JumpAddressMode::IndirectX(addr) => self.bytes_for_opcode(0x7C, addr),
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
+        let target = self.target_address(cpu, |address| cpu.peek(&Absolute(address)));
         match self.mode {
             JumpAddressMode::Absolute(_) => self.to_string(),
-            JumpAddressMode::Indirect(addr) => format!("JMP (${:04X}) = {:04X}", addr, self.target_address(&cpu))
+            JumpAddressMode::Indirect(addr) => format!("JMP (${:04X}) = {:04X}", addr, target),
+            JumpAddressMode::IndirectX(addr) => format!("JMP (${:04X},X) = {:04X}", addr, target)
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::cpu::{AddressingMode, CPU, Instruction};
+    use crate::cpu::{AddressingMode, CPU, Instruction, Variant};
     use super::{JMP, JumpAddressMode::*};
 
     #[test]
@@ -128,6 +143,39 @@ mod test {
         assert_eq!(0x0300, cpu.program_counter);
     }
 
+    #[test]
+    fn cmos_fixes_page_wrap() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.variant = Variant::Cmos65C02;
+
+        cpu.write(&AddressingMode::Absolute(0x02FF), 0x00);
+        cpu.write(&AddressingMode::Absolute(0x0300), 0xA9);
+        cpu.write(&AddressingMode::Absolute(0x0200), 0x03);
+
+        // When
+        let cycles = JMP::new(Indirect(0x02FF)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0xA900, cpu.program_counter);
+        assert_eq!(6, cycles);
+    }
+
+    #[test]
+    fn indexed_indirect_jump() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.variant = Variant::Cmos65C02;
+        cpu.index_register_x = 0x04;
+        cpu.write_mem16(0x0304, 0x8123);
+
+        // When
+        JMP::new(IndirectX(0x0300)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x8123, cpu.program_counter);
+    }
+
     #[test]
     fn string_representation_absolute() {
         let jmp = JMP::new(Absolute(0x5597));
@@ -0,0 +1,75 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#LAS
+// A = X = SP = memory AND SP
+pub(super) struct LAS {
+    mode: AddressingMode
+}
+
+impl LAS {
+    pub fn new(mode: AddressingMode) -> Self {
+        LAS{ mode }
+    }
+}
+
+impl Display for LAS {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LAS {}", self.mode)
+    }
+}
+
+impl Instruction for LAS {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.load(&self.mode) & cpu.stack_pointer;
+        cpu.accumulator = value;
+        cpu.index_register_x = value;
+        cpu.stack_pointer = value;
+
+        cpu.set_flag(StatusFlag::Zero, value == 0);
+        cpu.set_flag(StatusFlag::Negative, value > 0x7F);
+
+        cpu.default_cycles(&self.mode)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0xBB, addr),
+            _ => panic!("Addressing mode not allowed for LAS")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("LAS {}", self.mode.debug_string(cpu))
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::AbsoluteY;
+    use super::LAS;
+
+    #[test]
+    fn loads_all_three() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.stack_pointer = 0xF3;
+        cpu.index_register_y = 0x02;
+        cpu.write(&AbsoluteY(0x0010), 0x9E);
+
+        // When
+        let cycles = LAS::new(AbsoluteY(0x0010)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x92, cpu.accumulator);
+        assert_eq!(0x92, cpu.index_register_x);
+        assert_eq!(0x92, cpu.stack_pointer);
+        assert_eq!(0x80, cpu.processor_status);
+        assert_eq!(4, cycles);
+    }
+}
@@ -22,7 +22,7 @@ impl Display for LAX {
 
 impl Instruction for LAX {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem = cpu.read(&self.mode);
+        let mem = cpu.load(&self.mode);
         cpu.accumulator = mem;
         cpu.index_register_x = mem;
 
@@ -45,7 +45,7 @@ impl Instruction for LAX {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("LAX {}", self.mode.debug_string(&cpu))
+        format!("LAX {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -57,7 +57,7 @@ impl Instruction for LAX {
 mod test {
     use crate::cpu::{AddressingMode, CPU, Instruction};
     use crate::cpu::AddressingMode::ZeroPageY;
-    use crate::instructions::jmp::JumpAddressMode::Absolute;
+    
     use crate::instructions::lax::LAX;
 
     #[test]
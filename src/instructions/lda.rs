@@ -20,7 +20,7 @@ impl Display for LDA {
 
 impl Instruction for LDA {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.accumulator = cpu.read(&self.mode);
+        cpu.accumulator = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero,  cpu.accumulator == 0);
         cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
@@ -38,12 +38,13 @@ impl Instruction for LDA {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0xB9, addr),
             AddressingMode::IndirectX(addr) => vec![0xA1, addr],
             AddressingMode::IndirectY(addr) => vec![0xB1, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0xB2, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for LDA")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("LDA {}", self.mode.debug_string(&cpu))
+        format!("LDA {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -20,7 +20,7 @@ impl Display for LDX {
 
 impl Instruction for LDX {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.index_register_x = cpu.read(&self.mode);
+        cpu.index_register_x = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero,  cpu.index_register_x == 0);
         cpu.set_flag(StatusFlag::Negative, cpu.index_register_x > 0x7F);
@@ -40,7 +40,7 @@ impl Instruction for LDX {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("LDX {}", self.mode.debug_string(&cpu))
+        format!("LDX {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -20,7 +20,7 @@ impl Display for LDY {
 
 impl Instruction for LDY {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.index_register_y = cpu.read(&self.mode);
+        cpu.index_register_y = cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero,  cpu.index_register_y == 0);
         cpu.set_flag(StatusFlag::Negative, cpu.index_register_y > 0x7F);
@@ -40,7 +40,7 @@ impl Instruction for LDY {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("LDY {}", self.mode.debug_string(&cpu))
+        format!("LDY {}", self.mode.debug_string(cpu))
     }
 }
 
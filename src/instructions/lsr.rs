@@ -20,11 +20,11 @@ impl Display for LSR {
 
 impl Instruction for LSR {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let value = cpu.read(&self.mode);
+        let value = cpu.read_modify(&self.mode);
         let carry = value & 0x01 == 0x01;
         let shifted = value >> 1;
 
-        cpu.write(&self.mode, shifted);
+        cpu.write_modified(&self.mode, value, shifted);
 
         cpu.set_flag(StatusFlag::Carry, carry);
         cpu.set_flag(StatusFlag::Zero, shifted == 0);
@@ -45,7 +45,7 @@ impl Instruction for LSR {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("LSR {}", self.mode.debug_string(&cpu))
+        format!("LSR {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#LXA
+// Highly unstable:  A = X = (A OR magic) AND operand.  See CPU::magic_constant.
+pub(super) struct LXA {
+    mode: AddressingMode
+}
+
+impl LXA {
+    pub fn new(mode: AddressingMode) -> Self {
+        LXA{ mode }
+    }
+}
+
+impl Display for LXA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LXA {}", self.mode)
+    }
+}
+
+impl Instruction for LXA {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = (cpu.accumulator | cpu.magic_constant) & cpu.read(&self.mode);
+        cpu.accumulator = value;
+        cpu.index_register_x = value;
+
+        cpu.set_flag(StatusFlag::Zero, value == 0);
+        cpu.set_flag(StatusFlag::Negative, value > 0x7F);
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![0xAB, val],
+            _ => panic!("Addressing mode not allowed for LXA")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::LXA;
+
+    #[test]
+    fn loads_a_and_x() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x01;
+
+        // When
+        LXA::new(Immediate(0x0F)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x0F, cpu.accumulator);   // (0x01 | 0xEE) & 0x0F
+        assert_eq!(0x0F, cpu.index_register_x);
+        assert_eq!(0x00, cpu.processor_status);
+    }
+
+    #[test]
+    fn zero_result() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.magic_constant = 0x00;
+
+        // When
+        LXA::new(Immediate(0xFF)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x00, cpu.accumulator);
+        assert_eq!(0x00, cpu.index_register_x);
+        assert_eq!(0x02, cpu.processor_status);
+    }
+}
@@ -61,4 +61,45 @@ mod isc;
 mod slo;
 mod rla;
 mod sre;
-mod rra;
\ No newline at end of file
+mod rra;
+mod brk;
+mod anc;
+mod alr;
+mod arr;
+mod sbx;
+mod ane;
+mod lxa;
+mod las;
+mod sha;
+mod shx;
+mod shy;
+mod tas;
+mod jam;
+mod bra;
+mod phx;
+mod plx;
+mod phy;
+mod ply;
+mod stz;
+mod tsb;
+mod trb;
+
+use crate::cpu::CPU;
+use crate::cpu::AddressingMode::Absolute;
+
+// Shared by SHA, SHX, SHY and TAS.  The value is ANDed with the high byte of
+// the base address + 1, and when indexing crosses a page the high byte of the
+// target address is replaced by the value written.
+// See https://www.masswerk.at/6502/6502_instruction_set.html#illegals
+fn unstable_store(cpu: &mut CPU, base: u16, index: u8, value: u8) {
+    let address = base.wrapping_add(index as u16);
+    let value = value & ((base >> 8) as u8).wrapping_add(1);
+
+    let address = if address & 0xFF00 != base & 0xFF00 {
+        (value as u16) << 8 | (address & 0x00FF)
+    } else {
+        address
+    };
+
+    cpu.write(&Absolute(address), value);
+}
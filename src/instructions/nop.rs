@@ -43,7 +43,13 @@ impl Display for IllegalNOP {
 impl Instruction for IllegalNOP {
     fn execute(&self, cpu: &mut CPU) -> u8 {
         match &self.mode {
-            Some(m) => cpu.default_cycles(m),
+            Some(m) => {
+                // The operand is read and discarded
+                if cpu.dummy_accesses {
+                    cpu.load(m);
+                }
+                cpu.default_cycles(m)
+            },
             None => 2
         }
     }
@@ -62,7 +68,7 @@ impl Instruction for IllegalNOP {
 
     fn debug_string(&self, cpu: &CPU) -> String {
         match &self.mode {
-            Some(m) => format!("NOP {}", m.debug_string(&cpu)),
+            Some(m) => format!("NOP {}", m.debug_string(cpu)),
             None => self.to_string()
         }
     }
@@ -71,3 +77,41 @@ impl Instruction for IllegalNOP {
        true
     }
 }
+
+// The 65C02 has no unstable opcodes, everything undefined is a NOP that skips its
+// operand without reading it
+// http://6502.org/tutorials/65c02opcodes.html
+pub struct ReservedNOP {
+    opcode: u8,
+    operand: u16,
+    size: u8,
+    cycles: u8
+}
+
+impl ReservedNOP {
+    pub fn new(opcode: u8, operand: u16, size: u8, cycles: u8) -> Self {
+        ReservedNOP{ opcode, operand, size, cycles }
+    }
+}
+
+impl Display for ReservedNOP {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NOP")
+    }
+}
+
+impl Instruction for ReservedNOP {
+    fn execute(&self, _cpu: &mut CPU) -> u8 {
+        self.cycles
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bytes_for_opcode(self.opcode, self.operand);
+        bytes.truncate(self.size as usize);
+        bytes
+    }
+
+    fn illegal(&self) -> bool {
+       true
+    }
+}
@@ -20,7 +20,7 @@ impl Display for ORA {
 
 impl Instruction for ORA {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.accumulator |= cpu.read(&self.mode);
+        cpu.accumulator |= cpu.load(&self.mode);
 
         cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
         cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
@@ -38,12 +38,13 @@ impl Instruction for ORA {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x19, addr),
             AddressingMode::IndirectX(addr) => vec![0x01, addr],
             AddressingMode::IndirectY(addr) => vec![0x11, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0x12, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for ORA")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("ORA {}", self.mode.debug_string(&cpu))
+        format!("ORA {}", self.mode.debug_string(cpu))
     }
 }
 
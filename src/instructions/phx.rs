@@ -0,0 +1,44 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct PHX {}
+
+impl Display for PHX {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PHX")
+    }
+}
+
+impl Instruction for PHX {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.push_stack(cpu.index_register_x);
+
+        3
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0xDA]
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::instructions::phx::PHX;
+
+    #[test]
+    fn x_is_pushed() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x8C;
+
+        // When
+        PHX{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x8C, cpu.pop_stack());
+    }
+}
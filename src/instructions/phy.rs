@@ -0,0 +1,44 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct PHY {}
+
+impl Display for PHY {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PHY")
+    }
+}
+
+impl Instruction for PHY {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.push_stack(cpu.index_register_y);
+
+        3
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0x5A]
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::instructions::phy::PHY;
+
+    #[test]
+    fn y_is_pushed() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_y = 0x8C;
+
+        // When
+        PHY{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x8C, cpu.pop_stack());
+    }
+}
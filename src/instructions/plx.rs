@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU, StatusFlag};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct PLX {}
+
+impl Display for PLX {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PLX")
+    }
+}
+
+impl Instruction for PLX {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.index_register_x = cpu.pop_stack();
+
+        cpu.set_flag(StatusFlag::Zero, cpu.index_register_x == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.index_register_x > 0x7F);
+
+        4
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0xFA]
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::instructions::plx::PLX;
+
+    #[test]
+    fn x_is_pulled() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.push_stack(0x8C);
+
+        // When
+        PLX{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x8C, cpu.index_register_x);
+        assert_eq!(0x80, cpu.processor_status);  // Negative flag
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{Instruction, CPU, StatusFlag};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct PLY {}
+
+impl Display for PLY {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PLY")
+    }
+}
+
+impl Instruction for PLY {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.index_register_y = cpu.pop_stack();
+
+        cpu.set_flag(StatusFlag::Zero, cpu.index_register_y == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.index_register_y > 0x7F);
+
+        4
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        vec![0x7A]
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::instructions::ply::PLY;
+
+    #[test]
+    fn y_is_pulled() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.push_stack(0x8C);
+
+        // When
+        PLY{}.execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x8C, cpu.index_register_y);
+        assert_eq!(0x80, cpu.processor_status);  // Negative flag
+    }
+}
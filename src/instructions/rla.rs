@@ -20,7 +20,7 @@ impl Display for RLA {
 
 impl Instruction for RLA {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let value = cpu.read(&self.mode);
+        let value = cpu.read_modify(&self.mode);
         let new_carry = value > 0x7F;
         let mut shifted_value = value << 1;
 
@@ -28,7 +28,7 @@ impl Instruction for RLA {
             shifted_value |= 0x01;
         }
 
-        cpu.write(&self.mode, shifted_value);
+        cpu.write_modified(&self.mode, value, shifted_value);
 
         cpu.accumulator &= shifted_value;
         cpu.set_flag(StatusFlag::Carry, new_carry);
@@ -52,7 +52,7 @@ impl Instruction for RLA {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("RLA {}", self.mode.debug_string(&cpu))
+        format!("RLA {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -20,7 +20,7 @@ impl Display for ROR {
 
 impl Instruction for ROR {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let value = cpu.read(&self.mode);
+        let value = cpu.read_modify(&self.mode);
         let new_carry = value & 0x01 == 0x01;
         let mut new_value = value >> 1;
 
@@ -28,7 +28,7 @@ impl Instruction for ROR {
             new_value |= 0x80;
         }
 
-        cpu.write(&self.mode, new_value);
+        cpu.write_modified(&self.mode, value, new_value);
         cpu.set_flag(StatusFlag::Carry, new_carry);
         cpu.set_flag(StatusFlag::Zero, new_value == 0);
         cpu.set_flag(StatusFlag::Negative, new_value > 0x7F);
@@ -48,7 +48,7 @@ impl Instruction for ROR {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("ROR {}", self.mode.debug_string(&cpu))
+        format!("ROR {}", self.mode.debug_string(cpu))
     }
 }
 
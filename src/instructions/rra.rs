@@ -20,7 +20,7 @@ impl Display for RRA {
 
 impl Instruction for RRA {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let value = cpu.read(&self.mode);
+        let value = cpu.read_modify(&self.mode);
         let new_carry = value & 0x01 == 0x01;
         let mut new_value = value >> 1;
 
@@ -28,20 +28,9 @@ impl Instruction for RRA {
             new_value |= 0x80;
         }
 
-        let sum = new_value as u16 + new_carry as u16 + cpu.accumulator as u16;
-        let carry = sum > 0xFF;
-        // Looking at logic implemented here:
-        // https://github.com/bfirsh/jsnes/blob/master/src/cpu.js
-        let overflow = (cpu.accumulator ^ new_value) & 0x80 == 0 &&
-            (cpu.accumulator ^ sum as u8) & 0x80 != 0;
-
-        cpu.accumulator = sum as u8;
-
-        cpu.write(&self.mode, new_value);
-        cpu.set_flag(StatusFlag::Carry, carry);
-        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
-        cpu.set_flag(StatusFlag::Overflow, overflow);
-        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
+        cpu.write_modified(&self.mode, value, new_value);
+        cpu.set_flag(StatusFlag::Carry, new_carry);
+        cpu.add_with_carry(new_value);
 
         cpu.memory_cycles(&self.mode)
     }
@@ -60,7 +49,7 @@ impl Instruction for RRA {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("RRA {}", self.mode.debug_string(&cpu))
+        format!("RRA {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -20,9 +20,9 @@ impl Display for SAX {
 
 impl Instruction for SAX {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.write(&self.mode, cpu.accumulator & cpu.index_register_x);
+        cpu.store(&self.mode, cpu.accumulator & cpu.index_register_x);
 
-        cpu.default_cycles(&self.mode)
+        cpu.store_cycles(&self.mode)
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -36,7 +36,7 @@ impl Instruction for SAX {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("SAX {}", self.mode.debug_string(&cpu))
+        format!("SAX {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter};
-use crate::cpu::{AddressingMode, Instruction, CPU, StatusFlag};
+use crate::cpu::{AddressingMode, Instruction, CPU};
 
 // http://www.obelisk.me.uk/6502/reference.html#SBC
 pub(super) struct SBC {
@@ -25,27 +25,10 @@ impl Display for SBC {
 
 impl Instruction for SBC {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let memory_value= cpu.read(&self.mode) as u16;
-        let existing_carry = (cpu.processor_status & 0x01) as u16;
-
-        let diff = (cpu.accumulator as u16)
-            .wrapping_sub(memory_value)
-            .wrapping_sub(1 - existing_carry);
-
-        // http://nesdev.com/6502_cpu.txt
-        // http://www.6502.org/tutorials/vflag.html
-        let carry = diff <= 0xFF;
-        // See logic in:  https://github.com/bfirsh/jsnes/blob/master/src/cpu.js
-        let overflow = (cpu.accumulator ^ memory_value as u8) & 0x80 != 0 &&
-            (cpu.accumulator ^ diff as u8) & 0x80 != 0;
-
-        cpu.accumulator = diff as u8;
-        cpu.set_flag(StatusFlag::Carry, carry);
-        cpu.set_flag(StatusFlag::Zero, cpu.accumulator == 0);
-        cpu.set_flag(StatusFlag::Overflow, overflow);
-        cpu.set_flag(StatusFlag::Negative, cpu.accumulator > 0x7F);
-
-        cpu.default_cycles(&self.mode)
+        let memory_value = cpu.load(&self.mode);
+        let decimal_cycles = cpu.subtract_with_borrow(memory_value);
+
+        cpu.default_cycles(&self.mode) + decimal_cycles
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -59,12 +42,13 @@ impl Instruction for SBC {
             (false, &AddressingMode::AbsoluteY(addr)) => self.bytes_for_opcode(0xF9, addr),
             (false, &AddressingMode::IndirectX(addr)) => vec![0xE1, addr],
             (false, &AddressingMode::IndirectY(addr)) => vec![0xF1, addr],
+            (false, &AddressingMode::ZeroPageIndirect(addr)) => vec![0xF2, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for SBC")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("SBC {}", self.mode.debug_string(&cpu))
+        format!("SBC {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -179,14 +163,11 @@ mod test {
         // When
         SBC::new(Immediate(0x41)).execute(&mut cpu);
 
-        println!("Expected:  {:b}", 0xA4);
-        println!("Actual:    {:b}", cpu.processor_status);
-
         // Then
         assert_eq!(0xFF, cpu.accumulator);
         assert_eq!(0xAA, cpu.index_register_x);
         assert_eq!(0x73, cpu.index_register_y);
-        assert_eq!(0xA4, cpu.processor_status);
+        assert_eq!(0xA4, cpu.processor_status, "{:08b}", cpu.processor_status);
     }
 
     #[test]
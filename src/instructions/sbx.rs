@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction, StatusFlag};
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#SBX
+// X = (A AND X) - operand, setting flags like CMP (borrow is ignored)
+pub(super) struct SBX {
+    mode: AddressingMode
+}
+
+impl SBX {
+    pub fn new(mode: AddressingMode) -> Self {
+        SBX{ mode }
+    }
+}
+
+impl Display for SBX {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SBX {}", self.mode)
+    }
+}
+
+impl Instruction for SBX {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.read(&self.mode);
+        let and = cpu.accumulator & cpu.index_register_x;
+        cpu.index_register_x = and.wrapping_sub(value);
+
+        cpu.set_flag(StatusFlag::Carry, and >= value);
+        cpu.set_flag(StatusFlag::Zero, cpu.index_register_x == 0);
+        cpu.set_flag(StatusFlag::Negative, cpu.index_register_x > 0x7F);
+
+        2
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::Immediate(val) => vec![0xCB, val],
+            _ => panic!("Addressing mode not allowed for SBX")
+        }
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::Immediate;
+    use super::SBX;
+
+    #[test]
+    fn subtract_without_borrow() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x3F;
+        cpu.index_register_x = 0xF3;
+
+        // When
+        SBX::new(Immediate(0x02)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x31, cpu.index_register_x);
+        assert_eq!(0x3F, cpu.accumulator);
+        assert_eq!(0x01, cpu.processor_status);
+    }
+
+    #[test]
+    fn subtract_with_borrow() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x01;
+        cpu.index_register_x = 0x03;
+        cpu.processor_status = 0x01;    // Carry is ignored on input
+
+        // When
+        SBX::new(Immediate(0x02)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0xFF, cpu.index_register_x);
+        assert_eq!(0x80, cpu.processor_status);
+    }
+}
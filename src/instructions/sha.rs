@@ -0,0 +1,102 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction};
+use crate::cpu::AddressingMode::ZeroPage;
+use crate::instructions::unstable_store;
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#SHA
+// Stores A AND X AND (high byte of the base address + 1)
+pub(super) struct SHA {
+    mode: AddressingMode
+}
+
+impl SHA {
+    pub fn new(mode: AddressingMode) -> Self {
+        SHA{ mode }
+    }
+}
+
+impl Display for SHA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SHA {}", self.mode)
+    }
+}
+
+impl Instruction for SHA {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.accumulator & cpu.index_register_x;
+        let index = cpu.index_register_y;
+
+        match self.mode {
+            AddressingMode::AbsoluteY(base) => {
+                unstable_store(cpu, base, index, value);
+                5
+            },
+            AddressingMode::IndirectY(addr) => {
+                let base = u16::from_le_bytes([
+                    cpu.read(&ZeroPage(addr)),
+                    cpu.read(&ZeroPage(addr.wrapping_add(1)))
+                ]);
+                unstable_store(cpu, base, index, value);
+                6
+            },
+            _ => panic!("Addressing mode not allowed for SHA")
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x9F, addr),
+            AddressingMode::IndirectY(addr) => vec![0x93, addr],
+            _ => panic!("Addressing mode not allowed for SHA")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("SHA {}", self.mode.debug_string(cpu))
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::{Absolute, AbsoluteY, IndirectY, ZeroPage};
+    use super::SHA;
+
+    #[test]
+    fn absolute_y_store() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xFF;
+        cpu.index_register_x = 0xF7;
+        cpu.index_register_y = 0x10;
+
+        // When
+        let cycles = SHA::new(AbsoluteY(0x0400)).execute(&mut cpu);
+
+        // Then  (0xFF & 0xF7 & 0x05)
+        assert_eq!(0x05, cpu.read(&Absolute(0x0410)));
+        assert_eq!(5, cycles);
+    }
+
+    #[test]
+    fn indirect_y_store() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x3F;
+        cpu.index_register_x = 0xFF;
+        cpu.index_register_y = 0x01;
+        cpu.write(&ZeroPage(0x20), 0x00);
+        cpu.write(&ZeroPage(0x21), 0x06);
+
+        // When
+        let cycles = SHA::new(IndirectY(0x20)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x07, cpu.read(&Absolute(0x0601)));
+        assert_eq!(6, cycles);
+    }
+}
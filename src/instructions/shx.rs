@@ -0,0 +1,86 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction};
+use crate::instructions::unstable_store;
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#SHX
+// Stores X AND (high byte of the base address + 1)
+pub(super) struct SHX {
+    mode: AddressingMode
+}
+
+impl SHX {
+    pub fn new(mode: AddressingMode) -> Self {
+        SHX{ mode }
+    }
+}
+
+impl Display for SHX {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SHX {}", self.mode)
+    }
+}
+
+impl Instruction for SHX {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        match self.mode {
+            AddressingMode::AbsoluteY(base) => {
+                let (index, value) = (cpu.index_register_y, cpu.index_register_x);
+                unstable_store(cpu, base, index, value);
+                5
+            },
+            _ => panic!("Addressing mode not allowed for SHX")
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x9E, addr),
+            _ => panic!("Addressing mode not allowed for SHX")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("SHX {}", self.mode.debug_string(cpu))
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::{Absolute, AbsoluteY};
+    use super::SHX;
+
+    #[test]
+    fn store_masked_by_high_byte() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0xFF;
+        cpu.index_register_y = 0x10;
+
+        // When
+        let cycles = SHX::new(AbsoluteY(0x0400)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x05, cpu.read(&Absolute(0x0410)));
+        assert_eq!(5, cycles);
+    }
+
+    #[test]
+    fn page_cross_corrupts_address() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.index_register_x = 0x01;
+        cpu.index_register_y = 0x20;
+
+        // When
+        SHX::new(AbsoluteY(0x02F0)).execute(&mut cpu);
+
+        // Then  (value 0x01 & 0x03 replaces the high byte of $0310)
+        assert_eq!(0x01, cpu.read(&Absolute(0x0110)));
+        assert_eq!(0x00, cpu.read(&Absolute(0x0310)));
+    }
+}
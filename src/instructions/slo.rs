@@ -20,10 +20,10 @@ impl Display for SLO {
 
 impl Instruction for SLO {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let mem_value = cpu.read(&self.mode);
+        let mem_value = cpu.read_modify(&self.mode);
         let shifted_value = mem_value << 1;
 
-        cpu.write(&self.mode, shifted_value);
+        cpu.write_modified(&self.mode, mem_value, shifted_value);
         cpu.accumulator |= shifted_value;
 
         cpu.set_flag(StatusFlag::Carry, mem_value > 0x7F);
@@ -47,7 +47,7 @@ impl Instruction for SLO {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("SLO {}", self.mode.debug_string(&cpu))
+        format!("SLO {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
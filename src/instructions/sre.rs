@@ -20,11 +20,11 @@ impl Display for SRE {
 
 impl Instruction for SRE {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        let value = cpu.read(&self.mode);
+        let value = cpu.read_modify(&self.mode);
         let carry = value & 0x01 == 0x01;
         let shifted = value >> 1;
 
-        cpu.write(&self.mode, shifted);
+        cpu.write_modified(&self.mode, value, shifted);
         cpu.accumulator ^= shifted;
 
 
@@ -49,7 +49,7 @@ impl Instruction for SRE {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("SRE {}", self.mode.debug_string(&cpu))
+        format!("SRE {}", self.mode.debug_string(cpu))
     }
 
     fn illegal(&self) -> bool {
@@ -60,7 +60,7 @@ impl Instruction for SRE {
 #[cfg(test)]
 mod test {
     use crate::cpu::{AddressingMode, CPU, Instruction};
-    use crate::cpu::AddressingMode::IndirectX;
+    
     use crate::instructions::sre::SRE;
 
     #[test]
@@ -20,18 +20,9 @@ impl Display for STA {
 
 impl Instruction for STA {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.write(&self.mode, cpu.accumulator);
+        cpu.store(&self.mode, cpu.accumulator);
 
-        match self.mode {
-            AddressingMode::ZeroPage(_) => 3,
-            AddressingMode::ZeroPageX(_) => 4,
-            AddressingMode::Absolute(_) => 4,
-            AddressingMode::AbsoluteX(_) => 5,
-            AddressingMode::AbsoluteY(_) => 5,
-            AddressingMode::IndirectX(_) => 6,
-            AddressingMode::IndirectY(_) => 6,
-            _ => panic!("Invalid addressing mode for STA")
-        }
+        cpu.store_cycles(&self.mode)
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -43,12 +34,13 @@ impl Instruction for STA {
             AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x99, addr),
             AddressingMode::IndirectX(addr) => vec![0x81, addr],
             AddressingMode::IndirectY(addr) => vec![0x91, addr],
+            AddressingMode::ZeroPageIndirect(addr) => vec![0x92, addr],   // 65C02 only
             _ => panic!("Addressing mode not allowed for STA")
         }
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("STA {}", self.mode.debug_string(&cpu))
+        format!("STA {}", self.mode.debug_string(cpu))
     }
 }
 
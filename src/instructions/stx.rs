@@ -20,9 +20,9 @@ impl Display for STX {
 
 impl Instruction for STX {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.write(&self.mode, cpu.index_register_x);
+        cpu.store(&self.mode, cpu.index_register_x);
 
-        cpu.default_cycles(&self.mode)
+        cpu.store_cycles(&self.mode)
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -35,7 +35,7 @@ impl Instruction for STX {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("STX {}", self.mode.debug_string(&cpu))
+        format!("STX {}", self.mode.debug_string(cpu))
     }
 }
 
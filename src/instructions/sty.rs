@@ -20,9 +20,9 @@ impl Display for STY {
 
 impl Instruction for STY {
     fn execute(&self, cpu: &mut CPU) -> u8 {
-        cpu.write(&self.mode, cpu.index_register_y);
+        cpu.store(&self.mode, cpu.index_register_y);
 
-        cpu.default_cycles(&self.mode)
+        cpu.store_cycles(&self.mode)
     }
 
     fn bytes(&self) -> Vec<u8> {
@@ -35,7 +35,7 @@ impl Instruction for STY {
     }
 
     fn debug_string(&self, cpu: &CPU) -> String {
-        format!("STY {}", self.mode.debug_string(&cpu))
+        format!("STY {}", self.mode.debug_string(cpu))
     }
 }
 
@@ -0,0 +1,69 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, Instruction, CPU};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct STZ {
+    mode: AddressingMode
+}
+
+impl STZ {
+    pub fn new(mode: AddressingMode) -> Self {
+        STZ{ mode }
+    }
+}
+
+impl Display for STZ {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "STZ {}", self.mode)
+    }
+}
+
+impl Instruction for STZ {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.store(&self.mode, 0x00);
+
+        cpu.store_cycles(&self.mode)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::ZeroPage(addr) => vec![0x64, addr],
+            AddressingMode::ZeroPageX(addr) => vec![0x74, addr],
+            AddressingMode::Absolute(addr) => self.bytes_for_opcode(0x9C, addr),
+            AddressingMode::AbsoluteX(addr) => self.bytes_for_opcode(0x9E, addr),
+            _ => panic!("Addressing mode not allowed for STZ")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("STZ {}", self.mode.debug_string(cpu))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::{AbsoluteX, ZeroPage};
+    use super::STZ;
+
+    #[test]
+    fn zero_is_stored() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xA7;
+        cpu.write(&ZeroPage(0x88), 0x42);
+
+        // When
+        let cycles = STZ::new(ZeroPage(0x88)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x00, cpu.read(&ZeroPage(0x88)));
+        assert_eq!(3, cycles);
+    }
+
+    #[test]
+    fn bytes_representation() {
+        assert_eq!(vec![0x9E, 0x45, 0x02], STZ::new(AbsoluteX(0x0245)).bytes());
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, CPU, Instruction};
+use crate::instructions::unstable_store;
+
+// https://www.masswerk.at/6502/6502_instruction_set.html#TAS
+// SP = A AND X, then stores SP AND (high byte of the base address + 1)
+pub(super) struct TAS {
+    mode: AddressingMode
+}
+
+impl TAS {
+    pub fn new(mode: AddressingMode) -> Self {
+        TAS{ mode }
+    }
+}
+
+impl Display for TAS {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TAS {}", self.mode)
+    }
+}
+
+impl Instruction for TAS {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        cpu.stack_pointer = cpu.accumulator & cpu.index_register_x;
+
+        match self.mode {
+            AddressingMode::AbsoluteY(base) => {
+                let (index, value) = (cpu.index_register_y, cpu.stack_pointer);
+                unstable_store(cpu, base, index, value);
+                5
+            },
+            _ => panic!("Addressing mode not allowed for TAS")
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::AbsoluteY(addr) => self.bytes_for_opcode(0x9B, addr),
+            _ => panic!("Addressing mode not allowed for TAS")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("TAS {}", self.mode.debug_string(cpu))
+    }
+
+    fn illegal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::{Absolute, AbsoluteY};
+    use super::TAS;
+
+    #[test]
+    fn sets_stack_pointer_and_stores() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0xF6;
+        cpu.index_register_x = 0x3F;
+        cpu.index_register_y = 0x01;
+
+        // When
+        let cycles = TAS::new(AbsoluteY(0x0700)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x36, cpu.stack_pointer);
+        assert_eq!(0x00, cpu.read(&Absolute(0x0701)));  // 0x36 & 0x08
+        assert_eq!(5, cycles);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt::{Display, Formatter};
+use crate::cpu::{AddressingMode, Instruction, CPU, StatusFlag};
+
+// 65C02 only
+// http://6502.org/tutorials/65c02opcodes.html
+pub(super) struct TRB {
+    mode: AddressingMode
+}
+
+impl TRB {
+    pub fn new(mode: AddressingMode) -> Self {
+        TRB{ mode }
+    }
+}
+
+impl Display for TRB {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TRB {}", self.mode)
+    }
+}
+
+impl Instruction for TRB {
+    fn execute(&self, cpu: &mut CPU) -> u8 {
+        let value = cpu.read(&self.mode);
+        cpu.set_flag(StatusFlag::Zero, cpu.accumulator & value == 0);
+        cpu.write(&self.mode, value & !cpu.accumulator);
+
+        cpu.memory_cycles(&self.mode)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self.mode {
+            AddressingMode::ZeroPage(addr) => vec![0x14, addr],
+            AddressingMode::Absolute(addr) => self.bytes_for_opcode(0x1C, addr),
+            _ => panic!("Addressing mode not allowed for TRB")
+        }
+    }
+
+    fn debug_string(&self, cpu: &CPU) -> String {
+        format!("TRB {}", self.mode.debug_string(cpu))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{CPU, Instruction};
+    use crate::cpu::AddressingMode::{Absolute, ZeroPage};
+    use super::TRB;
+
+    #[test]
+    fn bits_are_reset() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x0F;
+        cpu.write(&ZeroPage(0x20), 0x35);
+
+        // When
+        let cycles = TRB::new(ZeroPage(0x20)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x30, cpu.read(&ZeroPage(0x20)));
+        assert_eq!(0x00, cpu.processor_status);
+        assert_eq!(5, cycles);
+    }
+
+    #[test]
+    fn zero_flag_from_and() {
+        // Given
+        let mut cpu = CPU::empty();
+        cpu.accumulator = 0x0F;
+        cpu.write(&Absolute(0x0300), 0xF0);
+
+        // When
+        TRB::new(Absolute(0x0300)).execute(&mut cpu);
+
+        // Then
+        assert_eq!(0x02, cpu.processor_status);  // Zero flag
+    }
+}
@@ -1,13 +1,26 @@
+#![allow(clippy::upper_case_acronyms)]
+
 mod cpu;
 mod instructions;
 mod rom;
 mod commands;
 mod bus;
+mod mappers;
+mod ppu;
+mod save;
+mod flat_ram;
+mod single_step;
+mod trace;
+mod binary_trace;
+mod trace_diff;
 
 extern crate clap;
-use clap::{App, Arg, SubCommand};
+use std::process;
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
 
-use crate::commands::{Info, Command, Log};
+use crate::commands::{Info, Command, Log, LogFormat, DiffLog, Benchmark, Screenshot, RunRaw, SingleStep, TraceQuery, Query, CpuOptions};
+use crate::cpu::Variant;
+use crate::trace::{LogOptions, StopCondition};
 
 fn main() {
     let app = App::new("NES Play")
@@ -17,24 +30,377 @@ fn main() {
             .about("Show ROM info")
             .arg(Arg::with_name("ROM").required(true))
         )
-        .subcommand(SubCommand::with_name("log")
+        .subcommand(cpu_args(save_args(SubCommand::with_name("log")))
             .about("Generate execution log for ROM")
             .arg(Arg::with_name("ROM").required(true))
-            .arg(Arg::with_name("LOG").required(true))
+            .arg(Arg::with_name("LOG").help("File to write the log to [default: stdout]"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["nestest", "mesen", "fceux", "jsonl", "binary"])
+                .default_value("nestest")
+                .help("Trace layout:  nestest.log, Mesen or FCEUX trace loggers, JSON Lines, or a compact binary trace for trace-query"))
+            .arg(Arg::with_name("max-instructions")
+                .long("max-instructions")
+                .takes_value(true)
+                .value_name("COUNT")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not an instruction count", v)))
+                .help("Stop after COUNT instructions"))
+            .arg(Arg::with_name("max-cycles")
+                .long("max-cycles")
+                .takes_value(true)
+                .value_name("CYCLES")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not a cycle count", v)))
+                .help("Stop once the CPU has run CYCLES cycles"))
+            .arg(Arg::with_name("stop-at")
+                .long("stop-at")
+                .takes_value(true)
+                .value_name("ADDR")
+                .validator(|v| parse_address(&v).map(|_| ()))
+                .help("Stop when PC reaches ADDR (hex), without running it"))
+            .arg(Arg::with_name("stop-when")
+                .long("stop-when")
+                .takes_value(true)
+                .value_name("ADDR=BYTE")
+                .validator(|v| parse_watch(&v).map(|_| ()))
+                .help("Stop when the RAM or cartridge address ADDR holds BYTE, e.g. 6000=80"))
+            .arg(Arg::with_name("stop-on-loop")
+                .long("stop-on-loop")
+                .help("Stop at an instruction that jumps or branches to itself"))
+            .arg(Arg::with_name("range")
+                .long("range")
+                .takes_value(true)
+                .value_name("START-END")
+                .validator(|v| parse_range(&v).map(|_| ()))
+                .help("Only log instructions with PC in START-END (hex, inclusive)"))
+            .arg(Arg::with_name("trigger")
+                .long("trigger")
+                .takes_value(true)
+                .value_name("ADDR")
+                .validator(|v| parse_address(&v).map(|_| ()))
+                .help("Only start logging once PC reaches ADDR (hex)"))
+        )
+        .subcommand(SubCommand::with_name("trace-query")
+            .about("Search or convert a binary trace written by log --format binary")
+            .arg(Arg::with_name("TRACE").required(true))
+            .arg(Arg::with_name("first-write")
+                .long("first-write")
+                .takes_value(true)
+                .value_name("ADDR")
+                .validator(|v| parse_address(&v).map(|_| ()))
+                .help("Show the first instruction that wrote to ADDR (hex)"))
+            .arg(Arg::with_name("pc")
+                .long("pc")
+                .takes_value(true)
+                .value_name("ADDR")
+                .validator(|v| parse_address(&v).map(|_| ()))
+                .help("Show every time the instruction at ADDR (hex) ran"))
+            .arg(Arg::with_name("cycles")
+                .long("cycles")
+                .takes_value(true)
+                .value_name("START-END")
+                .validator(|v| parse_cycle_range(&v).map(|_| ()))
+                .help("Show the instructions that started between two cycle counts (inclusive)"))
+            .group(ArgGroup::with_name("query")
+                .args(&["first-write", "pc", "cycles"]))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["nestest", "mesen", "fceux", "jsonl"])
+                .default_value("nestest")
+                .help("Text layout for the instructions shown, converts the whole trace without a query"))
+        )
+        .subcommand(cpu_args(SubCommand::with_name("diff-log"))
+            .about("Trace ROM against a golden log and report the first difference")
+            .arg(Arg::with_name("ROM").required(true))
+            .arg(Arg::with_name("GOLDEN").required(true))
+            .arg(Arg::with_name("context")
+                .long("context")
+                .takes_value(true)
+                .value_name("LINES")
+                .default_value("3")
+                .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|_| format!("'{}' is not a line count", v)))
+                .help("Matching lines to show before the first difference"))
+        )
+        .subcommand(SubCommand::with_name("benchmark")
+            .about("Measure instructions per second for ROM")
+            .arg(Arg::with_name("ROM").required(true))
+            .arg(Arg::with_name("instructions")
+                .long("instructions")
+                .takes_value(true)
+                .value_name("COUNT")
+                .default_value("10000000")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not an instruction count", v)))
+                .help("Number of instructions to run, boxed and then through the opcode table"))
+            .arg(start_pc_arg())
+            .arg(cpu_arg("2a03"))
+        )
+        .subcommand(save_args(SubCommand::with_name("screenshot"))
+            .about("Run ROM headless and save a frame as a PPM image")
+            .arg(Arg::with_name("ROM").required(true))
+            .arg(Arg::with_name("IMAGE").required(true))
+            .arg(Arg::with_name("frames")
+                .long("frames")
+                .takes_value(true)
+                .value_name("COUNT")
+                .default_value("60")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not a frame count", v)))
+                .help("Save the picture once COUNT frames have been drawn"))
+            .arg(start_pc_arg())
+        )
+        .subcommand(SubCommand::with_name("run-raw")
+            .about("Run a plain 6502 binary on 64KB of flat RAM until it traps")
+            .arg(Arg::with_name("BINARY").required(true))
+            .arg(Arg::with_name("load-address")
+                .long("load-address")
+                .takes_value(true)
+                .value_name("ADDR")
+                .default_value("0000")
+                .validator(|v| parse_address(&v).map(|_| ()))
+                .help("Address (hex) to load BINARY at"))
+            .arg(start_pc_arg()
+                .help("Start execution at ADDR (hex) instead of the reset vector, e.g. 0400 for Klaus Dormann's tests"))
+            .arg(Arg::with_name("max-instructions")
+                .long("max-instructions")
+                .takes_value(true)
+                .value_name("COUNT")
+                .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not an instruction count", v)))
+                .help("Stop after COUNT instructions if the program has not trapped"))
+            .arg(cpu_arg("6502"))
+        )
+        .subcommand(SubCommand::with_name("single-step")
+            .about("Run single step CPU tests in the SingleStepTests JSON format")
+            .arg(Arg::with_name("TESTS")
+                .required(true)
+                .multiple(true)
+                .help("JSON test files, or directories of them"))
+            .arg(Arg::with_name("bus-accesses")
+                .long("bus-accesses")
+                .help("Also compare every bus read and write, not just the cycle count"))
+            .arg(cpu_arg("2a03")
+                .help("CPU to emulate:  2a03 for the nes6502 tests, 6502 or 65c02 for the 65x02 tests"))
         );
 
     let matches = app.get_matches();
 
-    if let Some(matches) = matches.subcommand_matches("info") {
+    let command: Option<Box<dyn Command>> = if let Some(matches) = matches.subcommand_matches("info") {
         let filename = matches.value_of("ROM").unwrap();
-        Info::new(filename).execute();
-    }
+        Some(Box::new(Info::new(filename)))
+    } else if let Some(matches) = matches.subcommand_matches("log") {
+        let rom_filename = matches.value_of("ROM").unwrap();
+        let log_filename = matches.value_of("LOG");
+        let format = match matches.value_of("format").unwrap() {
+            "binary" => LogFormat::Binary,
+            name => LogFormat::Text(trace::formatter(name).unwrap())
+        };
+
+        let mut stop_conditions = Vec::new();
+        if let Some(count) = matches.value_of("max-instructions") {
+            stop_conditions.push(StopCondition::Instructions(count.parse().unwrap()));
+        }
+        if let Some(cycles) = matches.value_of("max-cycles") {
+            stop_conditions.push(StopCondition::Cycles(cycles.parse().unwrap()));
+        }
+        if let Some(address) = matches.value_of("stop-at") {
+            stop_conditions.push(StopCondition::ProgramCounter(parse_address(address).unwrap()));
+        }
+        if let Some(watch) = matches.value_of("stop-when") {
+            let (address, value) = parse_watch(watch).unwrap();
+            stop_conditions.push(StopCondition::MemoryValue(address, value));
+        }
+        let log_options = LogOptions {
+            stop_conditions,
+            stop_on_loop: matches.is_present("stop-on-loop"),
+            range: matches.value_of("range").map(|v| parse_range(v).unwrap()),
+            trigger: matches.value_of("trigger").map(|v| parse_address(v).unwrap())
+        };
 
-    if let Some(matches) = matches.subcommand_matches("log") {
+        let save_dir = matches.value_of("save-dir");
+        let clean_save = matches.is_present("clean-save");
+        let cpu_options = cpu_options(matches);
+
+        Some(Box::new(Log::new(rom_filename, log_filename, save_dir, clean_save, format, log_options, cpu_options)))
+    } else if let Some(matches) = matches.subcommand_matches("trace-query") {
+        let trace_filename = matches.value_of("TRACE").unwrap();
+        let formatter = trace::formatter(matches.value_of("format").unwrap()).unwrap();
+        let query = if let Some(address) = matches.value_of("first-write") {
+            Query::FirstWrite(parse_address(address).unwrap())
+        } else if let Some(pc) = matches.value_of("pc") {
+            Query::ProgramCounter(parse_address(pc).unwrap())
+        } else if let Some(cycles) = matches.value_of("cycles") {
+            let (start, end) = parse_cycle_range(cycles).unwrap();
+            Query::Cycles(start, end)
+        } else {
+            Query::All
+        };
+
+        Some(Box::new(TraceQuery::new(trace_filename, query, formatter)))
+    } else if let Some(matches) = matches.subcommand_matches("diff-log") {
+        let rom_filename = matches.value_of("ROM").unwrap();
+        let golden_filename = matches.value_of("GOLDEN").unwrap();
+        let context = matches.value_of("context").unwrap().parse().unwrap();
+        let cpu_options = cpu_options(matches);
+
+        Some(Box::new(DiffLog::new(rom_filename, golden_filename, context, cpu_options)))
+    } else if let Some(matches) = matches.subcommand_matches("benchmark") {
+        let rom_filename = matches.value_of("ROM").unwrap();
+        let instructions = matches.value_of("instructions").unwrap().parse().unwrap();
+        let cpu_options = cpu_options(matches);
+
+        Some(Box::new(Benchmark::new(rom_filename, instructions, cpu_options)))
+    } else if let Some(matches) = matches.subcommand_matches("screenshot") {
         let rom_filename = matches.value_of("ROM").unwrap();
-        let log_filename = matches.value_of("LOG").unwrap();
+        let image_filename = matches.value_of("IMAGE").unwrap();
+        let frames = matches.value_of("frames").unwrap().parse().unwrap();
+        let save_dir = matches.value_of("save-dir");
+        let clean_save = matches.is_present("clean-save");
+        let cpu_options = cpu_options(matches);
+
+        Some(Box::new(Screenshot::new(rom_filename, image_filename, frames, save_dir, clean_save, cpu_options)))
+    } else if let Some(matches) = matches.subcommand_matches("run-raw") {
+        let binary_filename = matches.value_of("BINARY").unwrap();
+        let load_address = parse_address(matches.value_of("load-address").unwrap()).unwrap();
+        let max_instructions = matches.value_of("max-instructions").map(|v| v.parse().unwrap());
+        let cpu_options = cpu_options(matches);
+
+        Some(Box::new(RunRaw::new(binary_filename, load_address, max_instructions, cpu_options)))
+    } else if let Some(matches) = matches.subcommand_matches("single-step") {
+        let paths = matches.values_of("TESTS").unwrap().map(String::from).collect();
+        let check_bus = matches.is_present("bus-accesses");
+        // The tests record real hardware, dummy accesses included
+        let cpu_options = CpuOptions {
+            dummy_accesses: true,
+            ..cpu_options(matches)
+        };
+
+        Some(Box::new(SingleStep::new(paths, check_bus, cpu_options)))
+    } else {
+        None
+    };
+
+    if let Some(command) = command {
+        if let Err(e) = command.execute() {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// Battery save file options for the commands that play a game
+fn save_args<'a, 'b>(command: App<'a, 'b>) -> App<'a, 'b> {
+    command
+        .arg(Arg::with_name("save-dir")
+            .long("save-dir")
+            .takes_value(true)
+            .help("Directory for battery save files (defaults to the ROM's directory)"))
+        .arg(Arg::with_name("clean-save")
+            .long("clean-save")
+            .help("Ignore any existing save file and start with empty save RAM"))
+}
+
+fn start_pc_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("start-pc")
+        .long("start-pc")
+        .takes_value(true)
+        .value_name("ADDR")
+        .validator(|v| parse_address(&v).map(|_| ()))
+        .help("Start execution at ADDR (hex) instead of the reset vector, e.g. C000 for nestest")
+}
+
+fn cpu_arg<'a, 'b>(default: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("cpu")
+        .long("cpu")
+        .takes_value(true)
+        .value_name("VARIANT")
+        .possible_values(&["2a03", "6502", "65c02"])
+        .default_value(default)
+        .help("CPU to emulate:  the NES 2A03, an NMOS 6502 with decimal mode, or a 65C02")
+}
+
+// CPU options for the commands that trace a ROM, to line it up with a reference log
+fn cpu_args<'a, 'b>(command: App<'a, 'b>) -> App<'a, 'b> {
+    command
+        .arg(start_pc_arg())
+        .arg(Arg::with_name("start-cycle")
+            .long("start-cycle")
+            .takes_value(true)
+            .value_name("CYCLES")
+            .validator(|v| v.parse::<u64>().map(|_| ()).map_err(|_| format!("'{}' is not a cycle count", v)))
+            .help("CPU cycle count to start the CYC column from [default: 7, after the reset sequence]"))
+        .arg(Arg::with_name("dummy-accesses")
+            .long("dummy-accesses")
+            .help("Perform the dummy reads and writes real hardware makes on indexed and read-modify-write instructions"))
+        .arg(Arg::with_name("magic")
+            .long("magic")
+            .takes_value(true)
+            .value_name("BYTE")
+            .validator(|v| parse_byte(&v).map(|_| ()))
+            .help("Magic constant (hex) used by the unstable ANE and LXA opcodes [default: EE]"))
+        .arg(cpu_arg("2a03"))
+}
+
+// Options a command doesn't take are left at their defaults
+fn cpu_options(matches: &ArgMatches) -> CpuOptions {
+    CpuOptions {
+        start_pc: matches.value_of("start-pc").map(|v| parse_address(v).unwrap()),
+        start_cycle: matches.value_of("start-cycle").map(|v| v.parse().unwrap()),
+        dummy_accesses: matches.is_present("dummy-accesses"),
+        magic: matches.value_of("magic").map(|v| parse_byte(v).unwrap()),
+        variant: matches.value_of("cpu").map(parse_variant)
+    }
+}
+
+// Accepts C000, $C000 or 0xC000
+fn parse_address(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16)
+        .map_err(|_| format!("'{}' is not a 16-bit hex address", value))
+}
+
+fn parse_byte(value: &str) -> Result<u8, String> {
+    let digits = value.trim_start_matches('$').trim_start_matches("0x");
+    u8::from_str_radix(digits, 16)
+        .map_err(|_| format!("'{}' is not an 8-bit hex value", value))
+}
+
+// 6000=80
+fn parse_watch(value: &str) -> Result<(u16, u8), String> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(address), Some(byte)) => Ok((parse_address(address)?, parse_byte(byte)?)),
+        _ => Err(format!("'{}' is not ADDR=BYTE", value))
+    }
+}
+
+// C000-C0FF
+fn parse_range(value: &str) -> Result<(u16, u16), String> {
+    let mut parts = value.splitn(2, '-');
+    match (parts.next(), parts.next()) {
+        (Some(start), Some(end)) => {
+            let (start, end) = (parse_address(start)?, parse_address(end)?);
+            if start > end {
+                return Err(format!("'{}' ends before it starts", value));
+            }
+            Ok((start, end))
+        },
+        _ => Err(format!("'{}' is not START-END", value))
+    }
+}
+
+// Decimal, 1000-2000
+fn parse_cycle_range(value: &str) -> Result<(u64, u64), String> {
+    let mut parts = value.splitn(2, '-');
+    match (parts.next().map(str::parse::<u64>), parts.next().map(str::parse::<u64>)) {
+        (Some(Ok(start)), Some(Ok(end))) if start <= end => Ok((start, end)),
+        _ => Err(format!("'{}' is not START-END in cycles", value))
+    }
+}
 
-        let command = Log::new(rom_filename, log_filename);
-        command.execute();
+// Only called with the CLI's possible values
+fn parse_variant(value: &str) -> Variant {
+    match value {
+        "6502" => Variant::Nmos6502,
+        "65c02" => Variant::Cmos65C02,
+        _ => Variant::Ricoh2A03
     }
 }
@@ -0,0 +1,116 @@
+use crate::mappers::{Mapper, chr_memory, has_bus_conflicts, prg_ram, read_prg_ram, write_prg_ram};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=AxROM
+pub struct AxROM {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    bus_conflicts: bool,
+    register: u8
+}
+
+impl AxROM {
+    pub fn new(rom: &INesRom) -> Self {
+        AxROM {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            bus_conflicts: has_bus_conflicts(rom),
+            register: 0
+        }
+    }
+}
+
+impl Mapper for AxROM {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            0x8000 ..= 0xFFFF => {
+                let bank = (self.register & 0x07) as usize;
+                self.prg_rom[(bank * 0x8000 + (addr - 0x8000) as usize) % self.prg_rom.len()]
+            },
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF => write_prg_ram(&mut self.prg_ram, addr, data),
+            0x8000 ..= 0xFFFF => self.register = if self.bus_conflicts { data & self.cpu_read(addr) } else { data },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_writable {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.register & 0x10 == 0 {
+            Mirroring::SingleScreenA
+        } else {
+            Mirroring::SingleScreenB
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use crate::rom::Mirroring;
+    use super::AxROM;
+
+    #[test]
+    fn switch_32k_bank() {
+        // Given
+        let mut axrom = AxROM::new(&test_rom(7, 0, banked(0x40000, 0x8000), vec![], 0));
+
+        // Then
+        assert_eq!(0, axrom.cpu_read(0xFFFC));
+
+        // When
+        axrom.cpu_write(0x8000, 0x05);
+
+        // Then
+        assert_eq!(5, axrom.cpu_read(0x8000));
+        assert_eq!(5, axrom.cpu_read(0xFFFF));
+    }
+
+    #[test]
+    fn single_screen_mirroring() {
+        // Given
+        let mut axrom = AxROM::new(&test_rom(7, 0, banked(0x40000, 0x8000), vec![], 0));
+
+        // Then
+        assert_eq!(Mirroring::SingleScreenA, axrom.mirroring());
+
+        // When
+        axrom.cpu_write(0x8000, 0x10);
+
+        // Then
+        assert_eq!(Mirroring::SingleScreenB, axrom.mirroring());
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        // Given bank 0 is all 0s, so nothing gets through
+        let mut axrom = AxROM::new(&test_rom(7, 2, banked(0x40000, 0x8000), vec![], 0));
+
+        // When
+        axrom.cpu_write(0x8000, 0x13);
+
+        // Then
+        assert_eq!(0, axrom.cpu_read(0x8000));
+        assert_eq!(Mirroring::SingleScreenA, axrom.mirroring());
+    }
+}
@@ -0,0 +1,199 @@
+use std::ops::Range;
+use crate::mappers::{Mapper, chr_memory, has_bus_conflicts, prg_ram, prg_nvram, read_prg_ram, write_prg_ram, battery_ram, battery_ram_mut};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=INES_Mapper_034#BNROM
+pub struct BNROM {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: u8
+}
+
+impl BNROM {
+    pub fn new(rom: &INesRom) -> Self {
+        BNROM {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            mirroring: rom.header.mirroring(),
+            bus_conflicts: has_bus_conflicts(rom),
+            prg_bank: 0
+        }
+    }
+}
+
+impl Mapper for BNROM {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            0x8000 ..= 0xFFFF => {
+                let bank = self.prg_bank as usize;
+                self.prg_rom[(bank * 0x8000 + (addr - 0x8000) as usize) % self.prg_rom.len()]
+            },
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF => write_prg_ram(&mut self.prg_ram, addr, data),
+            0x8000 ..= 0xFFFF => self.prg_bank = if self.bus_conflicts { data & self.cpu_read(addr) } else { data },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_writable {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+// https://wiki.nesdev.org/w/index.php?title=INES_Mapper_034#NINA-001
+pub struct NINA001 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    nvram: Range<usize>,
+    chr: Vec<u8>,
+    mirroring: Mirroring,
+    prg_bank: u8,
+    chr_banks: [u8; 2]
+}
+
+impl NINA001 {
+    pub fn new(rom: &INesRom) -> Self {
+        let prg_ram = prg_ram(rom);
+
+        NINA001 {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: if prg_ram.is_empty() { vec![0; 0x2000] } else { prg_ram },
+            nvram: prg_nvram(rom),
+            chr: chr_memory(rom),
+            mirroring: rom.header.mirroring(),
+            prg_bank: 0,
+            chr_banks: [0, 0]
+        }
+    }
+}
+
+impl Mapper for NINA001 {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            0x8000 ..= 0xFFFF => {
+                let bank = (self.prg_bank & 0x01) as usize;
+                self.prg_rom[(bank * 0x8000 + (addr - 0x8000) as usize) % self.prg_rom.len()]
+            },
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        // The registers sit on top of the last bytes of PRG RAM, which still get written
+        if let 0x6000 ..= 0x7FFF = addr {
+            write_prg_ram(&mut self.prg_ram, addr, data);
+        }
+
+        match addr {
+            0x7FFD => self.prg_bank = data,
+            0x7FFE => self.chr_banks[0] = data,
+            0x7FFF => self.chr_banks[1] = data,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank = self.chr_banks[(addr as usize >> 12) & 1] as usize & 0x0F;
+        self.chr[(bank * 0x1000 + (addr & 0x0FFF) as usize) % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        battery_ram(&self.prg_ram, &self.nvram)
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        battery_ram_mut(&mut self.prg_ram, &self.nvram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use super::{BNROM, NINA001};
+
+    #[test]
+    fn bnrom_switch_32k_bank() {
+        // Given
+        let mut bnrom = BNROM::new(&test_rom(34, 0, banked(0x20000, 0x8000), vec![], 0));
+
+        // When
+        bnrom.cpu_write(0x8000, 0x03);
+
+        // Then
+        assert_eq!(3, bnrom.cpu_read(0x8000));
+        assert_eq!(3, bnrom.cpu_read(0xFFFF));
+    }
+
+    #[test]
+    fn bnrom_bus_conflicts() {
+        // Given the byte at $8000 is 1, so only bit 0 of a write there gets through
+        let mut prg_rom = banked(0x20000, 0x8000);
+        prg_rom[0x0000] = 0x01;
+        let mut bnrom = BNROM::new(&test_rom(34, 2, prg_rom, vec![], 0));
+
+        // When
+        bnrom.cpu_write(0x8000, 0x03);
+
+        // Then
+        assert_eq!(1, bnrom.cpu_read(0x8001));
+    }
+
+    #[test]
+    fn nina001_switch_banks() {
+        // Given
+        let mut nina = NINA001::new(&test_rom(34, 1, banked(0x10000, 0x8000), banked(0x10000, 0x1000), 0));
+
+        // When
+        nina.cpu_write(0x7FFD, 0x01);
+        nina.cpu_write(0x7FFE, 0x05);
+        nina.cpu_write(0x7FFF, 0x0A);
+
+        // Then
+        assert_eq!(1, nina.cpu_read(0x8000));
+        assert_eq!(5, nina.ppu_read(0x0000));
+        assert_eq!(10, nina.ppu_read(0x1000));
+        assert_eq!(0x0A, nina.cpu_read(0x7FFF));
+    }
+
+    #[test]
+    fn nina001_prg_ram() {
+        // Given
+        let mut nina = NINA001::new(&test_rom(34, 1, banked(0x10000, 0x8000), banked(0x10000, 0x1000), 0));
+
+        // When
+        nina.cpu_write(0x6123, 0x42);
+
+        // Then
+        assert_eq!(0x42, nina.cpu_read(0x6123));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::mappers::{Mapper, chr_memory, has_bus_conflicts, prg_ram, read_prg_ram, write_prg_ram};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=CNROM
+pub struct CNROM {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    chr_bank: u8
+}
+
+impl CNROM {
+    pub fn new(rom: &INesRom) -> Self {
+        CNROM {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            chr: chr_memory(rom),
+            mirroring: rom.header.mirroring(),
+            bus_conflicts: has_bus_conflicts(rom),
+            chr_bank: 0
+        }
+    }
+}
+
+impl Mapper for CNROM {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            // Same fixed PRG as NROM, 16KB images are mirrored
+            0x8000 ..= 0xFFFF => self.prg_rom[(addr - 0x8000) as usize % self.prg_rom.len()],
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF => write_prg_ram(&mut self.prg_ram, addr, data),
+            0x8000 ..= 0xFFFF => self.chr_bank = if self.bus_conflicts { data & self.cpu_read(addr) } else { data },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[(self.chr_bank as usize * 0x2000 + addr as usize) % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use super::CNROM;
+
+    #[test]
+    fn switch_chr_bank() {
+        // Given
+        let mut cnrom = CNROM::new(&test_rom(3, 0, vec![0xFF; 0x8000], banked(0x8000, 0x2000), 0));
+
+        // When
+        cnrom.cpu_write(0x8000, 0x02);
+
+        // Then
+        assert_eq!(2, cnrom.ppu_read(0x0000));
+        assert_eq!(2, cnrom.ppu_read(0x1FFF));
+    }
+
+    #[test]
+    fn prg_rom_128_mirrored() {
+        // Given
+        let cnrom = CNROM::new(&test_rom(3, 0, banked(0x4000, 0x2000), banked(0x8000, 0x2000), 0));
+
+        // Then
+        assert_eq!(1, cnrom.cpu_read(0xA000));
+        assert_eq!(1, cnrom.cpu_read(0xE000));
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        // Given
+        let mut cnrom = CNROM::new(&test_rom(3, 2, vec![0x01; 0x8000], banked(0x8000, 0x2000), 0));
+
+        // When
+        cnrom.cpu_write(0x8000, 0x03);
+
+        // Then
+        assert_eq!(1, cnrom.ppu_read(0x0000));
+    }
+}
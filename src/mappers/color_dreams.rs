@@ -0,0 +1,90 @@
+use crate::mappers::{Mapper, chr_memory, has_bus_conflicts, prg_ram, read_prg_ram, write_prg_ram};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=Color_Dreams
+pub struct ColorDreams {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    register: u8
+}
+
+impl ColorDreams {
+    pub fn new(rom: &INesRom) -> Self {
+        ColorDreams {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            chr: chr_memory(rom),
+            mirroring: rom.header.mirroring(),
+            bus_conflicts: has_bus_conflicts(rom),
+            register: 0
+        }
+    }
+}
+
+impl Mapper for ColorDreams {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            0x8000 ..= 0xFFFF => {
+                let bank = (self.register & 0x03) as usize;
+                self.prg_rom[(bank * 0x8000 + (addr - 0x8000) as usize) % self.prg_rom.len()]
+            },
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF => write_prg_ram(&mut self.prg_ram, addr, data),
+            0x8000 ..= 0xFFFF => self.register = if self.bus_conflicts { data & self.cpu_read(addr) } else { data },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        let bank = (self.register >> 4) as usize;
+        self.chr[(bank * 0x2000 + addr as usize) % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use super::ColorDreams;
+
+    #[test]
+    fn switch_banks() {
+        // Given
+        let mut color_dreams = ColorDreams::new(&test_rom(11, 0, banked(0x20000, 0x8000), banked(0x20000, 0x2000), 0));
+
+        // When
+        color_dreams.cpu_write(0xC000, 0xA3);
+
+        // Then
+        assert_eq!(3, color_dreams.cpu_read(0x8000));
+        assert_eq!(3, color_dreams.cpu_read(0xFFFF));
+        assert_eq!(10, color_dreams.ppu_read(0x0000));
+        assert_eq!(10, color_dreams.ppu_read(0x1FFF));
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        // Given every PRG byte is $31, so only bits 0, 4 and 5 of a write get through
+        let mut color_dreams = ColorDreams::new(&test_rom(11, 2, vec![0x31; 0x20000], banked(0x20000, 0x2000), 0));
+
+        // When
+        color_dreams.cpu_write(0x8000, 0xF3);
+
+        // Then
+        assert_eq!(3, color_dreams.ppu_read(0x0000));
+    }
+}
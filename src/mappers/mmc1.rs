@@ -0,0 +1,459 @@
+use std::ops::Range;
+use crate::mappers::{Mapper, chr_memory, prg_ram, prg_nvram, battery_ram, battery_ram_mut};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=MMC1
+pub struct MMC1 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    nvram: Range<usize>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    board: Board,
+
+    // Writes shift in from bit 4 down, the 1 bit marks when all 5 bits have arrived
+    shift_register: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+
+    // The serial port ignores a write on the cycle after another write, which is how
+    // the double write of a read-modify-write instruction gets dropped
+    write_ready: bool
+}
+
+// SxROM boards reuse the CHR bank lines for extra PRG ROM or PRG RAM address bits
+// https://wiki.nesdev.org/w/index.php?title=MMC1#SOROM.2C_SUROM_and_SXROM
+enum Board {
+    Standard,
+    // Submapper 5:  32KB of PRG ROM that can't be banked
+    SEROM,
+    // CHR bank bit 3 selects 8KB of PRG RAM
+    SOROM,
+    // CHR bank bit 4 selects a 256KB half of PRG ROM
+    SUROM,
+    // Both of the above, with CHR bank bits 2-3 selecting 8KB of PRG RAM
+    SXROM
+}
+
+const SHIFT_RESET: u8 = 0b1_0000;
+
+impl MMC1 {
+    pub fn new(rom: &INesRom) -> Self {
+        let prg_ram = prg_ram(rom);
+
+        let board = match (rom.header.submapper(), rom.prg_rom.len(), prg_ram.len()) {
+            (5, _, _) => Board::SEROM,
+            (_, 0x80000, 0x8000) => Board::SXROM,
+            (_, 0x80000, _) => Board::SUROM,
+            (_, _, 0x4000) => Board::SOROM,
+            (_, _, 0x8000) => Board::SXROM,
+            _ => Board::Standard
+        };
+
+        MMC1 {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram,
+            nvram: prg_nvram(rom),
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            board,
+            shift_register: SHIFT_RESET,
+            control: 0x0C,  // PRG mode 3 at power on, so the reset vector is in the fixed last bank
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            write_ready: true
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000 ..= 0x9FFF => self.control = value,
+            0xA000 ..= 0xBFFF => self.chr_bank_0 = value,
+            0xC000 ..= 0xDFFF => self.chr_bank_1 = value,
+            _ => self.prg_bank = value
+        }
+    }
+
+    // 256KB PRG ROM half selected through the CHR bank, in 16KB bank units
+    fn prg_rom_outer_bank(&self) -> usize {
+        match self.board {
+            Board::SUROM | Board::SXROM => (self.chr_bank_0 & 0x10) as usize,
+            _ => 0
+        }
+    }
+
+    fn prg_rom_address(&self, addr: u16) -> usize {
+        let offset = (addr & 0x3FFF) as usize;
+        let outer = self.prg_rom_outer_bank();
+        let bank = outer | (self.prg_bank & 0x0F) as usize;
+
+        let bank = match (&self.board, (self.control >> 2) & 0x03) {
+            (Board::SEROM, _) => (addr - 0x8000) as usize / 0x4000,
+            // 32KB mode ignores the low bit of the bank number
+            (_, 0) | (_, 1) => (bank & !1) | ((addr - 0x8000) as usize / 0x4000),
+            // First bank fixed at $8000
+            (_, 2) => if addr < 0xC000 { outer } else { bank },
+            // Last bank fixed at $C000
+            _ => if addr < 0xC000 { bank } else { outer | 0x0F }
+        };
+
+        (bank * 0x4000 + offset) % self.prg_rom.len()
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        !self.prg_ram.is_empty() && self.prg_bank & 0x10 == 0
+    }
+
+    fn prg_ram_address(&self, addr: u16) -> usize {
+        let bank = match self.board {
+            Board::SOROM => (self.chr_bank_0 >> 3) & 0x01,
+            Board::SXROM => (self.chr_bank_0 >> 2) & 0x03,
+            _ => 0
+        } as usize;
+
+        (bank * 0x2000 + (addr - 0x6000) as usize) % self.prg_ram.len()
+    }
+
+    fn chr_address(&self, addr: u16) -> usize {
+        let bank = if self.control & 0x10 == 0 {
+            // 8KB mode ignores the low bit of the bank number
+            (self.chr_bank_0 & 0x1E) as usize | (addr as usize / 0x1000)
+        } else if addr < 0x1000 {
+            self.chr_bank_0 as usize
+        } else {
+            self.chr_bank_1 as usize
+        };
+
+        (bank * 0x1000 + (addr & 0x0FFF) as usize) % self.chr.len()
+    }
+}
+
+impl Mapper for MMC1 {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF if self.prg_ram_enabled() => self.prg_ram[self.prg_ram_address(addr)],
+            0x8000 ..= 0xFFFF => self.prg_rom[self.prg_rom_address(addr)],
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF if self.prg_ram_enabled() => {
+                let address = self.prg_ram_address(addr);
+                self.prg_ram[address] = data;
+            },
+            0x8000 ..= 0xFFFF => {
+                let ready = self.write_ready;
+                self.write_ready = false;
+
+                if data & 0x80 == 0x80 {
+                    self.shift_register = SHIFT_RESET;
+                    self.control |= 0x0C;
+                } else if ready {
+                    let complete = self.shift_register & 1 == 1;
+                    self.shift_register = (self.shift_register >> 1) | ((data & 1) << 4);
+
+                    if complete {
+                        let value = self.shift_register;
+                        self.write_register(addr, value);
+                        self.shift_register = SHIFT_RESET;
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_address(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_writable {
+            let address = self.chr_address(addr);
+            self.chr[address] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::SingleScreenA,
+            1 => Mirroring::SingleScreenB,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal
+        }
+    }
+
+    fn tick(&mut self, _cycles: u8) {
+        self.write_ready = true;
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        battery_ram(&self.prg_ram, &self.nvram)
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        battery_ram_mut(&mut self.prg_ram, &self.nvram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use crate::rom::Mirroring;
+    use super::MMC1;
+
+    // Shifts a value into one of the registers a bit at a time, like games do
+    fn write_serial(mmc1: &mut MMC1, addr: u16, value: u8) {
+        for bit in 0..5 {
+            mmc1.cpu_write(addr, (value >> bit) & 1);
+            mmc1.tick(4);
+        }
+    }
+
+    fn mmc1(prg_size: usize, chr_size: usize, prg_ram: u8) -> MMC1 {
+        MMC1::new(&test_rom(1, 0, banked(prg_size, 0x4000), banked(chr_size, 0x1000), prg_ram))
+    }
+
+    #[test]
+    fn power_on_fixes_last_bank() {
+        // Given
+        let mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // Then
+        assert_eq!(0, mmc1.cpu_read(0x8000));
+        assert_eq!(7, mmc1.cpu_read(0xC000));
+        assert_eq!(7, mmc1.cpu_read(0xFFFF));
+    }
+
+    #[test]
+    fn switch_bank_at_8000() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0xE000, 0x05);
+
+        // Then
+        assert_eq!(5, mmc1.cpu_read(0x8000));
+        assert_eq!(7, mmc1.cpu_read(0xC000));
+    }
+
+    #[test]
+    fn prg_mode_2_fixes_first_bank() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0x8000, 0x08);
+        write_serial(&mut mmc1, 0xE000, 0x03);
+
+        // Then
+        assert_eq!(0, mmc1.cpu_read(0x8000));
+        assert_eq!(3, mmc1.cpu_read(0xC000));
+    }
+
+    #[test]
+    fn prg_mode_32k_ignores_low_bit() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0x8000, 0x00);
+        write_serial(&mut mmc1, 0xE000, 0x03);
+
+        // Then
+        assert_eq!(2, mmc1.cpu_read(0x8000));
+        assert_eq!(3, mmc1.cpu_read(0xC000));
+    }
+
+    #[test]
+    fn reset_bit_clears_shift_register() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When
+        mmc1.cpu_write(0xE000, 0x01);
+        mmc1.tick(4);
+        mmc1.cpu_write(0xE000, 0x80);
+        mmc1.tick(4);
+        write_serial(&mut mmc1, 0xE000, 0x02);
+
+        // Then
+        assert_eq!(2, mmc1.cpu_read(0x8000));
+    }
+
+    #[test]
+    fn consecutive_write_ignored() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When the second write of each pair comes without a cycle in between
+        for bit in 0..5 {
+            mmc1.cpu_write(0xE000, (0x06 >> bit) & 1);
+            mmc1.cpu_write(0xE000, 1);
+            mmc1.tick(6);
+        }
+
+        // Then
+        assert_eq!(6, mmc1.cpu_read(0x8000));
+    }
+
+    #[test]
+    fn switch_mirroring() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0x8000, 0x0E);
+
+        // Then
+        assert_eq!(Mirroring::Vertical, mmc1.mirroring());
+
+        // When
+        write_serial(&mut mmc1, 0x8000, 0x0D);
+
+        // Then
+        assert_eq!(Mirroring::SingleScreenB, mmc1.mirroring());
+    }
+
+    #[test]
+    fn chr_8k_mode() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x8000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x05);
+
+        // Then
+        assert_eq!(4, mmc1.ppu_read(0x0000));
+        assert_eq!(5, mmc1.ppu_read(0x1000));
+    }
+
+    #[test]
+    fn chr_4k_mode() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x8000, 0x07);
+
+        // When
+        write_serial(&mut mmc1, 0x8000, 0x1C);
+        write_serial(&mut mmc1, 0xA000, 0x05);
+        write_serial(&mut mmc1, 0xC000, 0x02);
+
+        // Then
+        assert_eq!(5, mmc1.ppu_read(0x0000));
+        assert_eq!(2, mmc1.ppu_read(0x1000));
+    }
+
+    #[test]
+    fn prg_ram_disable() {
+        // Given
+        let mut mmc1 = mmc1(0x20000, 0x2000, 0x07);
+        mmc1.cpu_write(0x6000, 0x42);
+
+        // When
+        write_serial(&mut mmc1, 0xE000, 0x10);
+        mmc1.cpu_write(0x6001, 0x43);
+
+        // Then
+        assert_eq!(0, mmc1.cpu_read(0x6000));
+
+        // When
+        write_serial(&mut mmc1, 0xE000, 0x00);
+
+        // Then
+        assert_eq!(0x42, mmc1.cpu_read(0x6000));
+        assert_eq!(0, mmc1.cpu_read(0x6001));
+    }
+
+    #[test]
+    fn surom_selects_prg_half() {
+        // Given
+        let mut mmc1 = MMC1::new(&test_rom(1, 0, banked(0x80000, 0x4000), vec![], 0x70));
+
+        // Then
+        assert_eq!(0x0F, mmc1.cpu_read(0xC000));
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x10);
+        write_serial(&mut mmc1, 0xE000, 0x02);
+
+        // Then
+        assert_eq!(0x12, mmc1.cpu_read(0x8000));
+        assert_eq!(0x1F, mmc1.cpu_read(0xC000));
+    }
+
+    #[test]
+    fn sorom_selects_prg_ram_bank() {
+        // Given 8KB PRG RAM and 8KB PRG NVRAM
+        let mut mmc1 = MMC1::new(&test_rom(1, 0, banked(0x40000, 0x4000), vec![], 0x77));
+        mmc1.cpu_write(0x6000, 0x11);
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x08);
+        mmc1.cpu_write(0x6000, 0x22);
+
+        // Then
+        assert_eq!(0x22, mmc1.cpu_read(0x6000));
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x00);
+
+        // Then
+        assert_eq!(0x11, mmc1.cpu_read(0x6000));
+    }
+
+    #[test]
+    fn sorom_saves_second_bank() {
+        // Given 8KB PRG RAM and 8KB PRG NVRAM
+        let mut mmc1 = MMC1::new(&test_rom(1, 0, banked(0x40000, 0x4000), vec![], 0x77));
+        mmc1.cpu_write(0x6000, 0x11);
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x08);
+        mmc1.cpu_write(0x6000, 0x22);
+
+        // Then
+        let save_ram = mmc1.save_ram().unwrap();
+        assert_eq!(0x2000, save_ram.len());
+        assert_eq!(0x22, save_ram[0]);
+        assert!(!save_ram.contains(&0x11));
+    }
+
+    #[test]
+    fn sxrom_selects_prg_ram_bank() {
+        // Given 32KB of PRG NVRAM
+        let mut mmc1 = MMC1::new(&test_rom(1, 0, banked(0x80000, 0x4000), vec![], 0x90));
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x0C);
+        mmc1.cpu_write(0x6000, 0x33);
+        write_serial(&mut mmc1, 0xA000, 0x04);
+
+        // Then
+        assert_eq!(0x00, mmc1.cpu_read(0x6000));
+
+        // When
+        write_serial(&mut mmc1, 0xA000, 0x0C);
+
+        // Then
+        assert_eq!(0x33, mmc1.cpu_read(0x6000));
+    }
+
+    #[test]
+    fn serom_is_not_banked() {
+        // Given
+        let mut mmc1 = MMC1::new(&test_rom(1, 5, banked(0x8000, 0x4000), banked(0x2000, 0x1000), 0x00));
+
+        // When
+        write_serial(&mut mmc1, 0xE000, 0x01);
+
+        // Then
+        assert_eq!(0, mmc1.cpu_read(0x8000));
+        assert_eq!(1, mmc1.cpu_read(0xC000));
+    }
+}
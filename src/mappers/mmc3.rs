@@ -0,0 +1,497 @@
+use std::ops::Range;
+use crate::mappers::{Mapper, chr_memory, prg_ram, prg_nvram, battery_ram, battery_ram_mut};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=MMC3
+pub struct MMC3 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    nvram: Range<usize>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    // Submapper 1 is the MMC6, with 1KB of internal RAM and its own protection scheme
+    // https://wiki.nesdev.org/w/index.php?title=MMC6
+    mmc6: bool,
+
+    bank_select: u8,
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    prg_ram_protect: u8,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+
+    // Number of PPU fetches since A12 last went low, see clock_a12()
+    a12_low_count: u8
+}
+
+// The MMC6's 1KB is mirrored across $7000-$7FFF, so a trainer fills its first half
+fn mmc6_ram(rom: &INesRom) -> Vec<u8> {
+    let mut ram = vec![0; 0x400];
+    if let Some(trainer) = &rom.trainer {
+        ram[..trainer.len()].copy_from_slice(trainer);
+    }
+    ram
+}
+
+// A12 has to stay low for a few fetches before a rise counts, which filters out the
+// toggling between nametable and pattern fetches within each tile or sprite
+const A12_FILTER: u8 = 3;
+
+impl MMC3 {
+    pub fn new(rom: &INesRom) -> Self {
+        let mmc6 = rom.header.submapper() == 1;
+        let prg_ram = if mmc6 { mmc6_ram(rom) } else { prg_ram(rom) };
+        // The battery keeps all of the MMC6's internal RAM or none of it
+        let nvram = match (mmc6, prg_nvram(rom)) {
+            (true, nvram) if !nvram.is_empty() => 0..0x400,
+            (_, nvram) => nvram
+        };
+
+        // RAM starts out disabled, which would hide a trainer from the game, so boards
+        // with one power up with it enabled
+        let (bank_select, prg_ram_protect) = match (&rom.trainer, mmc6) {
+            (Some(_), true) => (0x20, 0xF0),
+            (Some(_), false) => (0x00, 0x80),
+            (None, _) => (0x00, 0x00)
+        };
+
+        MMC3 {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram,
+            nvram,
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            mmc6,
+            bank_select,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring: rom.header.mirroring(),
+            prg_ram_protect,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12_low_count: 0
+        }
+    }
+
+    fn prg_rom_address(&self, addr: u16) -> usize {
+        // An 8KB ROM is mirrored into both fixed banks
+        let last_bank = (self.prg_rom.len() / 0x2000).max(2) - 1;
+        let prg_mode = self.bank_select & 0x40 == 0x40;
+
+        let bank = match (addr, prg_mode) {
+            (0x8000 ..= 0x9FFF, false) | (0xC000 ..= 0xDFFF, true) => self.banks[6] as usize,
+            (0x8000 ..= 0x9FFF, true) | (0xC000 ..= 0xDFFF, false) => last_bank - 1,
+            (0xA000 ..= 0xBFFF, _) => self.banks[7] as usize,
+            _ => last_bank
+        };
+
+        (bank * 0x2000 + (addr & 0x1FFF) as usize) % self.prg_rom.len()
+    }
+
+    fn chr_address(&self, addr: u16) -> usize {
+        // CHR inversion swaps the 2KB and 1KB halves of the pattern tables
+        let addr = if self.bank_select & 0x80 == 0x80 { addr ^ 0x1000 } else { addr };
+
+        let bank = match addr {
+            0x0000 ..= 0x07FF => (self.banks[0] & 0xFE) as usize | (addr as usize >> 10 & 1),
+            0x0800 ..= 0x0FFF => (self.banks[1] & 0xFE) as usize | (addr as usize >> 10 & 1),
+            0x1000 ..= 0x13FF => self.banks[2] as usize,
+            0x1400 ..= 0x17FF => self.banks[3] as usize,
+            0x1800 ..= 0x1BFF => self.banks[4] as usize,
+            _ => self.banks[5] as usize
+        };
+
+        (bank * 0x0400 + (addr & 0x03FF) as usize) % self.chr.len()
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=MMC3#PRG_RAM_protect_.28.24A001-.24BFFF.2C_odd.29
+    fn prg_ram_readable(&self, addr: u16) -> bool {
+        if self.mmc6 {
+            self.bank_select & 0x20 == 0x20 && addr >= 0x7000 && self.prg_ram_protect & 0xA0 != 0
+        } else {
+            !self.prg_ram.is_empty() && self.prg_ram_protect & 0x80 == 0x80
+        }
+    }
+
+    fn prg_ram_writable(&self, addr: u16) -> bool {
+        if self.mmc6 {
+            // Each half can only be written while it is also readable
+            let half = self.prg_ram_protect & self.mmc6_half_mask(addr);
+            self.bank_select & 0x20 == 0x20 && addr >= 0x7000 && half & 0xA0 != 0 && half & 0x50 != 0
+        } else {
+            self.prg_ram_readable(addr) && self.prg_ram_protect & 0x40 == 0
+        }
+    }
+
+    // The MMC6 protects the two 512 byte halves of its RAM with separate read/write bits
+    fn mmc6_half_mask(&self, addr: u16) -> u8 {
+        if addr & 0x0200 == 0x0200 { 0xC0 } else { 0x30 }
+    }
+
+    fn read_prg_ram(&self, addr: u16) -> u8 {
+        let address = (addr - 0x6000) as usize % self.prg_ram.len();
+
+        // With only one MMC6 half readable, the other half reads back as 0
+        if self.mmc6 && self.prg_ram_protect & self.mmc6_half_mask(addr) & 0xA0 == 0 {
+            0
+        } else {
+            self.prg_ram[address]
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match (addr, addr & 1) {
+            (0x8000 ..= 0x9FFF, 0) => self.bank_select = data,
+            (0x8000 ..= 0x9FFF, _) => self.banks[(self.bank_select & 0x07) as usize] = data,
+            // Hard wired on four-screen boards
+            (0xA000 ..= 0xBFFF, 0) if self.mirroring != Mirroring::FourScreen => {
+                self.mirroring = if data & 1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            },
+            (0xA000 ..= 0xBFFF, 0) => {},
+            (0xA000 ..= 0xBFFF, _) => {
+                // MMC6 protection bits can only change while its RAM is enabled
+                if !self.mmc6 || self.bank_select & 0x20 == 0x20 {
+                    self.prg_ram_protect = data;
+                }
+            },
+            (0xC000 ..= 0xDFFF, 0) => self.irq_latch = data,
+            (0xC000 ..= 0xDFFF, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (_, 0) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            _ => self.irq_enabled = true
+        }
+    }
+
+    fn clock_a12(&mut self, addr: u16) {
+        if addr & 0x1000 == 0 {
+            self.a12_low_count = self.a12_low_count.saturating_add(1);
+            return;
+        }
+
+        if self.a12_low_count >= A12_FILTER {
+            self.clock_irq_counter();
+        }
+        self.a12_low_count = 0;
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=MMC3#IRQ_Specifics
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for MMC3 {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF if self.prg_ram_readable(addr) => self.read_prg_ram(addr),
+            0x8000 ..= 0xFFFF => self.prg_rom[self.prg_rom_address(addr)],
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF if self.prg_ram_writable(addr) => {
+                let len = self.prg_ram.len();
+                self.prg_ram[(addr - 0x6000) as usize % len] = data;
+            },
+            0x8000 ..= 0xFFFF => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.clock_a12(addr);
+        self.chr[self.chr_address(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        self.clock_a12(addr);
+        if self.chr_writable {
+            let address = self.chr_address(addr);
+            self.chr[address] = data;
+        }
+    }
+
+    fn ppu_address(&mut self, addr: u16) {
+        self.clock_a12(addr);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        battery_ram(&self.prg_ram, &self.nvram)
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        battery_ram_mut(&mut self.prg_ram, &self.nvram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use crate::rom::Mirroring;
+    use super::MMC3;
+
+    fn mmc3() -> MMC3 {
+        MMC3::new(&test_rom(4, 0, banked(0x20000, 0x2000), banked(0x20000, 0x0400), 0x07))
+    }
+
+    fn mmc6() -> MMC3 {
+        MMC3::new(&test_rom(4, 1, banked(0x20000, 0x2000), banked(0x20000, 0x0400), 0x00))
+    }
+
+    // Roughly the fetches of a scanline with the background at $0000 and sprites at $1000
+    fn scanline(mmc3: &mut MMC3) {
+        for _ in 0..34 {
+            mmc3.ppu_address(0x2000);
+            mmc3.ppu_read(0x0000);
+        }
+        for _ in 0..8 {
+            mmc3.ppu_address(0x2000);
+            mmc3.ppu_address(0x2000);
+            mmc3.ppu_read(0x1000);
+            mmc3.ppu_read(0x1008);
+        }
+    }
+
+    #[test]
+    fn prg_mode_0() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When
+        mmc3.cpu_write(0x8000, 0x06);
+        mmc3.cpu_write(0x8001, 0x03);
+        mmc3.cpu_write(0x8000, 0x07);
+        mmc3.cpu_write(0x8001, 0x05);
+
+        // Then
+        assert_eq!(3, mmc3.cpu_read(0x8000));
+        assert_eq!(5, mmc3.cpu_read(0xA000));
+        assert_eq!(14, mmc3.cpu_read(0xC000));
+        assert_eq!(15, mmc3.cpu_read(0xE000));
+    }
+
+    #[test]
+    fn prg_mode_1() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When
+        mmc3.cpu_write(0x8000, 0x46);
+        mmc3.cpu_write(0x8001, 0x03);
+
+        // Then
+        assert_eq!(14, mmc3.cpu_read(0x8000));
+        assert_eq!(3, mmc3.cpu_read(0xC000));
+        assert_eq!(15, mmc3.cpu_read(0xE000));
+    }
+
+    #[test]
+    fn chr_banks() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When
+        mmc3.cpu_write(0x8000, 0x00);
+        mmc3.cpu_write(0x8001, 0x09);
+        mmc3.cpu_write(0x8000, 0x05);
+        mmc3.cpu_write(0x8001, 0x21);
+
+        // Then
+        assert_eq!(0x08, mmc3.ppu_read(0x0000));
+        assert_eq!(0x09, mmc3.ppu_read(0x0400));
+        assert_eq!(0x21, mmc3.ppu_read(0x1C00));
+    }
+
+    #[test]
+    fn chr_inversion() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When
+        mmc3.cpu_write(0x8000, 0x80);
+        mmc3.cpu_write(0x8001, 0x10);
+        mmc3.cpu_write(0x8000, 0x82);
+        mmc3.cpu_write(0x8001, 0x30);
+
+        // Then
+        assert_eq!(0x30, mmc3.ppu_read(0x0000));
+        assert_eq!(0x10, mmc3.ppu_read(0x1000));
+        assert_eq!(0x11, mmc3.ppu_read(0x1400));
+    }
+
+    #[test]
+    fn mirroring_control() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When
+        mmc3.cpu_write(0xA000, 0x01);
+
+        // Then
+        assert_eq!(Mirroring::Horizontal, mmc3.mirroring());
+    }
+
+    #[test]
+    fn four_screen_ignores_mirroring_control() {
+        // Given
+        let mut mmc3 = mmc3();
+        mmc3.mirroring = Mirroring::FourScreen;
+
+        // When
+        mmc3.cpu_write(0xA000, 0x01);
+
+        // Then
+        assert_eq!(Mirroring::FourScreen, mmc3.mirroring());
+    }
+
+    #[test]
+    fn prg_ram_protect() {
+        // Given
+        let mut mmc3 = mmc3();
+
+        // When disabled
+        mmc3.cpu_write(0x6000, 0x11);
+
+        // Then
+        assert_eq!(0x00, mmc3.cpu_read(0x6000));
+
+        // When enabled
+        mmc3.cpu_write(0xA001, 0x80);
+        mmc3.cpu_write(0x6000, 0x22);
+
+        // Then
+        assert_eq!(0x22, mmc3.cpu_read(0x6000));
+
+        // When write protected
+        mmc3.cpu_write(0xA001, 0xC0);
+        mmc3.cpu_write(0x6000, 0x33);
+
+        // Then
+        assert_eq!(0x22, mmc3.cpu_read(0x6000));
+    }
+
+    #[test]
+    fn irq_after_latch_scanlines() {
+        // Given
+        let mut mmc3 = mmc3();
+        mmc3.cpu_write(0xC000, 0x02);
+        mmc3.cpu_write(0xC001, 0x00);
+        mmc3.cpu_write(0xE001, 0x00);
+
+        // When
+        scanline(&mut mmc3);
+        scanline(&mut mmc3);
+
+        // Then
+        assert!(!mmc3.irq());
+
+        // When
+        scanline(&mut mmc3);
+
+        // Then
+        assert!(mmc3.irq());
+
+        // When
+        mmc3.cpu_write(0xE000, 0x00);
+
+        // Then
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn irq_disabled() {
+        // Given
+        let mut mmc3 = mmc3();
+        mmc3.cpu_write(0xC000, 0x00);
+        mmc3.cpu_write(0xC001, 0x00);
+
+        // When
+        scanline(&mut mmc3);
+
+        // Then
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn a12_filter_ignores_quick_toggles() {
+        // Given
+        let mut mmc3 = mmc3();
+        mmc3.cpu_write(0xC000, 0x00);
+        mmc3.cpu_write(0xC001, 0x00);
+        mmc3.cpu_write(0xE001, 0x00);
+
+        // When
+        mmc3.ppu_address(0x2000);
+        mmc3.ppu_read(0x1000);
+
+        // Then
+        assert!(!mmc3.irq());
+    }
+
+    #[test]
+    fn mmc6_ram_halves() {
+        // Given
+        let mut mmc6 = mmc6();
+        mmc6.cpu_write(0x8000, 0x20);
+
+        // When only the low half is readable and writable
+        mmc6.cpu_write(0xA001, 0x30);
+        mmc6.cpu_write(0x7000, 0x11);
+        mmc6.cpu_write(0x7200, 0x22);
+
+        // Then
+        assert_eq!(0x11, mmc6.cpu_read(0x7000));
+        assert_eq!(0x11, mmc6.cpu_read(0x7400));
+        assert_eq!(0x00, mmc6.cpu_read(0x7200));
+        assert_eq!(0x00, mmc6.cpu_read(0x6000));
+
+        // When the high half becomes readable
+        mmc6.cpu_write(0xA001, 0xB0);
+
+        // Then
+        assert_eq!(0x00, mmc6.cpu_read(0x7200));
+    }
+
+    #[test]
+    fn mmc6_ram_disabled() {
+        // Given
+        let mut mmc6 = mmc6();
+
+        // When RAM isn't enabled through $8000, $A001 is ignored
+        mmc6.cpu_write(0xA001, 0xF0);
+        mmc6.cpu_write(0x7000, 0x11);
+
+        // Then
+        assert_eq!(0x00, mmc6.cpu_read(0x7000));
+    }
+}
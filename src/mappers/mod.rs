@@ -0,0 +1,217 @@
+// Cartridge hardware lives behind the Mapper trait, so that Bus only needs to know that
+// $4020-$FFFF (and the PPU pattern tables) belong to the cartridge.
+// See:  https://wiki.nesdev.org/w/index.php?title=Mapper
+use std::ops::Range;
+use crate::rom::{INesRom, Mirroring, RomError};
+use crate::mappers::nrom::NROM;
+use crate::mappers::mmc1::MMC1;
+use crate::mappers::mmc3::MMC3;
+use crate::mappers::uxrom::UxROM;
+use crate::mappers::cnrom::CNROM;
+use crate::mappers::axrom::AxROM;
+use crate::mappers::color_dreams::ColorDreams;
+use crate::mappers::bnrom::{BNROM, NINA001};
+use crate::mappers::gxrom::GxROM;
+
+pub(crate) mod nrom;
+mod mmc1;
+mod mmc3;
+mod uxrom;
+mod cnrom;
+mod axrom;
+mod color_dreams;
+mod bnrom;
+mod gxrom;
+
+pub trait Mapper {
+    // CPU $4020-$FFFF
+    fn cpu_read(&self, addr: u16) -> u8;
+    fn cpu_write(&mut self, addr: u16, data: u8);
+
+    // PPU $0000-$1FFF
+    fn ppu_read(&mut self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, data: u8);
+
+    // Any other address the PPU puts on its bus (nametable fetches, $2006 writes), for
+    // boards that watch the PPU address lines
+    fn ppu_address(&mut self, _addr: u16) {}
+
+    // Checked on every nametable access, so boards can switch it at any time
+    fn mirroring(&self) -> Mirroring;
+
+    // Called once the CPU has finished an instruction that took the given number of cycles
+    fn tick(&mut self, _cycles: u8) {}
+
+    // True while the cartridge is holding the CPU /IRQ line low
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Battery backed PRG RAM, if the board has any
+    fn save_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+}
+
+pub fn for_rom(rom: &INesRom) -> Result<Box<dyn Mapper>, RomError> {
+    match rom.header.mapper_number() {
+        0 => Ok(Box::new(NROM::new(rom))),
+        1 => Ok(Box::new(MMC1::new(rom))),
+        2 => Ok(Box::new(UxROM::new(rom))),
+        3 => Ok(Box::new(CNROM::new(rom))),
+        4 => Ok(Box::new(MMC3::new(rom))),
+        7 => Ok(Box::new(AxROM::new(rom))),
+        11 => Ok(Box::new(ColorDreams::new(rom))),
+        // Two unrelated boards share mapper 34, NINA-001 is the one with CHR ROM
+        // https://wiki.nesdev.org/w/index.php?title=INES_Mapper_034
+        34 if rom.header.submapper() == 1 || rom.chr_rom.len() > 0x2000 => Ok(Box::new(NINA001::new(rom))),
+        34 => Ok(Box::new(BNROM::new(rom))),
+        66 => Ok(Box::new(GxROM::new(rom))),
+        mapper => Err(RomError::UnsupportedMapper(mapper))
+    }
+}
+
+// Boards without CHR ROM have CHR RAM instead, 8KB unless NES 2.0 says otherwise
+fn chr_memory(rom: &INesRom) -> Vec<u8> {
+    if rom.chr_rom.is_empty() {
+        let size = rom.header.chr_ram_size_bytes() + rom.header.chr_nvram_size_bytes();
+        vec![0; if size == 0 { 0x2000 } else { size }]
+    } else {
+        rom.chr_rom.clone()
+    }
+}
+
+// Discrete logic boards where the ROM keeps driving the data bus during a register write,
+// so the latch sees the written value ANDed with the ROM byte.  NES 2.0 uses submapper 2
+// for boards known to have conflicts.
+// https://wiki.nesdev.org/w/index.php?title=Bus_conflict
+fn has_bus_conflicts(rom: &INesRom) -> bool {
+    rom.header.submapper() == 2
+}
+
+// A trainer gets copied to $7000-$71FF before the game starts, so there has to be at
+// least 8KB of RAM at $6000 to hold it, even on boards that otherwise have none
+// https://wiki.nesdev.org/w/index.php?title=INES#Trainer
+fn prg_ram(rom: &INesRom) -> Vec<u8> {
+    let size = rom.header.prg_ram_size_bytes() + rom.header.prg_nvram_size_bytes();
+
+    match &rom.trainer {
+        Some(trainer) => {
+            let mut ram = vec![0; size.max(0x2000)];
+            ram[0x1000..0x1000 + trainer.len()].copy_from_slice(trainer);
+            ram
+        },
+        None => vec![0; size]
+    }
+}
+
+// Where the battery backed part of prg_ram() sits.  Boards with both kinds have the
+// volatile RAM first, so SOROM keeps its save in the second 8KB bank.
+// https://wiki.nesdev.org/w/index.php?title=MMC1#SOROM.2C_SUROM_and_SXROM
+fn prg_nvram(rom: &INesRom) -> Range<usize> {
+    let start = rom.header.prg_ram_size_bytes();
+    start..start + rom.header.prg_nvram_size_bytes()
+}
+
+// Only the NVRAM gets saved, anything else in PRG RAM is lost at power off
+fn battery_ram<'a>(prg_ram: &'a [u8], nvram: &Range<usize>) -> Option<&'a [u8]> {
+    if nvram.is_empty() { None } else { Some(&prg_ram[nvram.clone()]) }
+}
+
+fn battery_ram_mut<'a>(prg_ram: &'a mut [u8], nvram: &Range<usize>) -> Option<&'a mut [u8]> {
+    if nvram.is_empty() { None } else { Some(&mut prg_ram[nvram.clone()]) }
+}
+
+// $6000-$7FFF on the discrete boards, where RAM is only fitted when the header asks for
+// it, or to hold a trainer.  Smaller sizes are mirrored across the 8KB, and with none at
+// all the range is as empty as the rest of the unmapped space.
+fn read_prg_ram(prg_ram: &[u8], addr: u16) -> u8 {
+    if prg_ram.is_empty() {
+        return 0;
+    }
+
+    prg_ram[(addr - 0x6000) as usize % prg_ram.len()]
+}
+
+fn write_prg_ram(prg_ram: &mut [u8], addr: u16, data: u8) {
+    if !prg_ram.is_empty() {
+        let len = prg_ram.len();
+        prg_ram[(addr - 0x6000) as usize % len] = data;
+    }
+}
+
+// Builds an NES 2.0 image around the given ROM areas, with prg_ram as the raw byte 10
+#[cfg(test)]
+pub(crate) fn test_rom(mapper: u8, submapper: u8, prg_rom: Vec<u8>, chr_rom: Vec<u8>, prg_ram: u8) -> INesRom {
+    let chr_ram = if chr_rom.is_empty() { 0x07 } else { 0x00 };
+    let mut contents = vec![
+        0x4E, 0x45, 0x53, 0x1A,
+        (prg_rom.len() / 0x4000) as u8, (chr_rom.len() / 0x2000) as u8,
+        mapper << 4, (mapper & 0xF0) | 0x08, submapper << 4, 0x00,
+        prg_ram, chr_ram, 0x00, 0x00, 0x00, 0x00
+    ];
+    contents.extend(prg_rom);
+    contents.extend(chr_rom);
+
+    INesRom::new(contents).unwrap()
+}
+
+// test_rom with a trainer, whose bytes count up from 0
+#[cfg(test)]
+pub(crate) fn trainer_rom(mapper: u8, submapper: u8, prg_rom: Vec<u8>, chr_rom: Vec<u8>) -> INesRom {
+    let mut rom = test_rom(mapper, submapper, prg_rom, chr_rom, 0);
+    rom.trainer = Some((0..512).map(|i| i as u8).collect());
+    rom
+}
+
+// Every byte of each bank holds that bank's number, so reads show which bank is mapped
+#[cfg(test)]
+pub(crate) fn banked(size: usize, bank_size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i / bank_size) as u8).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{for_rom, test_rom, trainer_rom, banked};
+
+    #[test]
+    fn trainer_at_7000() {
+        // Given
+        let boards = vec![
+            (0, 0, vec![0xFF; 0x8000], vec![]),
+            (1, 0, banked(0x20000, 0x4000), banked(0x20000, 0x1000)),
+            (2, 0, banked(0x20000, 0x4000), vec![]),
+            (3, 0, vec![0xFF; 0x8000], banked(0x8000, 0x2000)),
+            (4, 0, banked(0x20000, 0x2000), banked(0x20000, 0x0400)),
+            (4, 1, banked(0x20000, 0x2000), banked(0x20000, 0x0400)),   // MMC6
+            (7, 0, banked(0x40000, 0x8000), vec![]),
+            (11, 0, banked(0x20000, 0x8000), banked(0x20000, 0x2000)),
+            (34, 0, banked(0x20000, 0x8000), vec![]),                   // BNROM
+            (34, 1, banked(0x10000, 0x8000), banked(0x10000, 0x1000)),  // NINA-001
+            (66, 0, banked(0x20000, 0x8000), banked(0x8000, 0x2000))
+        ];
+
+        for (number, submapper, prg_rom, chr_rom) in boards {
+            // When
+            let mapper = for_rom(&trainer_rom(number, submapper, prg_rom, chr_rom)).unwrap();
+
+            // Then
+            assert_eq!(0x00, mapper.cpu_read(0x7000), "mapper {}.{}", number, submapper);
+            assert_eq!(0x01, mapper.cpu_read(0x7001), "mapper {}.{}", number, submapper);
+            assert_eq!(0xFF, mapper.cpu_read(0x71FF), "mapper {}.{}", number, submapper);
+        }
+    }
+
+    #[test]
+    fn volatile_prg_ram_not_saved() {
+        // Given 8KB PRG RAM and no PRG NVRAM
+        let mapper = for_rom(&test_rom(0, 0, vec![0xFF; 0x8000], vec![], 0x07)).unwrap();
+
+        // Then
+        assert_eq!(None, mapper.save_ram());
+    }
+}
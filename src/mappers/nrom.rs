@@ -0,0 +1,164 @@
+use std::ops::Range;
+use crate::mappers::{Mapper, chr_memory, prg_ram, prg_nvram, read_prg_ram, write_prg_ram, battery_ram, battery_ram_mut};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=NROM
+pub struct NROM {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    nvram: Range<usize>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    mirroring: Mirroring
+}
+
+impl NROM {
+    pub fn new(rom: &INesRom) -> Self {
+        NROM {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            nvram: prg_nvram(rom),
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            mirroring: rom.header.mirroring()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn from_program(program: Vec<u8>) -> Self {
+        NROM {
+            prg_rom: program,
+            prg_ram: vec![0; 0x2000],
+            nvram: 0..0,
+            chr: vec![0; 0x2000],
+            chr_writable: true,
+            mirroring: Mirroring::Horizontal
+        }
+    }
+}
+
+impl Mapper for NROM {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000 ..= 0x7FFF => read_prg_ram(&self.prg_ram, addr),
+            // 16KB images (NROM-128) show up at both $8000 and $C000
+            0x8000 ..= 0xFFFF => self.prg_rom[(addr - 0x8000) as usize % self.prg_rom.len()],
+            _ => 0
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if let 0x6000 ..= 0x7FFF = addr {
+            write_prg_ram(&mut self.prg_ram, addr, data);
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_writable {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        battery_ram(&self.prg_ram, &self.nvram)
+    }
+
+    fn save_ram_mut(&mut self) -> Option<&mut [u8]> {
+        battery_ram_mut(&mut self.prg_ram, &self.nvram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::Mapper;
+    use crate::rom::INesRom;
+    use super::NROM;
+
+    #[test]
+    fn prg_rom_128_mirrored() {
+        // Given
+        let mut program = vec![0; 0x4000];
+        program[0x0123] = 0x4C;
+        let nrom = NROM::from_program(program);
+
+        // Then
+        assert_eq!(0x4C, nrom.cpu_read(0x8123));
+        assert_eq!(0x4C, nrom.cpu_read(0xC123));
+    }
+
+    #[test]
+    fn prg_rom_256_not_mirrored() {
+        // Given
+        let mut program = vec![0; 0x8000];
+        program[0x0123] = 0x4C;
+        program[0x4123] = 0x60;
+        let nrom = NROM::from_program(program);
+
+        // Then
+        assert_eq!(0x4C, nrom.cpu_read(0x8123));
+        assert_eq!(0x60, nrom.cpu_read(0xC123));
+    }
+
+    #[test]
+    fn prg_rom_ignores_writes() {
+        // Given
+        let mut nrom = NROM::from_program(vec![0x11; 0x4000]);
+
+        // When
+        nrom.cpu_write(0x8000, 0x22);
+
+        // Then
+        assert_eq!(0x11, nrom.cpu_read(0x8000));
+    }
+
+    #[test]
+    fn prg_ram_read_write() {
+        // Given
+        let mut nrom = NROM::from_program(vec![0; 0x4000]);
+
+        // When
+        nrom.cpu_write(0x6ABC, 0x5A);
+
+        // Then
+        assert_eq!(0x5A, nrom.cpu_read(0x6ABC));
+    }
+
+    #[test]
+    fn chr_ram_read_write() {
+        // Given
+        let mut nrom = NROM::from_program(vec![0; 0x4000]);
+
+        // When
+        nrom.ppu_write(0x1FF0, 0xA5);
+
+        // Then
+        assert_eq!(0xA5, nrom.ppu_read(0x1FF0));
+    }
+
+    #[test]
+    fn trainer_seeds_prg_ram() {
+        // Given
+        let mut contents = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        contents.extend((0..512).map(|i| i as u8));
+        contents.extend(vec![0; 0x4000 + 0x2000]);
+
+        // When
+        let nrom = NROM::new(&INesRom::new(contents).unwrap());
+
+        // Then
+        assert_eq!(0x00, nrom.cpu_read(0x6FFF));
+        assert_eq!(0x00, nrom.cpu_read(0x7000));
+        assert_eq!(0x01, nrom.cpu_read(0x7001));
+        assert_eq!(0xFF, nrom.cpu_read(0x71FF));
+        assert_eq!(0x00, nrom.cpu_read(0x7200));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::mappers::{Mapper, chr_memory, has_bus_conflicts, prg_ram, read_prg_ram, write_prg_ram};
+use crate::rom::{INesRom, Mirroring};
+
+// https://wiki.nesdev.org/w/index.php?title=UxROM
+pub struct UxROM {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_writable: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: u8
+}
+
+impl UxROM {
+    pub fn new(rom: &INesRom) -> Self {
+        UxROM {
+            prg_rom: rom.prg_rom.clone(),
+            prg_ram: prg_ram(rom),
+            chr: chr_memory(rom),
+            chr_writable: rom.chr_rom.is_empty(),
+            mirroring: rom.header.mirroring(),
+            bus_conflicts: has_bus_conflicts(rom),
+            prg_bank: 0
+        }
+    }
+}
+
+impl Mapper for UxROM {
+    fn cpu_read(&self, addr: u16) -> u8 {
+        // ROMs smaller than a bank are mirrored into it
+        let bank = match addr {
+            0x6000 ..= 0x7FFF => return read_prg_ram(&self.prg_ram, addr),
+            0x8000 ..= 0xBFFF => self.prg_bank as usize,
+            0xC000 ..= 0xFFFF => (self.prg_rom.len() / 0x4000).max(1) - 1,
+            _ => return 0
+        };
+
+        self.prg_rom[(bank * 0x4000 + (addr & 0x3FFF) as usize) % self.prg_rom.len()]
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000 ..= 0x7FFF => write_prg_ram(&mut self.prg_ram, addr, data),
+            0x8000 ..= 0xFFFF => self.prg_bank = if self.bus_conflicts { data & self.cpu_read(addr) } else { data },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_writable {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::{Mapper, test_rom, banked};
+    use super::UxROM;
+
+    #[test]
+    fn switch_bank_at_8000() {
+        // Given
+        let mut uxrom = UxROM::new(&test_rom(2, 0, banked(0x20000, 0x4000), vec![], 0));
+
+        // When
+        uxrom.cpu_write(0x8000, 0x03);
+
+        // Then
+        assert_eq!(3, uxrom.cpu_read(0x8000));
+        assert_eq!(3, uxrom.cpu_read(0xBFFF));
+        assert_eq!(7, uxrom.cpu_read(0xC000));
+    }
+
+    #[test]
+    fn chr_ram() {
+        // Given
+        let mut uxrom = UxROM::new(&test_rom(2, 0, banked(0x20000, 0x4000), vec![], 0));
+
+        // When
+        uxrom.ppu_write(0x0ABC, 0x99);
+
+        // Then
+        assert_eq!(0x99, uxrom.ppu_read(0x0ABC));
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        // Given the fixed bank is all 7s, so only the low 3 bits of a write get through
+        let mut uxrom = UxROM::new(&test_rom(2, 2, banked(0x20000, 0x4000), vec![], 0));
+
+        // When
+        uxrom.cpu_write(0xC000, 0x0E);
+
+        // Then
+        assert_eq!(6, uxrom.cpu_read(0x8000));
+    }
+
+    #[test]
+    fn no_bus_conflicts() {
+        // Given
+        let mut uxrom = UxROM::new(&test_rom(2, 1, banked(0x40000, 0x4000), vec![], 0));
+
+        // When
+        uxrom.cpu_write(0xC000, 0x0E);
+
+        // Then
+        assert_eq!(14, uxrom.cpu_read(0x8000));
+    }
+}
@@ -0,0 +1,101 @@
+// The picture the PPU produces, as 24-bit RGB
+use std::io;
+use std::io::Write;
+
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 240;
+
+// The 2C02's 64 colours.  Real hardware outputs a composite signal, so any RGB palette is
+// an approximation, this is the one from https://bugzmanov.github.io/nes_ebook/chapter_6_3.html
+#[rustfmt::skip]
+const SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
+    (0x80, 0x80, 0x80), (0x00, 0x3D, 0xA6), (0x00, 0x12, 0xB0), (0x44, 0x00, 0x96),
+    (0xA1, 0x00, 0x5E), (0xC7, 0x00, 0x28), (0xBA, 0x06, 0x00), (0x8C, 0x17, 0x00),
+    (0x5C, 0x2F, 0x00), (0x10, 0x45, 0x00), (0x05, 0x4A, 0x00), (0x00, 0x47, 0x2E),
+    (0x00, 0x41, 0x66), (0x00, 0x00, 0x00), (0x05, 0x05, 0x05), (0x05, 0x05, 0x05),
+    (0xC7, 0xC7, 0xC7), (0x00, 0x77, 0xFF), (0x21, 0x55, 0xFF), (0x82, 0x37, 0xFA),
+    (0xEB, 0x2F, 0xB5), (0xFF, 0x29, 0x50), (0xFF, 0x22, 0x00), (0xD6, 0x32, 0x00),
+    (0xC4, 0x62, 0x00), (0x35, 0x80, 0x00), (0x05, 0x8F, 0x00), (0x00, 0x8A, 0x55),
+    (0x00, 0x99, 0xCC), (0x21, 0x21, 0x21), (0x09, 0x09, 0x09), (0x09, 0x09, 0x09),
+    (0xFF, 0xFF, 0xFF), (0x0F, 0xD7, 0xFF), (0x69, 0xA2, 0xFF), (0xD4, 0x80, 0xFF),
+    (0xFF, 0x45, 0xF3), (0xFF, 0x61, 0x8B), (0xFF, 0x88, 0x33), (0xFF, 0x9C, 0x12),
+    (0xFA, 0xBC, 0x20), (0x9F, 0xE3, 0x0E), (0x2B, 0xF0, 0x35), (0x0C, 0xF0, 0xA4),
+    (0x05, 0xFB, 0xFF), (0x5E, 0x5E, 0x5E), (0x0D, 0x0D, 0x0D), (0x0D, 0x0D, 0x0D),
+    (0xFF, 0xFF, 0xFF), (0xA6, 0xFC, 0xFF), (0xB3, 0xEC, 0xFF), (0xDA, 0xAB, 0xEB),
+    (0xFF, 0xA8, 0xF9), (0xFF, 0xAB, 0xB3), (0xFF, 0xD2, 0xB0), (0xFF, 0xEF, 0xA6),
+    (0xFF, 0xF7, 0x9C), (0xD7, 0xE8, 0x95), (0xA6, 0xED, 0xAF), (0xA2, 0xF2, 0xDA),
+    (0x99, 0xFF, 0xFC), (0xDD, 0xDD, 0xDD), (0x11, 0x11, 0x11), (0x11, 0x11, 0x11)
+];
+
+// Emphasis attenuates the other two channels, by roughly 0.816 on an NTSC PPU
+// See https://wiki.nesdev.org/w/index.php?title=NTSC_video#Color_Tint_Bits
+const EMPHASIS_NUMERATOR: u16 = 209;
+
+// Converts a palette entry to RGB.  emphasis is PPUMASK bits 5-7:  red, green, blue.
+pub fn rgb(colour: u8, emphasis: u8) -> (u8, u8, u8) {
+    let (red, green, blue) = SYSTEM_PALETTE[(colour & 0x3F) as usize];
+    if emphasis == 0 {
+        return (red, green, blue);
+    }
+
+    let dim = |channel: u8, emphasised: bool| {
+        if emphasised { channel } else { (channel as u16 * EMPHASIS_NUMERATOR / 256) as u8 }
+    };
+    // With all three set, everything is dimmed
+    let all = emphasis == 0x07;
+    (dim(red, emphasis & 0x01 != 0 && !all),
+     dim(green, emphasis & 0x02 != 0 && !all),
+     dim(blue, emphasis & 0x04 != 0 && !all))
+}
+
+pub struct Frame {
+    pub pixels: Vec<u8>,    // WIDTH x HEIGHT, 3 bytes per pixel
+    pub count: u64          // Frames completed since power-up
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame { pixels: vec![0; WIDTH * HEIGHT * 3], count: 0 }
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let index = (y * WIDTH + x) * 3;
+        (self.pixels[index], self.pixels[index + 1], self.pixels[index + 2])
+    }
+
+    // Binary PPM, which nearly every image tool can read
+    // See http://netpbm.sourceforge.net/doc/ppm.html
+    pub fn write_ppm(&self, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", WIDTH, HEIGHT)?;
+        out.write_all(&self.pixels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rgb, Frame, HEIGHT, WIDTH};
+
+    #[test]
+    fn emphasis_dims_other_channels() {
+        // Then
+        assert_eq!((0xFF, 0xFF, 0xFF), rgb(0x30, 0x00));
+        assert_eq!((0xFF, 0xD0, 0xD0), rgb(0x30, 0x01));
+        assert_eq!((0xD0, 0xFF, 0xFF), rgb(0x30, 0x06));
+        assert_eq!((0xD0, 0xD0, 0xD0), rgb(0x30, 0x07));
+    }
+
+    #[test]
+    fn ppm_header() {
+        // Given
+        let frame = Frame::new();
+        let mut out: Vec<u8> = Vec::new();
+
+        // When
+        frame.write_ppm(&mut out).unwrap();
+
+        // Then
+        assert!(out.starts_with(b"P6\n256 240\n255\n"));
+        assert_eq!(15 + WIDTH * HEIGHT * 3, out.len());
+    }
+}
@@ -0,0 +1,549 @@
+// The PPU as the CPU sees it:  eight registers at $2000-$2007, mirrored every 8 bytes up
+// to $3FFF, and the memory behind them.  The PPU owns 2KB of nametable VRAM, 256 bytes of
+// OAM and 32 bytes of palette RAM, the pattern tables belong to the cartridge.
+// See https://wiki.nesdev.org/w/index.php?title=PPU_registers
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::mappers::Mapper;
+use crate::rom::Mirroring;
+use crate::ppu::frame::Frame;
+use crate::ppu::frame::WIDTH;
+use crate::ppu::render::{LineSprite, SPRITES_PER_LINE};
+
+pub mod frame;
+mod render;
+mod scroll;
+
+const PPUCTRL: u16 = 0;
+const PPUMASK: u16 = 1;
+const PPUSTATUS: u16 = 2;
+const OAMADDR: u16 = 3;
+const OAMDATA: u16 = 4;
+const PPUSCROLL: u16 = 5;
+const PPUADDR: u16 = 6;
+const PPUDATA: u16 = 7;
+
+// PPUCTRL
+const VRAM_INCREMENT_32: u8 = 0b0000_0100;
+const GENERATE_NMI: u8 = 0b1000_0000;
+
+// PPUSTATUS, the low 5 bits are open bus
+const SPRITE_OVERFLOW: u8 = 0b0010_0000;
+const SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+const VBLANK: u8 = 0b1000_0000;
+
+// https://wiki.nesdev.org/w/index.php?title=PPU_memory_map
+const PALETTE: u16 = 0x3F00;
+
+// 341 dots per scanline, 262 scanlines per frame, vblank starts on the second dot of
+// scanline 241 and ends on the second dot of the pre-render line
+// See https://wiki.nesdev.org/w/index.php?title=PPU_rendering
+const DOTS: u16 = 341;
+const SCANLINES: u16 = 262;
+const VISIBLE_SCANLINES: u16 = 240;
+const VBLANK_SCANLINE: u16 = 241;
+const PRE_RENDER_SCANLINE: u16 = 261;
+
+pub struct Ppu {
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_address: u8,
+    oam: [u8; 0x100],
+    vram: [u8; 0x1000],     // The top 2KB is on the cartridge, for four-screen boards only
+    palette: [u8; 0x20],
+
+    // The internal scroll registers, named as on nesdev.  v is both the VRAM address
+    // PPUDATA goes to and the position rendering fetches from.
+    // See https://wiki.nesdev.org/w/index.php?title=PPU_scrolling
+    vram_address: u16,      // v
+    temp_address: u16,      // t, the top left of the screen until copied into v
+    fine_x: u8,             // x
+    write_toggle: bool,     // w, shared by PPUSCROLL and PPUADDR, false for the first write
+    read_buffer: u8,        // PPUDATA reads return the previous read, except from the palette
+
+    // Reading a write-only register returns whatever was last driven onto the PPU's data
+    // bus.  The real latch decays after a few hundred milliseconds, this one doesn't.
+    // See https://wiki.nesdev.org/w/index.php?title=Open_bus_behavior#PPU_open_bus
+    open_bus: u8,
+
+    scanline: u16,
+    dot: u16,
+
+    // Background fetches for the next tile, and the shift registers pixels come from
+    next_tile: u8,
+    next_attribute: u8,
+    next_pattern: (u8, u8),
+    pattern_shifters: (u16, u16),
+    attribute_shifters: (u16, u16),
+
+    line_sprites: Vec<LineSprite>,      // Evaluated on the previous scanline
+    line: [u8; WIDTH * 3],              // The scanline being drawn
+
+    // Shared, since the CPU owns the bus that owns the PPU
+    frame: Rc<RefCell<Frame>>
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Ppu {
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_address: 0,
+            oam: [0; 0x100],
+            vram: [0; 0x1000],
+            palette: [0; 0x20],
+            vram_address: 0,
+            temp_address: 0,
+            fine_x: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            open_bus: 0,
+            scanline: 0,
+            dot: 0,
+            next_tile: 0,
+            next_attribute: 0,
+            next_pattern: (0, 0),
+            pattern_shifters: (0, 0),
+            attribute_shifters: (0, 0),
+            line_sprites: Vec::with_capacity(SPRITES_PER_LINE),
+            line: [0; WIDTH * 3],
+            frame: Rc::new(RefCell::new(Frame::new()))
+        }
+    }
+
+    pub fn frame(&self) -> Rc<RefCell<Frame>> {
+        Rc::clone(&self.frame)
+    }
+
+    pub fn read_register(&mut self, addr: u16, mapper: &mut dyn Mapper) -> u8 {
+        let value = match addr & 0x0007 {
+            PPUSTATUS => {
+                let value = self.peek_register(addr);
+                self.status &= !VBLANK;
+                self.write_toggle = false;
+                value
+            },
+            OAMDATA => self.peek_register(addr),
+            PPUDATA => {
+                let data = self.read_memory(self.vram_address, mapper);
+                let value = if self.vram_address & 0x3FFF >= PALETTE {
+                    // Palette reads are immediate, the buffer gets the nametable underneath
+                    self.read_buffer = self.read_memory(self.vram_address - 0x1000, mapper);
+                    data | (self.open_bus & 0xC0)
+                } else {
+                    let buffered = self.read_buffer;
+                    self.read_buffer = data;
+                    buffered
+                };
+                self.increment_address();
+                value
+            },
+            _ => self.open_bus
+        };
+
+        self.open_bus = value;
+        value
+    }
+
+    // What a read would return, without clearing vblank or moving the VRAM address.
+    // Used by the trace, which shows the values instructions are about to read.
+    pub fn peek_register(&self, addr: u16) -> u8 {
+        match addr & 0x0007 {
+            PPUSTATUS => (self.status & 0xE0) | (self.open_bus & 0x1F),
+            OAMDATA => {
+                // Bits 2-4 of the attribute byte don't exist
+                let value = self.oam[self.oam_address as usize];
+                if self.oam_address & 0x03 == 2 { value & 0xE3 } else { value }
+            },
+            PPUDATA => self.read_buffer,
+            _ => self.open_bus
+        }
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8, mapper: &mut dyn Mapper) {
+        self.open_bus = data;
+
+        match addr & 0x0007 {
+            PPUCTRL => {
+                self.ctrl = data;
+                self.write_nametable_select(data);
+            },
+            PPUMASK => self.mask = data,
+            PPUSTATUS => {},
+            OAMADDR => self.oam_address = data,
+            OAMDATA => {
+                self.oam[self.oam_address as usize] = data;
+                self.oam_address = self.oam_address.wrapping_add(1);
+            },
+            PPUSCROLL => self.write_scroll(data),
+            PPUADDR => {
+                self.write_address(data);
+                if !self.write_toggle {
+                    mapper.ppu_address(self.vram_address);
+                }
+            },
+            _ => {
+                self.write_memory(self.vram_address, data, mapper);
+                self.increment_address();
+            }
+        }
+    }
+
+    fn increment_address(&mut self) {
+        if self.rendering_enabled() && self.rendering_scanline() {
+            // While rendering, the PPUDATA increment goes through the scrolling counters
+            // https://wiki.nesdev.org/w/index.php?title=PPU_scrolling#$2007_reads_and_writes
+            self.increment_coarse_x();
+            self.increment_y();
+        } else {
+            let increment = if self.ctrl & VRAM_INCREMENT_32 != 0 { 32 } else { 1 };
+            self.vram_address = self.vram_address.wrapping_add(increment) & 0x3FFF;
+        }
+    }
+
+    // Visible scanlines and the pre-render line, where the PPU is fetching
+    fn rendering_scanline(&self) -> bool {
+        self.scanline < VISIBLE_SCANLINES || self.scanline == PRE_RENDER_SCANLINE
+    }
+
+    fn read_memory(&mut self, addr: u16, mapper: &mut dyn Mapper) -> u8 {
+        match addr & 0x3FFF {
+            addr @ 0x0000 ..= 0x1FFF => mapper.ppu_read(addr),
+            addr @ 0x2000 ..= 0x3EFF => {
+                mapper.ppu_address(addr);
+                self.vram[nametable_address(mapper.mirroring(), addr)]
+            },
+            addr => self.palette[palette_address(addr)] & 0x3F
+        }
+    }
+
+    fn write_memory(&mut self, addr: u16, data: u8, mapper: &mut dyn Mapper) {
+        match addr & 0x3FFF {
+            addr @ 0x0000 ..= 0x1FFF => mapper.ppu_write(addr, data),
+            addr @ 0x2000 ..= 0x3EFF => {
+                mapper.ppu_address(addr);
+                self.vram[nametable_address(mapper.mirroring(), addr)] = data;
+            },
+            addr => self.palette[palette_address(addr)] = data & 0x3F
+        }
+    }
+
+    // Advances 3 dots per CPU cycle
+    pub fn tick(&mut self, cycles: u8, mapper: &mut dyn Mapper) {
+        for _ in 0..cycles as u16 * 3 {
+            self.dot += 1;
+            if self.dot == DOTS {
+                self.dot = 0;
+                self.scanline = (self.scanline + 1) % SCANLINES;
+            }
+
+            match (self.scanline, self.dot) {
+                (VBLANK_SCANLINE, 1) => {
+                    self.status |= VBLANK;
+                    self.frame.borrow_mut().count += 1;
+                },
+                (PRE_RENDER_SCANLINE, 1) => self.status &= !(VBLANK | SPRITE_ZERO_HIT | SPRITE_OVERFLOW),
+                _ => {}
+            }
+
+            if self.rendering_scanline() {
+                self.render_dot(mapper);
+            }
+        }
+    }
+
+    // /NMI is held low for as long as vblank is set with NMIs enabled
+    pub fn nmi(&self) -> bool {
+        self.ctrl & GENERATE_NMI != 0 && self.status & VBLANK != 0
+    }
+}
+
+// Four logical nametables at $2000-$2FFF (mirrored up to $3EFF) share 2KB of VRAM, unless
+// the cartridge provides another 2KB
+// See https://wiki.nesdev.org/w/index.php?title=Mirroring#Nametable_Mirroring
+fn nametable_address(mirroring: Mirroring, addr: u16) -> usize {
+    let table = (addr as usize >> 10) & 0x03;
+    let offset = addr as usize & 0x03FF;
+    let physical = match mirroring {
+        Mirroring::Horizontal => table >> 1,
+        Mirroring::Vertical => table & 0x01,
+        Mirroring::SingleScreenA => 0,
+        Mirroring::SingleScreenB => 1,
+        Mirroring::FourScreen => table
+    };
+
+    physical * 0x0400 + offset
+}
+
+// $3F10/$3F14/$3F18/$3F1C are the backdrop entries of $3F00/$3F04/$3F08/$3F0C
+fn palette_address(addr: u16) -> usize {
+    let index = addr as usize & 0x1F;
+    if index & 0x13 == 0x10 { index & 0x0F } else { index }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::Mapper;
+    use crate::mappers::nrom::NROM;
+    use crate::rom::Mirroring;
+    use super::{nametable_address, Ppu, VBLANK};
+
+    fn nrom() -> NROM {
+        NROM::from_program(vec![0; 0x4000])
+    }
+
+    fn set_address(ppu: &mut Ppu, mapper: &mut NROM, address: u16) {
+        ppu.write_register(0x2006, (address >> 8) as u8, mapper);
+        ppu.write_register(0x2006, address as u8, mapper);
+    }
+
+    #[test]
+    fn status_read_clears_vblank_and_toggle() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        ppu.status = VBLANK;
+        ppu.write_register(0x2006, 0x21, &mut mapper);
+
+        // When
+        let status = ppu.read_register(0x2002, &mut mapper);
+
+        // Then
+        assert_eq!(VBLANK, status & 0xE0);
+        assert_eq!(0, ppu.read_register(0x2002, &mut mapper) & VBLANK);
+        assert!(!ppu.write_toggle);
+    }
+
+    #[test]
+    fn vblank_set_and_cleared_by_timing() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+
+        // When
+        for _ in 0..27_395 {
+            ppu.tick(1, &mut mapper);
+        }
+
+        // Then
+        assert_eq!((241, 4), (ppu.scanline, ppu.dot));
+        assert_ne!(0, ppu.status & VBLANK);
+
+        for _ in 0..2273 {
+            ppu.tick(1, &mut mapper);
+        }
+        assert_eq!((261, 3), (ppu.scanline, ppu.dot));
+        assert_eq!(0, ppu.status & VBLANK);
+    }
+
+    #[test]
+    fn nmi_needs_vblank_and_enable() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        ppu.status = VBLANK;
+
+        // When
+        ppu.write_register(0x2000, 0x80, &mut mapper);
+
+        // Then
+        assert!(ppu.nmi());
+        ppu.read_register(0x2002, &mut mapper);
+        assert!(!ppu.nmi());
+    }
+
+    #[test]
+    fn ppudata_reads_are_buffered() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        set_address(&mut ppu, &mut mapper, 0x2400);
+        ppu.write_register(0x2007, 0x11, &mut mapper);
+        ppu.write_register(0x2007, 0x22, &mut mapper);
+        set_address(&mut ppu, &mut mapper, 0x2400);
+
+        // When
+        let first = ppu.read_register(0x2007, &mut mapper);
+        let second = ppu.read_register(0x2007, &mut mapper);
+        let third = ppu.read_register(0x2007, &mut mapper);
+
+        // Then
+        assert_eq!((0x00, 0x11, 0x22), (first, second, third));
+    }
+
+    #[test]
+    fn ppudata_reads_chr_through_mapper() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        mapper.ppu_write(0x1234, 0x5A);
+        set_address(&mut ppu, &mut mapper, 0x1234);
+
+        // When
+        ppu.read_register(0x2007, &mut mapper);
+
+        // Then
+        assert_eq!(0x5A, ppu.read_register(0x2007, &mut mapper));
+    }
+
+    #[test]
+    fn palette_reads_are_immediate() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        set_address(&mut ppu, &mut mapper, 0x2F05);
+        ppu.write_register(0x2007, 0x77, &mut mapper);
+        set_address(&mut ppu, &mut mapper, 0x3F05);
+        ppu.write_register(0x2007, 0x2C, &mut mapper);
+        set_address(&mut ppu, &mut mapper, 0x3F05);
+
+        // When
+        let value = ppu.read_register(0x2007, &mut mapper);
+
+        // Then
+        assert_eq!(0x2C, value & 0x3F);
+        assert_eq!(0x77, ppu.read_buffer);
+    }
+
+    #[test]
+    fn palette_backdrop_mirrors() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        set_address(&mut ppu, &mut mapper, 0x3F10);
+
+        // When
+        ppu.write_register(0x2007, 0x0F, &mut mapper);
+
+        // Then
+        assert_eq!(0x0F, ppu.palette[0x00]);
+        set_address(&mut ppu, &mut mapper, 0x3F11);
+        ppu.write_register(0x2007, 0x16, &mut mapper);
+        assert_eq!(0x16, ppu.palette[0x11]);
+        assert_eq!(0x00, ppu.palette[0x01]);
+    }
+
+    #[test]
+    fn vram_increment_32() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        ppu.write_register(0x2000, 0x04, &mut mapper);
+        set_address(&mut ppu, &mut mapper, 0x2000);
+
+        // When
+        ppu.write_register(0x2007, 0xAA, &mut mapper);
+        ppu.write_register(0x2007, 0xBB, &mut mapper);
+
+        // Then
+        assert_eq!(0xAA, ppu.vram[0x0000]);
+        assert_eq!(0xBB, ppu.vram[0x0020]);
+        assert_eq!(0x2040, ppu.vram_address);
+    }
+
+    #[test]
+    fn horizontal_mirroring() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        set_address(&mut ppu, &mut mapper, 0x2403);
+
+        // When
+        ppu.write_register(0x2007, 0x42, &mut mapper);
+
+        // Then
+        assert_eq!(0x42, ppu.vram[0x0003]);
+        set_address(&mut ppu, &mut mapper, 0x2803);
+        ppu.write_register(0x2007, 0x24, &mut mapper);
+        assert_eq!(0x24, ppu.vram[0x0403]);
+    }
+
+    #[test]
+    fn nametable_mirroring() {
+        // Then, $2000/$2400/$2800/$2C00 and $3000 which mirrors $2000
+        let tables = |mirroring| [0x2000, 0x2400, 0x2800, 0x2C00, 0x3000].iter()
+            .map(|&addr| nametable_address(mirroring, addr + 0x0123))
+            .collect::<Vec<usize>>();
+
+        assert_eq!(vec![0x0123, 0x0123, 0x0523, 0x0523, 0x0123], tables(Mirroring::Horizontal));
+        assert_eq!(vec![0x0123, 0x0523, 0x0123, 0x0523, 0x0123], tables(Mirroring::Vertical));
+        assert_eq!(vec![0x0123, 0x0123, 0x0123, 0x0123, 0x0123], tables(Mirroring::SingleScreenA));
+        assert_eq!(vec![0x0523, 0x0523, 0x0523, 0x0523, 0x0523], tables(Mirroring::SingleScreenB));
+        assert_eq!(vec![0x0123, 0x0523, 0x0923, 0x0D23, 0x0123], tables(Mirroring::FourScreen));
+    }
+
+    // A board whose mirroring can be switched from the test
+    struct SwitchingMapper {
+        mirroring: Mirroring
+    }
+
+    impl Mapper for SwitchingMapper {
+        fn cpu_read(&self, _addr: u16) -> u8 { 0 }
+        fn cpu_write(&mut self, _addr: u16, _data: u8) {}
+        fn ppu_read(&mut self, _addr: u16) -> u8 { 0 }
+        fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+        fn mirroring(&self) -> Mirroring { self.mirroring }
+    }
+
+    #[test]
+    fn mapper_switches_mirroring() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = SwitchingMapper { mirroring: Mirroring::SingleScreenA };
+        ppu.write_register(0x2006, 0x2C, &mut mapper);
+        ppu.write_register(0x2006, 0x00, &mut mapper);
+        ppu.write_register(0x2007, 0x5A, &mut mapper);
+
+        // When
+        mapper.mirroring = Mirroring::SingleScreenB;
+        ppu.write_register(0x2006, 0x20, &mut mapper);
+        ppu.write_register(0x2006, 0x00, &mut mapper);
+        ppu.read_register(0x2007, &mut mapper);
+
+        // Then
+        assert_eq!(0x00, ppu.read_register(0x2007, &mut mapper));
+        mapper.mirroring = Mirroring::SingleScreenA;
+        ppu.write_register(0x2006, 0x24, &mut mapper);
+        ppu.write_register(0x2006, 0x00, &mut mapper);
+        ppu.read_register(0x2007, &mut mapper);
+        assert_eq!(0x5A, ppu.read_register(0x2007, &mut mapper));
+    }
+
+    #[test]
+    fn oam_data_increments_address() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        ppu.write_register(0x2003, 0x01, &mut mapper);
+
+        // When
+        ppu.write_register(0x2004, 0x12, &mut mapper);
+        ppu.write_register(0x2004, 0xFF, &mut mapper);
+        ppu.write_register(0x2003, 0x02, &mut mapper);
+
+        // Then
+        assert_eq!(0x12, ppu.oam[0x01]);
+        assert_eq!(0xE3, ppu.read_register(0x2004, &mut mapper));
+        assert_eq!(0xE3, ppu.read_register(0x2004, &mut mapper));
+    }
+
+    #[test]
+    fn write_only_registers_read_open_bus() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = nrom();
+        ppu.write_register(0x2000, 0x00, &mut mapper);
+        ppu.write_register(0x3FFD, 0x5F, &mut mapper);
+
+        // When
+        let ctrl = ppu.read_register(0x2000, &mut mapper);
+        let status = ppu.read_register(0x2002, &mut mapper);
+
+        // Then
+        assert_eq!(0x5F, ctrl);
+        assert_eq!(0x1F, status);
+        assert_eq!((0x000B, 0x07), (ppu.temp_address, ppu.fine_x));
+    }
+}
@@ -0,0 +1,481 @@
+// Draws the picture a dot at a time.  Background tiles are fetched every 8 dots into
+// shift registers that feed one pixel per dot, following the scroll position in v.
+// Sprites for the next line are evaluated and fetched at dot 257, where the real PPU
+// spreads that work over dots 65-320.
+// See https://wiki.nesdev.org/w/index.php?title=PPU_rendering
+use crate::mappers::Mapper;
+use super::{Ppu, PRE_RENDER_SCANLINE, SPRITE_OVERFLOW, SPRITE_ZERO_HIT, VISIBLE_SCANLINES};
+use super::frame::{rgb, WIDTH};
+
+// PPUCTRL
+const SPRITE_TABLE: u8 = 0b0000_1000;
+const BACKGROUND_TABLE: u8 = 0b0001_0000;
+const TALL_SPRITES: u8 = 0b0010_0000;
+
+// PPUMASK
+const GREYSCALE: u8 = 0b0000_0001;
+const SHOW_LEFT_BACKGROUND: u8 = 0b0000_0010;
+const SHOW_LEFT_SPRITES: u8 = 0b0000_0100;
+const SHOW_BACKGROUND: u8 = 0b0000_1000;
+const SHOW_SPRITES: u8 = 0b0001_0000;
+
+// Sprite attributes
+// https://wiki.nesdev.org/w/index.php?title=PPU_OAM#Byte_2
+const BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const FLIP_VERTICAL: u8 = 0b1000_0000;
+
+pub const SPRITES_PER_LINE: usize = 8;
+
+// One sprite picked by evaluation, with its row of pattern already fetched
+#[derive(Clone, Copy)]
+pub struct LineSprite {
+    x: u8,
+    attributes: u8,
+    low: u8,
+    high: u8,
+    zero: bool      // OAM entry 0, for sprite 0 hit
+}
+
+impl LineSprite {
+    // The 2 bit pixel at column x of the screen, 0 when transparent or not covered
+    fn pixel(&self, x: usize) -> u8 {
+        let column = x.wrapping_sub(self.x as usize);
+        if column >= 8 {
+            return 0;
+        }
+
+        let bit = if self.attributes & FLIP_HORIZONTAL != 0 { column } else { 7 - column };
+        (((self.high >> bit) & 0x01) << 1) | ((self.low >> bit) & 0x01)
+    }
+}
+
+impl Ppu {
+    pub(super) fn rendering_enabled(&self) -> bool {
+        self.mask & (SHOW_BACKGROUND | SHOW_SPRITES) != 0
+    }
+
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl & TALL_SPRITES != 0 { 16 } else { 8 }
+    }
+
+    // One dot of a visible or the pre-render scanline, in the order of the timing diagram
+    // https://wiki.nesdev.org/w/images/4/4f/Ppu.svg
+    pub(super) fn render_dot(&mut self, mapper: &mut dyn Mapper) {
+        let (scanline, dot) = (self.scanline, self.dot);
+
+        if self.rendering_enabled() {
+            if (2 ..= 257).contains(&dot) || (322 ..= 337).contains(&dot) {
+                self.shift_background();
+            }
+            if (1 ..= 257).contains(&dot) || (321 ..= 337).contains(&dot) {
+                self.fetch_background(mapper);
+            }
+
+            match dot {
+                256 => self.increment_y(),
+                257 => {
+                    self.load_background();
+                    self.copy_horizontal();
+                    if scanline < VISIBLE_SCANLINES - 1 {
+                        self.evaluate_sprites(scanline, mapper);
+                    } else if scanline == PRE_RENDER_SCANLINE {
+                        // Nothing is in range for the first line, but the mapper still
+                        // sees the eight fetches
+                        self.fetch_sprites(scanline, &[], mapper);
+                    }
+                },
+                280 ..= 304 if scanline == PRE_RENDER_SCANLINE => self.copy_vertical(),
+                // Unused nametable fetches, which MMC5 counts
+                338 | 340 => {
+                    self.read_memory(0x2000 | (self.vram_address & 0x0FFF), mapper);
+                },
+                _ => {}
+            }
+        }
+
+        if scanline < VISIBLE_SCANLINES && (1 ..= 256).contains(&dot) {
+            self.output_pixel(dot as usize - 1);
+            if dot == 256 {
+                let start = scanline as usize * WIDTH * 3;
+                self.frame.borrow_mut().pixels[start .. start + WIDTH * 3].copy_from_slice(&self.line);
+            }
+        }
+    }
+
+    // Each tile takes 8 dots:  nametable byte, attribute byte, then the two pattern planes
+    // https://wiki.nesdev.org/w/index.php?title=PPU_scrolling#Tile_and_attribute_fetching
+    fn fetch_background(&mut self, mapper: &mut dyn Mapper) {
+        let v = self.vram_address;
+        match (self.dot - 1) % 8 {
+            0 => {
+                self.load_background();
+                self.next_tile = self.read_memory(0x2000 | (v & 0x0FFF), mapper);
+            },
+            2 => {
+                let attribute = self.read_memory(0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07), mapper);
+                let shift = ((v >> 4) & 0x04) | (v & 0x02);
+                self.next_attribute = (attribute >> shift) & 0x03;
+            },
+            4 => self.next_pattern.0 = mapper.ppu_read(self.pattern_address()),
+            6 => self.next_pattern.1 = mapper.ppu_read(self.pattern_address() + 8),
+            7 => self.increment_coarse_x(),
+            _ => {}
+        }
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl & BACKGROUND_TABLE != 0 { 0x1000 } else { 0x0000 };
+        table + self.next_tile as u16 * 16 + (self.vram_address >> 12)
+    }
+
+    // The fetched tile goes into the low 8 bits, behind the one being drawn
+    fn load_background(&mut self) {
+        let (low, high) = self.next_pattern;
+        self.pattern_shifters.0 = (self.pattern_shifters.0 & 0xFF00) | low as u16;
+        self.pattern_shifters.1 = (self.pattern_shifters.1 & 0xFF00) | high as u16;
+
+        let attribute = self.next_attribute;
+        let fill = |bit: u8| if attribute & bit != 0 { 0x00FF } else { 0x0000 };
+        self.attribute_shifters.0 = (self.attribute_shifters.0 & 0xFF00) | fill(0x01);
+        self.attribute_shifters.1 = (self.attribute_shifters.1 & 0xFF00) | fill(0x02);
+    }
+
+    fn shift_background(&mut self) {
+        self.pattern_shifters.0 <<= 1;
+        self.pattern_shifters.1 <<= 1;
+        self.attribute_shifters.0 <<= 1;
+        self.attribute_shifters.1 <<= 1;
+    }
+
+    // The 2 bit pattern and palette of the background pixel being drawn, fine X picks
+    // which bit of the shift registers that is
+    fn background_pixel(&self) -> u8 {
+        let bit = 0x8000 >> self.fine_x;
+        let plane = |shifter: u16, value: u8| if shifter & bit != 0 { value } else { 0 };
+
+        let pixel = plane(self.pattern_shifters.0, 0x01) | plane(self.pattern_shifters.1, 0x02);
+        if pixel == 0 {
+            return 0;
+        }
+        plane(self.attribute_shifters.0, 0x04) | plane(self.attribute_shifters.1, 0x08) | pixel
+    }
+
+    // Finds the first 8 sprites on the line after scanline and fetches their patterns.
+    // Sprites are drawn one line below their Y coordinate.
+    // See https://wiki.nesdev.org/w/index.php?title=PPU_sprite_evaluation
+    pub(super) fn evaluate_sprites(&mut self, scanline: u16, mapper: &mut dyn Mapper) {
+        let height = self.sprite_height();
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+
+        let mut found: Vec<usize> = Vec::with_capacity(SPRITES_PER_LINE);
+        let mut n = 0;
+        while n < 64 && found.len() < SPRITES_PER_LINE {
+            if in_range(self.oam[n * 4]) {
+                found.push(n);
+            }
+            n += 1;
+        }
+
+        // The hardware bug:  once 8 are found, the byte index within each entry is
+        // incremented along with the entry, so tile numbers and attributes get checked as
+        // Y coordinates.
+        // See https://wiki.nesdev.org/w/index.php?title=PPU_sprite_evaluation#Sprite_overflow_bug
+        let mut m = 0;
+        while n < 64 {
+            if in_range(self.oam[n * 4 + m]) {
+                self.status |= SPRITE_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+
+        self.fetch_sprites(scanline, &found, mapper);
+    }
+
+    // Eight pattern fetches happen regardless, empty slots fetch tile $FF
+    fn fetch_sprites(&mut self, scanline: u16, found: &[usize], mapper: &mut dyn Mapper) {
+        let height = self.sprite_height();
+        self.line_sprites.clear();
+        for slot in 0..SPRITES_PER_LINE {
+            let sprite = found.get(slot).map(|&n| &self.oam[n * 4 .. n * 4 + 4]);
+            let (y, tile, attributes, x) = match sprite {
+                Some(entry) => (entry[0], entry[1], entry[2], entry[3]),
+                None => (scanline as u8, 0xFF, 0, 0xFF)
+            };
+
+            let mut row = scanline.wrapping_sub(y as u16) & (height - 1);
+            if attributes & FLIP_VERTICAL != 0 {
+                row = height - 1 - row;
+            }
+            let address = if height == 16 {
+                let table = (tile as u16 & 0x01) * 0x1000;
+                table + ((tile as u16 & 0xFE) + row / 8) * 16 + row % 8
+            } else {
+                let table = if self.ctrl & SPRITE_TABLE != 0 { 0x1000 } else { 0x0000 };
+                table + tile as u16 * 16 + row
+            };
+            let low = mapper.ppu_read(address);
+            let high = mapper.ppu_read(address + 8);
+
+            if let Some(&n) = found.get(slot) {
+                self.line_sprites.push(LineSprite { x, attributes, low, high, zero: n == 0 });
+            }
+        }
+    }
+
+    // Picks between the background and sprite pixel at x and draws it into the line
+    fn output_pixel(&mut self, x: usize) {
+        let left = x < 8;
+        let background = if self.mask & SHOW_BACKGROUND != 0 && (!left || self.mask & SHOW_LEFT_BACKGROUND != 0) {
+            self.background_pixel()
+        } else {
+            0
+        };
+        let sprite = if self.mask & SHOW_SPRITES != 0 && (!left || self.mask & SHOW_LEFT_SPRITES != 0) {
+            self.line_sprites.iter()
+                .map(|sprite| (sprite, sprite.pixel(x)))
+                .find(|(_, pixel)| *pixel != 0)
+        } else {
+            None
+        };
+
+        // https://wiki.nesdev.org/w/index.php?title=PPU_OAM#Sprite_zero_hits
+        if let Some((sprite, _)) = sprite {
+            if sprite.zero && background != 0 && x != 255 {
+                self.status |= SPRITE_ZERO_HIT;
+            }
+        }
+
+        // https://wiki.nesdev.org/w/index.php?title=PPU_rendering#Preliminary_notes
+        let entry = match sprite {
+            Some((sprite, pixel)) if background == 0 || sprite.attributes & BEHIND_BACKGROUND == 0 => {
+                0x10 | ((sprite.attributes & 0x03) << 2) | pixel
+            },
+            _ => background
+        };
+
+        let mut colour = self.palette[super::palette_address(entry as u16)];
+        if self.mask & GREYSCALE != 0 {
+            colour &= 0x30;
+        }
+        let (red, green, blue) = rgb(colour, self.mask >> 5);
+        self.line[x * 3] = red;
+        self.line[x * 3 + 1] = green;
+        self.line[x * 3 + 2] = blue;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mappers::Mapper;
+    use crate::mappers::nrom::NROM;
+    use crate::ppu::{Ppu, PRE_RENDER_SCANLINE, SPRITE_OVERFLOW, SPRITE_ZERO_HIT};
+
+    const WHITE: (u8, u8, u8) = (0xFF, 0xFF, 0xFF);
+    const BLACK: (u8, u8, u8) = (0x05, 0x05, 0x05);
+    const RED: (u8, u8, u8) = (0xFF, 0x61, 0x8B);
+
+    // Tile 1 has a solid top row in the background table, tile 2 the same in the sprite
+    // table, with a black backdrop, white background and red sprites
+    fn setup() -> (Ppu, NROM) {
+        let mut ppu = Ppu::new();
+        let mut mapper = NROM::from_program(vec![0; 0x4000]);
+        mapper.ppu_write(0x0010, 0xFF);
+        mapper.ppu_write(0x1020, 0xFF);
+        ppu.vram[0] = 0x01;
+        ppu.palette[0x00] = 0x0F;
+        ppu.palette[0x01] = 0x30;
+        ppu.palette[0x11] = 0x25;
+        ppu.ctrl = 0x08;
+        ppu.mask = 0x1E;
+        (ppu, mapper)
+    }
+
+    fn sprite(ppu: &mut Ppu, index: usize, y: u8, tile: u8, attributes: u8, x: u8) {
+        ppu.oam[index * 4 .. index * 4 + 4].copy_from_slice(&[y, tile, attributes, x]);
+    }
+
+    // Runs from the start of the pre-render line until lines have been drawn
+    fn draw(ppu: &mut Ppu, mapper: &mut NROM, lines: u16) {
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.dot = 0;
+        while ppu.scanline == PRE_RENDER_SCANLINE || ppu.scanline < lines {
+            ppu.tick(1, mapper);
+        }
+    }
+
+    fn pixel(ppu: &Ppu, x: usize, y: usize) -> (u8, u8, u8) {
+        ppu.frame.borrow().pixel(x, y)
+    }
+
+    #[test]
+    fn background_tile() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_eq!(WHITE, pixel(&ppu, 0, 0));
+        assert_eq!(WHITE, pixel(&ppu, 7, 0));
+        assert_eq!(BLACK, pixel(&ppu, 8, 0));
+        assert_eq!(BLACK, pixel(&ppu, 0, 1));
+    }
+
+    #[test]
+    fn fine_x_scroll() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        ppu.fine_x = 3;
+
+        // When
+        draw(&mut ppu, &mut mapper, 1);
+
+        // Then
+        assert_eq!(WHITE, pixel(&ppu, 4, 0));
+        assert_eq!(BLACK, pixel(&ppu, 5, 0));
+    }
+
+    #[test]
+    fn left_column_clipping() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        ppu.mask = 0x18;
+        sprite(&mut ppu, 0, 0, 0x02, 0x00, 4);
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_eq!(BLACK, pixel(&ppu, 7, 1));
+        assert_eq!(RED, pixel(&ppu, 8, 1));
+    }
+
+    #[test]
+    fn sprite_priority() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        mapper.ppu_write(0x0011, 0xFF);
+        sprite(&mut ppu, 0, 0, 0x02, 0x20, 4);      // Behind the background
+        sprite(&mut ppu, 1, 0, 0x02, 0x00, 12);
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_eq!(WHITE, pixel(&ppu, 7, 1));
+        assert_eq!(RED, pixel(&ppu, 8, 1));
+        assert_eq!(RED, pixel(&ppu, 12, 1));
+        assert_eq!(BLACK, pixel(&ppu, 20, 1));
+    }
+
+    #[test]
+    fn sprite_zero_hit() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        mapper.ppu_write(0x0011, 0xFF);
+        sprite(&mut ppu, 0, 0, 0x02, 0x00, 5);
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_ne!(0, ppu.status & SPRITE_ZERO_HIT);
+    }
+
+    #[test]
+    fn no_sprite_zero_hit_on_transparent_background() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        sprite(&mut ppu, 0, 0, 0x02, 0x00, 8);
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_eq!(0, ppu.status & SPRITE_ZERO_HIT);
+    }
+
+    #[test]
+    fn eight_sprites_per_line() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        for index in 0..9 {
+            sprite(&mut ppu, index, 0, 0x02, 0x00, index as u8 * 8);
+        }
+
+        // When
+        draw(&mut ppu, &mut mapper, 2);
+
+        // Then
+        assert_eq!(8, ppu.line_sprites.len());
+        assert_ne!(0, ppu.status & SPRITE_OVERFLOW);
+        assert_eq!(RED, pixel(&ppu, 63, 1));
+        assert_eq!(BLACK, pixel(&ppu, 64, 1));
+    }
+
+    #[test]
+    fn sprite_overflow_bug() {
+        // Given, 8 sprites on the line and a 10th whose tile number looks like a Y in range
+        let (mut ppu, mut mapper) = setup();
+        for index in 0..64 {
+            sprite(&mut ppu, index, if index < 8 { 0 } else { 0xF0 }, 0xF0, 0xF0, 0xF0);
+        }
+        ppu.oam[9 * 4 + 1] = 0x00;
+
+        // When
+        ppu.evaluate_sprites(0, &mut mapper);
+
+        // Then
+        assert_ne!(0, ppu.status & SPRITE_OVERFLOW);
+    }
+
+    #[test]
+    fn no_overflow_from_pre_render_line() {
+        // Given, every sprite hidden at Y = $FF, which is in range of the pre-render line
+        let (mut ppu, mut mapper) = setup();
+        for index in 0..64 {
+            sprite(&mut ppu, index, 0xFF, 0xF0, 0xF0, 0xF0);
+        }
+
+        // When
+        draw(&mut ppu, &mut mapper, 0);
+
+        // Then
+        assert_eq!(0, ppu.status & SPRITE_OVERFLOW);
+    }
+
+    #[test]
+    fn tall_sprites() {
+        // Given, tile 3 is the bottom half of an odd tile pair in the $1000 table
+        let (mut ppu, mut mapper) = setup();
+        ppu.ctrl = 0x20;
+        mapper.ppu_write(0x1031, 0x80);
+        sprite(&mut ppu, 0, 10, 0x03, 0x00, 16);
+
+        // When
+        draw(&mut ppu, &mut mapper, 21);
+
+        // Then
+        assert_eq!(RED, pixel(&ppu, 16, 20));
+        assert_eq!(BLACK, pixel(&ppu, 17, 20));
+    }
+
+    #[test]
+    fn greyscale() {
+        // Given
+        let (mut ppu, mut mapper) = setup();
+        ppu.mask |= 0x01;
+        ppu.palette[0x01] = 0x16;
+
+        // When
+        draw(&mut ppu, &mut mapper, 1);
+
+        // Then
+        assert_eq!((0xC7, 0xC7, 0xC7), pixel(&ppu, 0, 0));
+        assert_eq!((0x80, 0x80, 0x80), pixel(&ppu, 8, 0));
+    }
+}
@@ -0,0 +1,371 @@
+// The scroll registers v, t, x and w, and the ways register writes and rendering change
+// them.  v and t are laid out as yyy NN YYYYY XXXXX:  fine Y, nametable, coarse Y and
+// coarse X.
+// See https://wiki.nesdev.org/w/index.php?title=PPU_scrolling
+use super::Ppu;
+
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const FINE_Y: u16 = 0x7000;
+
+const HORIZONTAL: u16 = NAMETABLE_X | COARSE_X;
+const VERTICAL: u16 = FINE_Y | NAMETABLE_Y | COARSE_Y;
+
+impl Ppu {
+    // $2000 write:  t: ...GH.. ........ <- d: ......GH
+    pub(super) fn write_nametable_select(&mut self, data: u8) {
+        self.temp_address = (self.temp_address & !(NAMETABLE_X | NAMETABLE_Y)) | ((data as u16 & 0x03) << 10);
+    }
+
+    // $2005 first write:   t: ....... ...ABCDE <- d: ABCDE...,  x <- d: .....FGH
+    // $2005 second write:  t: FGH..AB CDE..... <- d: ABCDEFGH
+    pub(super) fn write_scroll(&mut self, data: u8) {
+        if self.write_toggle {
+            self.temp_address = (self.temp_address & !(FINE_Y | COARSE_Y))
+                | ((data as u16 & 0x07) << 12)
+                | ((data as u16 & 0xF8) << 2);
+        } else {
+            self.temp_address = (self.temp_address & !COARSE_X) | (data as u16 >> 3);
+            self.fine_x = data & 0x07;
+        }
+        self.write_toggle = !self.write_toggle;
+    }
+
+    // $2006 first write:   t: .CDEFGH ........ <- d: ..CDEFGH, bit 14 cleared
+    // $2006 second write:  t: ....... ABCDEFGH <- d: ABCDEFGH,  v <- t
+    pub(super) fn write_address(&mut self, data: u8) {
+        if self.write_toggle {
+            self.temp_address = (self.temp_address & 0xFF00) | data as u16;
+            self.vram_address = self.temp_address;
+        } else {
+            self.temp_address = (self.temp_address & 0x00FF) | ((data as u16 & 0x3F) << 8);
+        }
+        self.write_toggle = !self.write_toggle;
+    }
+
+    // After each tile fetch, wrapping into the horizontally adjacent nametable
+    pub(super) fn increment_coarse_x(&mut self) {
+        if self.vram_address & COARSE_X == 31 {
+            self.vram_address &= !COARSE_X;
+            self.vram_address ^= NAMETABLE_X;
+        } else {
+            self.vram_address += 1;
+        }
+    }
+
+    // At dot 256.  Coarse Y wraps into the next nametable after row 29, the last row of
+    // tiles, but rows 30 and 31 (the attribute table) wrap without switching.
+    pub(super) fn increment_y(&mut self) {
+        if self.vram_address & FINE_Y != FINE_Y {
+            self.vram_address += 0x1000;
+            return;
+        }
+
+        self.vram_address &= !FINE_Y;
+        let coarse_y = match (self.vram_address & COARSE_Y) >> 5 {
+            29 => {
+                self.vram_address ^= NAMETABLE_Y;
+                0
+            },
+            31 => 0,
+            coarse_y => coarse_y + 1
+        };
+        self.vram_address = (self.vram_address & !COARSE_Y) | (coarse_y << 5);
+    }
+
+    // At dot 257
+    pub(super) fn copy_horizontal(&mut self) {
+        self.vram_address = (self.vram_address & !HORIZONTAL) | (self.temp_address & HORIZONTAL);
+    }
+
+    // Over dots 280-304 of the pre-render line
+    pub(super) fn copy_vertical(&mut self) {
+        self.vram_address = (self.vram_address & !VERTICAL) | (self.temp_address & VERTICAL);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bus::NesBus;
+    use crate::cpu::CPU;
+    use crate::mappers::nrom::NROM;
+    use crate::ppu::frame::Frame;
+    use crate::ppu::Ppu;
+    use crate::rom::INesRom;
+
+    // The register walkthrough from the nesdev wiki
+    #[test]
+    fn register_writes() {
+        // Given
+        let mut ppu = Ppu::new();
+        let mut mapper = NROM::from_program(vec![0; 0x4000]);
+
+        // When
+        ppu.write_register(0x2000, 0x02, &mut mapper);
+        ppu.read_register(0x2002, &mut mapper);
+        ppu.write_register(0x2005, 0x7D, &mut mapper);
+
+        // Then
+        assert_eq!((0x080F, 0x05, true), (ppu.temp_address, ppu.fine_x, ppu.write_toggle));
+        ppu.write_register(0x2005, 0x5E, &mut mapper);
+        assert_eq!((0x696F, false), (ppu.temp_address, ppu.write_toggle));
+        ppu.write_register(0x2006, 0x3D, &mut mapper);
+        assert_eq!(0x3D6F, ppu.temp_address);
+        ppu.write_register(0x2006, 0xF0, &mut mapper);
+        assert_eq!((0x3DF0, 0x3DF0), (ppu.temp_address, ppu.vram_address));
+    }
+
+    #[test]
+    fn coarse_x_wraps_to_next_nametable() {
+        // Given
+        let mut ppu = Ppu::new();
+        ppu.vram_address = 0x001F;
+
+        // When
+        ppu.increment_coarse_x();
+
+        // Then
+        assert_eq!(0x0400, ppu.vram_address);
+    }
+
+    #[test]
+    fn y_wraps_to_next_nametable_after_row_29() {
+        // Given
+        let mut ppu = Ppu::new();
+        ppu.vram_address = 0x73A5;
+
+        // When
+        ppu.increment_y();
+
+        // Then
+        assert_eq!(0x0805, ppu.vram_address);
+    }
+
+    #[test]
+    fn y_wraps_in_attribute_rows() {
+        // Given
+        let mut ppu = Ppu::new();
+        ppu.vram_address = 0x73E0;
+
+        // When
+        ppu.increment_y();
+
+        // Then
+        assert_eq!(0x0000, ppu.vram_address);
+    }
+
+    #[test]
+    fn copies_from_temp_address() {
+        // Given
+        let mut ppu = Ppu::new();
+        ppu.vram_address = 0x0000;
+        ppu.temp_address = 0x7FFF;
+
+        // When
+        ppu.copy_horizontal();
+
+        // Then
+        assert_eq!(0x041F, ppu.vram_address);
+        ppu.copy_vertical();
+        assert_eq!(0x7FFF, ppu.vram_address);
+    }
+
+    // Wraps a program at $C000 in a vertically mirrored NROM image.  Tile 1 of the CHR is
+    // solid colour 1.
+    fn rom(program: &[u8]) -> INesRom {
+        let mut contents = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut prg = vec![0xEA; 0x4000];
+        prg[.. program.len()].copy_from_slice(program);
+        prg[0x3FFC .. 0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+        let mut chr = vec![0; 0x2000];
+        chr[0x10 .. 0x18].copy_from_slice(&[0xFF; 8]);
+
+        contents.extend(prg);
+        contents.extend(chr);
+        INesRom::new(contents).unwrap()
+    }
+
+    // Runs the ROM until frame has been drawn
+    fn run_frames(rom: &INesRom, frames: u64) -> Frame {
+        let bus: NesBus = rom.to_bus().unwrap();
+        let frame = bus.frame();
+        let mut cpu = CPU::new(Box::new(bus));
+
+        while frame.borrow().count < frames && !cpu.halted() {
+            cpu.step();
+        }
+
+        let frame = frame.borrow();
+        Frame { pixels: frame.pixels.clone(), count: frame.count }
+    }
+
+    // Waits for two vblanks, sets a black backdrop with white colour 1, then puts tiles
+    // in the nametable at each of the addresses
+    fn setup(tiles: &[u16]) -> Vec<u8> {
+        let mut program = vec![
+            0x2C, 0x02, 0x20,   // C000:  BIT $2002
+            0x10, 0xFB,         //        BPL $C000
+            0x2C, 0x02, 0x20,   // C005:  BIT $2002
+            0x10, 0xFB,         //        BPL $C005
+            0xA9, 0x3F, 0x8D, 0x06, 0x20, 0xA9, 0x00, 0x8D, 0x06, 0x20,     // $2006 = $3F00
+            0xA9, 0x0F, 0x8D, 0x07, 0x20, 0xA9, 0x30, 0x8D, 0x07, 0x20,     // $2007 = $0F, $30
+        ];
+        for address in tiles {
+            program.extend(&[0xA9, (address >> 8) as u8, 0x8D, 0x06, 0x20,
+                             0xA9, *address as u8, 0x8D, 0x06, 0x20,
+                             0xA9, 0x01, 0x8D, 0x07, 0x20]);
+        }
+
+        program
+    }
+
+    // Sprite 0 at (x, y), behind the background, through OAMADDR/OAMDATA, then a frame loop that resets the scroll
+    // in vblank, shows everything and waits for sprite 0 hit before running split
+    fn frame_loop(mut program: Vec<u8>, sprite: (u8, u8), split: &[u8]) -> Vec<u8> {
+        program.extend(&[0xA9, 0x00, 0x8D, 0x03, 0x20,
+                         0xA9, sprite.1, 0x8D, 0x04, 0x20, 0xA9, 0x01, 0x8D, 0x04, 0x20,
+                         0xA9, 0x20, 0x8D, 0x04, 0x20, 0xA9, sprite.0, 0x8D, 0x04, 0x20]);
+
+        let start = 0xC000 + program.len() as u16;
+        program.extend(&[
+            0x2C, 0x02, 0x20,   // BIT $2002
+            0x10, 0xFB,         // BPL, wait for vblank
+            0xA9, 0x00, 0x8D, 0x05, 0x20, 0x8D, 0x05, 0x20,     // Scroll to 0, 0
+            0x8D, 0x00, 0x20,   // Nametable $2000
+            0xA9, 0x1E, 0x8D, 0x01, 0x20,                       // Show background and sprites
+            0x2C, 0x02, 0x20,   // BIT $2002
+            0x70, 0xFB,         // BVS, wait for the pre-render line to clear sprite 0 hit
+            0x2C, 0x02, 0x20,   // BIT $2002
+            0x50, 0xFB,         // BVC, wait for sprite 0 hit
+        ]);
+        program.extend(split);
+        program.extend(&[0x4C, start as u8, (start >> 8) as u8]);
+
+        program
+    }
+
+    fn white(frame: &Frame, x: usize, y: usize) -> bool {
+        frame.pixel(x, y) == (0xFF, 0xFF, 0xFF)
+    }
+
+    #[test]
+    fn rom_draws_unscrolled() {
+        // Given, tile at column 1, row 1 and sprite 0 on top of it
+        let program = frame_loop(setup(&[0x2021]), (8, 7), &[]);
+
+        // When
+        let frame = run_frames(&rom(&program), 4);
+
+        // Then
+        assert!(white(&frame, 8, 8));
+        assert!(white(&frame, 15, 15));
+        assert!(!white(&frame, 7, 8));
+        assert!(!white(&frame, 8, 16));
+    }
+
+    #[test]
+    fn horizontal_split_on_sprite_zero_hit() {
+        // Given, a column of tiles at x = 8 and a split that scrolls 4 pixels right
+        let column: Vec<u16> = (0..30).map(|row| 0x2001 + row * 32).collect();
+        let program = frame_loop(setup(&column), (8, 99), &[
+            0xA9, 0x04, 0x8D, 0x05, 0x20,   // $2005 = 4
+            0xA9, 0x00, 0x8D, 0x05, 0x20,   // $2005 = 0, has no effect until the next frame
+        ]);
+
+        // When
+        let frame = run_frames(&rom(&program), 4);
+
+        // Then
+        assert!(white(&frame, 8, 50));
+        assert!(!white(&frame, 4, 50));
+        assert!(white(&frame, 8, 99));
+        assert!(white(&frame, 4, 110));
+        assert!(!white(&frame, 12, 110));
+        assert!(white(&frame, 4, 239));
+    }
+
+    #[test]
+    fn vertical_split_through_ppuaddr() {
+        // Given, a tile at the top left, one under sprite 0 at row 12, and a split that
+        // points v back at the top of the nametable
+        let program = frame_loop(setup(&[0x2000, 0x218A]), (80, 95), &[
+            0xA9, 0x20, 0x8D, 0x06, 0x20,   // $2006 = $20
+            0xA9, 0x00, 0x8D, 0x06, 0x20,   // $2006 = $00
+        ]);
+
+        // When
+        let frame = run_frames(&rom(&program), 4);
+
+        // Then
+        assert!(white(&frame, 0, 3));
+        assert!(white(&frame, 0, 100));
+        assert!(!white(&frame, 0, 120));
+        assert!(!white(&frame, 80, 120));
+    }
+
+    #[test]
+    fn fine_x_and_nametable_from_registers() {
+        // Given, a tile at the right edge of nametable $2000 and the left edge of $2400,
+        // scrolled 3 pixels into $2000 via PPUCTRL and PPUSCROLL
+        let mut program = setup(&[0x201F, 0x2400, 0x2040]);
+        program.extend(&[
+            0x2C, 0x02, 0x20,   // BIT $2002
+            0x10, 0xFB,         // BPL
+            0xA9, 0x03, 0x8D, 0x05, 0x20,   // $2005 = 3
+            0xA9, 0x00, 0x8D, 0x05, 0x20,   // $2005 = 0
+            0x8D, 0x00, 0x20,               // $2000 = 0
+            0xA9, 0x0A, 0x8D, 0x01, 0x20,   // Show background
+        ]);
+        let end = 0xC000 + program.len() as u16;
+        program.extend(&[0x4C, end as u8, (end >> 8) as u8]);
+
+        // When
+        let frame = run_frames(&rom(&program), 4);
+
+        // Then
+        assert!(white(&frame, 0, 16));      // Row 2, column 0, 3 pixels in
+        assert!(white(&frame, 4, 16));
+        assert!(!white(&frame, 5, 16));
+        assert!(white(&frame, 245, 0));     // Column 31 of $2000
+        assert!(white(&frame, 253, 0));     // Column 0 of $2400
+        assert!(!white(&frame, 244, 0));
+        assert!(!white(&frame, 0, 0));
+    }
+
+    #[test]
+    fn ppuaddr_write_lands_mid_instruction() {
+        // Given, a row of tiles in nametable $2400 and a $2006 write timed from power-on
+        // so the second write is on scanline 4, dot 100.  The next tile fetch is at dot
+        // 105 and is drawn from x = 120.  Ticking the PPU after the whole STA would
+        // move the write 4 cycles earlier, to dot 88.
+        let mut program = vec![
+            0xA9, 0x24, 0x8D, 0x06, 0x20, 0xA9, 0x00, 0x8D, 0x06, 0x20,     // $2006 = $2400
+            0xA9, 0x01,
+        ];
+        for _ in 0..32 {
+            program.extend(&[0x8D, 0x07, 0x20]);                            // $2007 = 1
+        }
+        program.extend(&[
+            0xA9, 0x3F, 0x8D, 0x06, 0x20, 0xA9, 0x00, 0x8D, 0x06, 0x20,     // $2006 = $3F00
+            0xA9, 0x0F, 0x8D, 0x07, 0x20, 0xA9, 0x30, 0x8D, 0x07, 0x20,     // $2007 = $0F, $30
+            0xA9, 0x20, 0x8D, 0x06, 0x20, 0xA9, 0x00, 0x8D, 0x06, 0x20,     // $2006 = $2000
+            0xA9, 0x0A, 0x8D, 0x01, 0x20,                                   // Show background
+        ]);
+        program.extend(vec![0xEA; 143]);
+        program.extend(&[
+            0xA9, 0x24, 0x8D, 0x06, 0x20, 0xA9, 0x00, 0x8D, 0x06, 0x20,     // $2006 = $2400
+        ]);
+        let end = 0xC000 + program.len() as u16;
+        program.extend(&[0x4C, end as u8, (end >> 8) as u8]);
+
+        // When
+        let frame = run_frames(&rom(&program), 1);
+
+        // Then
+        assert!(!white(&frame, 119, 4));
+        assert!(white(&frame, 120, 4));
+        assert!(!white(&frame, 120, 3));
+    }
+}
@@ -2,9 +2,18 @@ use std::convert::TryInto;
 // Intended to support both iNes and iNes 2.0 formats (2.0 should be backwards compatible.
 // Reference:  https://wiki.nesdev.org/w/index.php?title=INES#iNES_file_format
 // https://wiki.nesdev.org/w/index.php?title=NES_2.0
+use std::error::Error;
 use std::fmt;
 use std::fmt::Formatter;
+use crate::bus::NesBus;
 use crate::cpu::CPU;
+use crate::mappers;
+
+// Exponent-multiplier sizes this large can't describe a real cartridge
+const MAX_ROM_AREA_BYTES: usize = 0xFFFF_FFFF;
+
+// "NES" followed by MS-DOS end-of-file
+const INES_IDENTIFIER: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
 
 enum INesFormat {
     ArchaicINes,
@@ -12,9 +21,44 @@ enum INesFormat {
     INes2
 }
 
-enum Mirroring {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mirroring {
     Horizontal,
-    Vertical
+    Vertical,
+    // Only selectable by mappers, both nametables map to the same 1KB of VRAM
+    SingleScreenA,
+    SingleScreenB,
+    // The cartridge adds 2KB of VRAM so all four nametables are separate
+    FourScreen
+}
+
+// https://wiki.nesdev.org/w/index.php?title=NES_2.0#Console_Type
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice10,
+    Extended(u8)
+}
+
+// https://wiki.nesdev.org/w/index.php?title=NES_2.0#CPU.2FPPU_Timing
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    BadMagic,
+    TruncatedHeader,
+    TruncatedTrainer,
+    TruncatedPrgRom { expected: usize, found: usize },
+    TruncatedChrRom { expected: usize, found: usize },
+    EmptyPrgRom,
+    SizeMismatch { expected: usize, found: usize },
+    OversizedExponent,
+    UnsupportedMapper(u16)
 }
 
 pub struct INes2Header {
@@ -23,7 +67,12 @@ pub struct INes2Header {
 
 pub struct INesRom {
     pub header: INes2Header,
-    prg_rom: [u8; 0x4000]
+    pub trainer: Option<Vec<u8>>,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+    // Nothing consumes the misc ROM area yet
+    #[allow(dead_code)]
+    pub misc_rom: Vec<u8>
 }
 
 impl INes2Header {
@@ -32,41 +81,345 @@ impl INes2Header {
     }
 
     fn has_ines_identifier(&self) -> bool {
-        &self.data[0..4] == [0x4E, 0x45, 0x53, 0x1A]
+        self.data[0..4] == INES_IDENTIFIER
     }
 
+    // The identifier alone decides NES 2.0.  nesdev also suggests checking the sizes from
+    // byte 9 against the file, but a file too short for its header is broken, and the
+    // loader reports it as truncated rather than guessing at another format.
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Identification
     fn format(&self) -> INesFormat {
-        if self.data[7] & 0x0C == 0x08 {
-            // TODO:  Also check total size including byte 9 - need a reference to the whole rom?
-            INesFormat::INes2
-        } else if self.data[7] & 0x0C == 0 && &self.data[12..16] == [0, 0, 0 ,0] {
-            INesFormat::INes
-        } else {
-            INesFormat::ArchaicINes
+        match self.data[7] & 0x0C {
+            0x08 => INesFormat::INes2,
+            0x00 if self.data[12..16] == [0, 0, 0, 0] => INesFormat::INes,
+            _ => INesFormat::ArchaicINes
         }
     }
 
+    fn has_oversized_exponent(&self) -> bool {
+        self.prg_rom_size_bytes() > MAX_ROM_AREA_BYTES || self.chr_rom_size_bytes() > MAX_ROM_AREA_BYTES
+    }
+
+    fn is_nes2(&self) -> bool {
+        matches!(self.format(), INesFormat::INes2)
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Mapper_Number
+    pub fn mapper_number(&self) -> u16 {
+        let low = (self.data[6] >> 4) as u16;
+        match self.format() {
+            INesFormat::INes2 => ((self.data[8] & 0x0F) as u16) << 8 | (self.data[7] & 0xF0) as u16 | low,
+            INesFormat::INes => (self.data[7] & 0xF0) as u16 | low,
+            // Byte 7 of archaic dumps often holds part of a ripper's signature
+            INesFormat::ArchaicINes => low
+        }
+    }
+
+    pub fn submapper(&self) -> u8 {
+        if self.is_nes2() { self.data[8] >> 4 } else { 0 }
+    }
+
     // https://wiki.nesdev.org/w/index.php?title=NES_2.0#PRG-ROM_Area
-    fn prg_rom_size_bytes(&self) -> usize {
-        (self.data[4] as u16 * 0x4000u16) as usize
+    pub fn prg_rom_size_bytes(&self) -> usize {
+        let msb = if self.is_nes2() { self.data[9] & 0x0F } else { 0 };
+        rom_size(self.data[4], msb, 0x4000)
     }
 
     // https://wiki.nesdev.org/w/index.php?title=NES_2.0#CHR-ROM_Area
-    fn chr_rom_size_bytes(&self) -> usize {
-        (self.data[5] as u16 * 0x2000u16) as usize
+    pub fn chr_rom_size_bytes(&self) -> usize {
+        let msb = if self.is_nes2() { self.data[9] >> 4 } else { 0 };
+        rom_size(self.data[5], msb, 0x2000)
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#PRG-.28NV.29RAM.2FEEPROM
+    pub fn prg_ram_size_bytes(&self) -> usize {
+        match self.format() {
+            INesFormat::INes2 => shift_size(self.data[10] & 0x0F),
+            _ if self.has_battery() => 0,
+            _ => self.ines_prg_ram_size_bytes()
+        }
+    }
+
+    pub fn prg_nvram_size_bytes(&self) -> usize {
+        match self.format() {
+            INesFormat::INes2 => shift_size(self.data[10] >> 4),
+            _ if self.has_battery() => self.ines_prg_ram_size_bytes(),
+            _ => 0
+        }
+    }
+
+    // iNES stores PRG RAM in 8KB units, with 0 meaning 8KB for compatibility
+    // https://wiki.nesdev.org/w/index.php?title=INES#Flags_8
+    fn ines_prg_ram_size_bytes(&self) -> usize {
+        match self.format() {
+            INesFormat::INes if self.data[8] > 0 => self.data[8] as usize * 0x2000,
+            _ => 0x2000
+        }
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#CHR-.28NV.29RAM
+    pub fn chr_ram_size_bytes(&self) -> usize {
+        match self.format() {
+            INesFormat::INes2 => shift_size(self.data[11] & 0x0F),
+            _ if self.chr_rom_size_bytes() == 0 => 0x2000,
+            _ => 0
+        }
+    }
+
+    pub fn chr_nvram_size_bytes(&self) -> usize {
+        if self.is_nes2() { shift_size(self.data[11] >> 4) } else { 0 }
     }
 
-    fn has_trainer_data(&self) -> bool {
+    pub fn has_trainer_data(&self) -> bool {
         self.data[6] & 0b0000_0100 == 0b0000_0100
     }
 
-    fn mirroring(&self) -> Mirroring {
-        if self.data[6] & 1 == 1 {
+    fn trainer_size_bytes(&self) -> usize {
+        if self.has_trainer_data() { 512 } else { 0 }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.data[6] & 0b0000_0010 == 0b0000_0010
+    }
+
+    pub fn has_four_screen_vram(&self) -> bool {
+        self.data[6] & 0b0000_1000 == 0b0000_1000
+    }
+
+    // Four-screen boards ignore the mirroring bit
+    // https://wiki.nesdev.org/w/index.php?title=INES#Flags_6
+    pub fn mirroring(&self) -> Mirroring {
+        if self.has_four_screen_vram() {
+            Mirroring::FourScreen
+        } else if self.data[6] & 1 == 1 {
             Mirroring::Vertical
         } else {
             Mirroring::Horizontal
         }
     }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Console_Type
+    pub fn console_type(&self) -> ConsoleType {
+        match self.format() {
+            INesFormat::INes2 => match self.data[7] & 0x03 {
+                0 => ConsoleType::Nes,
+                1 => ConsoleType::VsSystem,
+                2 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Extended(self.data[13] & 0x0F)
+            },
+            INesFormat::INes if self.data[7] & 0x01 == 0x01 => ConsoleType::VsSystem,
+            INesFormat::INes if self.data[7] & 0x02 == 0x02 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Nes
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        match self.format() {
+            INesFormat::INes2 => match self.data[12] & 0x03 {
+                0 => Timing::Ntsc,
+                1 => Timing::Pal,
+                2 => Timing::MultiRegion,
+                _ => Timing::Dendy
+            },
+            INesFormat::INes if self.data[9] & 0x01 == 0x01 => Timing::Pal,
+            _ => Timing::Ntsc
+        }
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Vs._System_Type
+    pub fn vs_ppu_type(&self) -> Option<u8> {
+        match (self.format(), self.console_type()) {
+            (INesFormat::INes2, ConsoleType::VsSystem) => Some(self.data[13] & 0x0F),
+            _ => None
+        }
+    }
+
+    pub fn vs_hardware_type(&self) -> Option<u8> {
+        match (self.format(), self.console_type()) {
+            (INesFormat::INes2, ConsoleType::VsSystem) => Some(self.data[13] >> 4),
+            _ => None
+        }
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Miscellaneous_ROM_Area
+    pub fn misc_rom_count(&self) -> u8 {
+        if self.is_nes2() { self.data[14] & 0x03 } else { 0 }
+    }
+
+    // https://wiki.nesdev.org/w/index.php?title=NES_2.0#Default_Expansion_Device
+    pub fn default_expansion_device(&self) -> u8 {
+        if self.is_nes2() { self.data[15] & 0x3F } else { 0 }
+    }
+}
+
+// ROM areas are either a 12 bit count of units, or when the MSB nibble is all 1s,
+// an exponent-multiplier pair in the LSB:  EEEEEEMM  =>  2^E * (MM*2 + 1)
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+
+        2usize.checked_pow(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .unwrap_or(usize::MAX)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * unit
+    }
+}
+
+// RAM areas are stored as a shift count:  64 << shift, or nothing when 0
+fn shift_size(shift: u8) -> usize {
+    if shift == 0 { 0 } else { 64 << shift }
+}
+
+impl fmt::Display for Mirroring {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Mirroring::Horizontal => write!(f, "Horizontal"),
+            Mirroring::Vertical => write!(f, "Vertical"),
+            Mirroring::SingleScreenA => write!(f, "Single-screen A"),
+            Mirroring::SingleScreenB => write!(f, "Single-screen B"),
+            Mirroring::FourScreen => write!(f, "Four-screen")
+        }
+    }
+}
+
+impl fmt::Display for ConsoleType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleType::Nes => write!(f, "NES/Famicom"),
+            ConsoleType::VsSystem => write!(f, "Vs. System"),
+            ConsoleType::Playchoice10 => write!(f, "Playchoice 10"),
+            ConsoleType::Extended(kind) => write!(f, "Extended ({})", extended_console_name(*kind))
+        }
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Timing::Ntsc => write!(f, "NTSC (RP2C02)"),
+            Timing::Pal => write!(f, "PAL (RP2C07)"),
+            Timing::MultiRegion => write!(f, "Multiple-region"),
+            Timing::Dendy => write!(f, "Dendy (UA6538)")
+        }
+    }
+}
+
+// https://wiki.nesdev.org/w/index.php?title=NES_2.0#Extended_Console_Type
+fn extended_console_name(kind: u8) -> &'static str {
+    match kind {
+        0x0 => "Regular NES/Famicom/Dendy",
+        0x1 => "Nintendo Vs. System",
+        0x2 => "Playchoice 10",
+        0x3 => "Famiclone with Decimal Mode",
+        0x4 => "NES/Famicom with EPSM module",
+        0x5 => "V.R. Technology VT01",
+        0x6 => "V.R. Technology VT02",
+        0x7 => "V.R. Technology VT03",
+        0x8 => "V.R. Technology VT09",
+        0x9 => "V.R. Technology VT32",
+        0xA => "V.R. Technology VT369",
+        0xB => "UMC UM6578",
+        0xC => "Famicom Network System",
+        _ => "Reserved"
+    }
+}
+
+// https://wiki.nesdev.org/w/index.php?title=NES_2.0#Vs._System_Type
+fn vs_ppu_name(kind: u8) -> &'static str {
+    match kind {
+        0x0 => "RP2C03B",
+        0x1 => "RP2C03G",
+        0x2 => "RP2C04-0001",
+        0x3 => "RP2C04-0002",
+        0x4 => "RP2C04-0003",
+        0x5 => "RP2C04-0004",
+        0x6 => "RC2C03B",
+        0x7 => "RC2C03C",
+        0x8 => "RC2C05-01",
+        0x9 => "RC2C05-02",
+        0xA => "RC2C05-03",
+        0xB => "RC2C05-04",
+        0xC => "RC2C05-05",
+        _ => "Reserved"
+    }
+}
+
+fn vs_hardware_name(kind: u8) -> &'static str {
+    match kind {
+        0x0 => "Vs. Unisystem (normal)",
+        0x1 => "Vs. Unisystem (RBI Baseball protection)",
+        0x2 => "Vs. Unisystem (TKO Boxing protection)",
+        0x3 => "Vs. Unisystem (Super Xevious protection)",
+        0x4 => "Vs. Unisystem (Vs. Ice Climber Japan protection)",
+        0x5 => "Vs. Dual System (normal)",
+        0x6 => "Vs. Dual System (Raid on Bungeling Bay protection)",
+        _ => "Reserved"
+    }
+}
+
+// https://wiki.nesdev.org/w/index.php?title=NES_2.0#Default_Expansion_Device
+fn expansion_device_name(device: u8) -> &'static str {
+    match device {
+        0x00 => "Unspecified",
+        0x01 => "Standard NES/Famicom controllers",
+        0x02 => "NES Four Score/Satellite",
+        0x03 => "Famicom Four Players Adapter",
+        0x04 => "Vs. System (1P via $4016)",
+        0x05 => "Vs. System (1P via $4017)",
+        0x07 => "Vs. Zapper",
+        0x08 => "Zapper ($4017)",
+        0x09 => "Two Zappers",
+        0x0A => "Bandai Hyper Shot Lightgun",
+        0x0B => "Power Pad Side A",
+        0x0C => "Power Pad Side B",
+        0x0D => "Family Trainer Side A",
+        0x0E => "Family Trainer Side B",
+        0x0F => "Arkanoid Vaus Controller (NES)",
+        0x10 => "Arkanoid Vaus Controller (Famicom)",
+        0x11 => "Two Vaus Controllers plus Famicom Data Recorder",
+        0x12 => "Konami Hyper Shot Controller",
+        0x13 => "Coconuts Pachinko Controller",
+        0x14 => "Exciting Boxing Punching Bag",
+        0x15 => "Jissen Mahjong Controller",
+        0x16 => "Party Tap",
+        0x17 => "Oeka Kids Tablet",
+        0x18 => "Sunsoft Barcode Battler",
+        0x19 => "Miracle Piano Keyboard",
+        0x1A => "Pokkun Moguraa",
+        0x1B => "Top Rider",
+        0x1C => "Double-Fisted",
+        0x1D => "Famicom 3D System",
+        0x1E => "Doremikko Keyboard",
+        0x1F => "R.O.B. Gyro Set",
+        0x20 => "Famicom Data Recorder",
+        0x21 => "ASCII Turbo File",
+        0x22 => "IGS Storage Battle Box",
+        0x23 => "Family BASIC Keyboard plus Famicom Data Recorder",
+        0x24 => "Dongda PEC-586 Keyboard",
+        0x25 => "Bit Corp. Bit-79 Keyboard",
+        0x26 => "Subor Keyboard",
+        0x27 => "Subor Keyboard plus mouse (3x8-bit protocol)",
+        0x28 => "Subor Keyboard plus mouse (24-bit protocol)",
+        0x29 => "SNES Mouse",
+        0x2A => "Multicart",
+        0x2B => "Two SNES controllers",
+        0x2C => "RacerMate Bicycle",
+        0x2D => "U-Force",
+        0x2E => "R.O.B. Stack-Up",
+        0x2F => "City Patrolman Lightgun",
+        0x30 => "Sharp C1 Cassette Interface",
+        0x31 => "Standard Controller with swapped buttons",
+        0x32 => "Excalibor Sudoku Pad",
+        0x33 => "ABL Pinball",
+        0x34 => "Golden Nugget Casino extra buttons",
+        _ => "Unknown"
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
 }
 
 impl fmt::Display for INes2Header {
@@ -79,34 +432,393 @@ impl fmt::Display for INes2Header {
             };
 
             writeln!(f, "Format:  {}", format)?;
+            writeln!(f, "Mapper:  {} (submapper {})", self.mapper_number(), self.submapper())?;
             writeln!(f, "PRG ROM size:  {} bytes", self.prg_rom_size_bytes())?;
-            writeln!(f, "CHR ROM size:  {} bytes", self.chr_rom_size_bytes())
+            writeln!(f, "CHR ROM size:  {} bytes", self.chr_rom_size_bytes())?;
+            writeln!(f, "PRG RAM size:  {} bytes", self.prg_ram_size_bytes())?;
+            writeln!(f, "PRG NVRAM size:  {} bytes", self.prg_nvram_size_bytes())?;
+            writeln!(f, "CHR RAM size:  {} bytes", self.chr_ram_size_bytes())?;
+            writeln!(f, "CHR NVRAM size:  {} bytes", self.chr_nvram_size_bytes())?;
+            writeln!(f, "Mirroring:  {}", self.mirroring())?;
+            writeln!(f, "Battery:  {}", yes_no(self.has_battery()))?;
+            if self.has_trainer_data() {
+                writeln!(f, "Trainer:  {} bytes, loaded at $7000-$71FF", self.trainer_size_bytes())?;
+            } else {
+                writeln!(f, "Trainer:  no")?;
+            }
+            writeln!(f, "Console type:  {}", self.console_type())?;
+            writeln!(f, "Timing:  {}", self.timing())?;
+            if let Some(ppu) = self.vs_ppu_type() {
+                writeln!(f, "Vs. PPU type:  {}", vs_ppu_name(ppu))?;
+            }
+            if let Some(hardware) = self.vs_hardware_type() {
+                writeln!(f, "Vs. hardware type:  {}", vs_hardware_name(hardware))?;
+            }
+            writeln!(f, "Misc ROMs:  {}", self.misc_rom_count())?;
+            writeln!(f, "Default expansion device:  {}", expansion_device_name(self.default_expansion_device()))
         } else {
             writeln!(f, "Invalid ROM")
         }
     }
 }
 
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::BadMagic => write!(f, "Not an iNES file (missing NES<EOF> identifier)"),
+            RomError::TruncatedHeader => write!(f, "File ends inside the 16 byte header"),
+            RomError::TruncatedTrainer => write!(f, "File ends inside the 512 byte trainer"),
+            RomError::TruncatedPrgRom { expected, found } =>
+                write!(f, "PRG ROM truncated:  expected {} bytes, found {}", expected, found),
+            RomError::TruncatedChrRom { expected, found } =>
+                write!(f, "CHR ROM truncated:  expected {} bytes, found {}", expected, found),
+            RomError::EmptyPrgRom => write!(f, "Header declares no PRG ROM"),
+            RomError::SizeMismatch { expected, found } =>
+                write!(f, "File size mismatch:  header describes {} bytes, file has {}", expected, found),
+            RomError::OversizedExponent => write!(f, "NES 2.0 exponent-multiplier ROM size is too large"),
+            RomError::UnsupportedMapper(mapper) => write!(f, "Mapper {} is not supported", mapper)
+        }
+    }
+}
+
+impl Error for RomError {}
+
 impl INesRom {
-    pub fn new(contents: Vec<u8>) -> Self {
-        let header_bytes = contents[0..16].try_into().expect("Header not found");
+    pub fn new(contents: Vec<u8>) -> Result<Self, RomError> {
+        let header_bytes: [u8; 16] = match contents.get(0..16) {
+            Some(bytes) => bytes.try_into().unwrap(),
+            // Whatever there is of the identifier still has to match
+            None if INES_IDENTIFIER.starts_with(&contents[..contents.len().min(4)]) => {
+                return Err(RomError::TruncatedHeader)
+            },
+            None => return Err(RomError::BadMagic)
+        };
         let header = INes2Header::new(header_bytes);
 
-        let trainer_data_size = match header.has_trainer_data() {
-            true => 512,
-            false => 0
+        if !header.has_ines_identifier() {
+            return Err(RomError::BadMagic);
+        }
+        if header.has_oversized_exponent() {
+            return Err(RomError::OversizedExponent);
+        }
+        // The CPU's vectors are in PRG ROM, so there is nothing to run without it
+        if header.prg_rom_size_bytes() == 0 {
+            return Err(RomError::EmptyPrgRom);
+        }
+
+        // https://wiki.nesdev.org/w/index.php?title=NES_2.0#File_Structure
+        let trainer_end = 16 + header.trainer_size_bytes();
+        let prg_rom_end = trainer_end + header.prg_rom_size_bytes();
+        let chr_rom_end = prg_rom_end + header.chr_rom_size_bytes();
+
+        if contents.len() < trainer_end {
+            return Err(RomError::TruncatedTrainer);
+        }
+        if contents.len() < prg_rom_end {
+            return Err(RomError::TruncatedPrgRom {
+                expected: header.prg_rom_size_bytes(),
+                found: contents.len() - trainer_end
+            });
+        }
+        if contents.len() < chr_rom_end {
+            return Err(RomError::TruncatedChrRom {
+                expected: header.chr_rom_size_bytes(),
+                found: contents.len() - prg_rom_end
+            });
+        }
+        // NES 2.0 accounts for every byte, anything past CHR ROM must be declared as misc ROM
+        if header.is_nes2() && header.misc_rom_count() == 0 && contents.len() != chr_rom_end {
+            return Err(RomError::SizeMismatch { expected: chr_rom_end, found: contents.len() });
+        }
+
+        let trainer = match header.has_trainer_data() {
+            true => Some(contents[16..trainer_end].to_vec()),
+            false => None
         };
+        let prg_rom = contents[trainer_end..prg_rom_end].to_vec();
+        let chr_rom = contents[prg_rom_end..chr_rom_end].to_vec();
+
+        // Misc ROM is whatever follows CHR ROM, and only has meaning for NES 2.0
+        let misc_rom = match header.misc_rom_count() {
+            0 => Vec::new(),
+            _ => contents[chr_rom_end..].to_vec()
+        };
+
+        Ok(INesRom{ header, trainer, prg_rom, chr_rom, misc_rom })
+    }
+
+    pub fn to_bus(&self) -> Result<NesBus, RomError> {
+        Ok(NesBus::new(mappers::for_rom(self)?))
+    }
+
+    pub fn to_cpu(&self) -> Result<CPU, RomError> {
+        Ok(CPU::new(Box::new(self.to_bus()?)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{INes2Header, INesRom, RomError, ConsoleType, Timing, Mirroring};
+
+    fn header(bytes: [u8; 12]) -> [u8; 16] {
+        let mut data = [0x4E, 0x45, 0x53, 0x1A, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        data[4..16].copy_from_slice(&bytes);
+        data
+    }
+
+    #[test]
+    fn ines_nestest_header() {
+        // Given
+        let header = INes2Header::new(header(
+            [0x01, 0x01, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]));
+
+        // Then
+        assert!(header.to_string().starts_with("Format:  iNES\n"));
+        assert_eq!(0, header.mapper_number());
+        assert_eq!(0x4000, header.prg_rom_size_bytes());
+        assert_eq!(0x2000, header.chr_rom_size_bytes());
+        assert_eq!(0x2000, header.prg_ram_size_bytes());
+        assert_eq!(0, header.chr_ram_size_bytes());
+        assert!(matches!(header.mirroring(), Mirroring::Horizontal));
+    }
+
+    #[test]
+    fn ines_mapper_and_flags() {
+        // Given
+        let header = INes2Header::new(header(
+            [0x08, 0x00, 0x4B, 0x10, 0x00, 0x01, 0, 0, 0, 0, 0, 0]));
+
+        // Then
+        assert_eq!(0x14, header.mapper_number());
+        assert_eq!(0, header.chr_rom_size_bytes());
+        assert_eq!(0x2000, header.chr_ram_size_bytes());
+        assert_eq!(0, header.prg_ram_size_bytes());
+        assert_eq!(0x2000, header.prg_nvram_size_bytes());
+        assert!(header.has_battery());
+        assert!(header.has_four_screen_vram());
+        assert!(!header.has_trainer_data());
+        assert!(matches!(header.mirroring(), Mirroring::FourScreen));
+        assert!(header.to_string().contains("Mirroring:  Four-screen\n"));
+        assert!(matches!(header.timing(), Timing::Pal));
+    }
+
+    #[test]
+    fn archaic_ines_ignores_byte7() {
+        // Given "DiskDude!" garbage over bytes 7-15
+        let header = INes2Header::new(
+            [0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x10, 0x44, 0x69, 0x73, 0x6B, 0x44, 0x75, 0x64, 0x65, 0x21]);
+
+        // Then
+        assert_eq!(1, header.mapper_number());
+        assert_eq!(0x8000, header.prg_rom_size_bytes());
+        assert!(header.to_string().starts_with("Format:  Archaic iNES"));
+    }
+
+    #[test]
+    fn nes2_header_fields() {
+        // Given
+        let header = INes2Header::new(header(
+            [0x10, 0x20, 0x12, 0x09, 0x31, 0x00, 0x70, 0x07, 0x01, 0x21, 0x01, 0x01]));
+
+        // Then
+        assert!(header.to_string().starts_with("Format:  iNES 2.0"));
+        assert_eq!(0x101, header.mapper_number());
+        assert_eq!(3, header.submapper());
+        assert_eq!(0x40000, header.prg_rom_size_bytes());
+        assert_eq!(0x40000, header.chr_rom_size_bytes());
+        assert_eq!(0, header.prg_ram_size_bytes());
+        assert_eq!(0x2000, header.prg_nvram_size_bytes());
+        assert_eq!(0x2000, header.chr_ram_size_bytes());
+        assert_eq!(0, header.chr_nvram_size_bytes());
+        assert!(matches!(header.console_type(), ConsoleType::VsSystem));
+        assert!(matches!(header.timing(), Timing::Pal));
+        assert_eq!(Some(0x1), header.vs_ppu_type());
+        assert_eq!(Some(0x2), header.vs_hardware_type());
+        assert_eq!(1, header.misc_rom_count());
+        assert_eq!(1, header.default_expansion_device());
+    }
+
+    #[test]
+    fn nes2_exponent_multiplier_size() {
+        // Given 2^4 * (1*2 + 1) = 48 bytes of PRG ROM
+        let header = INes2Header::new(header(
+            [0b0001_0001, 0x00, 0x00, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0]));
+
+        // Then
+        assert_eq!(48, header.prg_rom_size_bytes());
+    }
+
+    #[test]
+    fn nes2_size_msb_past_end_of_file_error() {
+        // Given a header claiming 0x100 extra PRG banks from byte 9 that are not in the file
+        let mut contents = header([0x02, 0x01, 0x00, 0x08, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x8000 + 0x2000]);
+
+        assert!(matches!(INesRom::new(contents),
+            Err(RomError::TruncatedPrgRom { expected: 0x408000, found: 0xA000 })));
+    }
 
-        let prg_rom_start = 16 + trainer_data_size;
-        let prg_rom_end = prg_rom_start + header.prg_rom_size_bytes();
+    #[test]
+    fn nes2_chr_size_msb_past_end_of_file_error() {
+        let mut contents = header([0x02, 0x01, 0x00, 0x08, 0x00, 0x10, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x8000 + 0x2000]);
 
-        let mut prg_rom: [u8; 0x4000] = [0x00; 0x4000];
-        prg_rom.copy_from_slice(&contents[prg_rom_start..prg_rom_end]);
+        assert!(matches!(INesRom::new(contents),
+            Err(RomError::TruncatedChrRom { expected: 0x202000, found: 0x2000 })));
+    }
+
+    #[test]
+    fn extended_console_type() {
+        // Given
+        let header = INes2Header::new(header(
+            [0x01, 0x00, 0x00, 0x0B, 0x00, 0x00, 0, 0, 0x03, 0x03, 0, 0]));
+
+        // Then
+        assert!(matches!(header.console_type(), ConsoleType::Extended(0x03)));
+        assert!(matches!(header.timing(), Timing::Dendy));
+        assert_eq!(None, header.vs_hardware_type());
+    }
+
+    #[test]
+    fn rom_regions_are_split() {
+        // Given
+        let mut contents = header([0x02, 0x01, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x01; 512]);
+        contents.extend(vec![0x02; 0x8000]);
+        contents.extend(vec![0x03; 0x2000]);
+
+        // When
+        let rom = INesRom::new(contents).unwrap();
 
-        INesRom{ header, prg_rom }
+        // Then
+        assert_eq!(Some(vec![0x01; 512]), rom.trainer);
+        assert_eq!(vec![0x02; 0x8000], rom.prg_rom);
+        assert_eq!(vec![0x03; 0x2000], rom.chr_rom);
+        assert!(rom.misc_rom.is_empty());
     }
 
-    pub fn to_cpu(&self) -> CPU {
-        CPU::new(self.prg_rom)
+    #[test]
+    fn nes2_misc_rom_is_kept() {
+        // Given
+        let mut contents = header([0x01, 0x00, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0x01, 0]).to_vec();
+        contents.extend(vec![0x02; 0x4000]);
+        contents.extend(vec![0x04; 0x100]);
+
+        // When
+        let rom = INesRom::new(contents).unwrap();
+
+        // Then
+        assert_eq!(None, rom.trainer);
+        assert_eq!(0x4000, rom.prg_rom.len());
+        assert!(rom.chr_rom.is_empty());
+        assert_eq!(vec![0x04; 0x100], rom.misc_rom);
+    }
+
+    #[test]
+    fn bad_magic_error() {
+        let mut contents = header([0x01, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents[3] = 0x00;
+        contents.extend(vec![0x00; 0x4000]);
+
+        assert!(matches!(INesRom::new(contents), Err(RomError::BadMagic)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn missing_header_error() {
+        assert!(matches!(INesRom::new(vec![0x4E, 0x45, 0x53]), Err(RomError::TruncatedHeader)));
+        assert!(matches!(INesRom::new(vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01]), Err(RomError::TruncatedHeader)));
+    }
+
+    #[test]
+    fn short_file_bad_magic_error() {
+        assert!(matches!(INesRom::new(vec![0x4E, 0x45, 0x53, 0x00, 0x01]), Err(RomError::BadMagic)));
+        assert!(matches!(INesRom::new(vec![0x50, 0x4B]), Err(RomError::BadMagic)));
+    }
+
+    #[test]
+    fn truncated_trainer_error() {
+        let mut contents = header([0x01, 0x00, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x100]);
+
+        assert!(matches!(INesRom::new(contents), Err(RomError::TruncatedTrainer)));
+    }
+
+    #[test]
+    fn truncated_prg_rom_error() {
+        let mut contents = header([0x02, 0x01, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x4000]);
+
+        assert!(matches!(INesRom::new(contents),
+            Err(RomError::TruncatedPrgRom { expected: 0x8000, found: 0x4000 })));
+    }
+
+    #[test]
+    fn truncated_chr_rom_error() {
+        let mut contents = header([0x01, 0x01, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x4000 + 0x1000]);
+
+        assert!(matches!(INesRom::new(contents),
+            Err(RomError::TruncatedChrRom { expected: 0x2000, found: 0x1000 })));
+    }
+
+    #[test]
+    fn empty_prg_rom_error() {
+        let mut contents = header([0x00, 0x01, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x2000]);
+
+        assert!(matches!(INesRom::new(contents), Err(RomError::EmptyPrgRom)));
+    }
+
+    #[test]
+    fn prg_rom_smaller_than_a_bank() {
+        // Given 2^13 * 1 = 8KB of PRG ROM on UxROM (16KB banks) and MMC3 (8KB banks, with
+        // the second to last fixed)
+        for mapper in [0x20, 0x40].iter() {
+            let mut contents = header([0b0011_0100, 0x01, *mapper, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0]).to_vec();
+            let mut prg_rom = vec![0x00; 0x2000];
+            prg_rom[0x1FFC..].copy_from_slice(&[0x34, 0x12, 0x00, 0x00]);
+            contents.extend(prg_rom);
+            contents.extend(vec![0x00; 0x2000]);
+
+            // When
+            let cpu = INesRom::new(contents).unwrap().to_cpu().unwrap();
+
+            // Then
+            assert_eq!(0x1234, cpu.program_counter);
+        }
+    }
+
+    #[test]
+    fn nes2_trailing_data_error() {
+        let mut contents = header([0x01, 0x00, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x4000 + 0x80]);
+
+        assert!(matches!(INesRom::new(contents),
+            Err(RomError::SizeMismatch { expected: 0x4010, found: 0x4090 })));
+    }
+
+    #[test]
+    fn oversized_exponent_error() {
+        // Given 2^63 * 3 bytes of PRG ROM
+        let mut contents = header([0xFD, 0x00, 0x00, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x100]);
+
+        assert!(matches!(INesRom::new(contents), Err(RomError::OversizedExponent)));
+    }
+
+    #[test]
+    fn unsupported_mapper_error() {
+        let mut contents = header([0x01, 0x00, 0x50, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]).to_vec();
+        contents.extend(vec![0x00; 0x4000]);
+        let rom = INesRom::new(contents).unwrap();
+
+        assert!(matches!(rom.to_cpu(), Err(RomError::UnsupportedMapper(5))));
+    }
+
+    #[test]
+    fn missing_identifier_is_invalid() {
+        let header = INes2Header::new([0; 16]);
+
+        assert_eq!("Invalid ROM\n", header.to_string());
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+// Roughly once a second of NTSC CPU time
+const FLUSH_INTERVAL_CYCLES: u64 = 1_789_773;
+
+// Battery backed PRG RAM is kept in a .sav file named after the ROM, by default right
+// next to it.  Writes only happen when the RAM has changed since the last flush.
+pub struct SaveFile {
+    path: PathBuf,
+    clean: bool,
+    saved: Vec<u8>,
+    cycles_since_flush: u64
+}
+
+impl SaveFile {
+    pub fn new(rom_filename: &str, save_dir: Option<&str>, clean: bool) -> Self {
+        let rom_path = Path::new(rom_filename);
+        let file_name = rom_path.with_extension("sav");
+        let file_name = file_name.file_name().unwrap_or_default();
+
+        let path = match save_dir {
+            Some(dir) => Path::new(dir).join(file_name),
+            None => rom_path.with_extension("sav")
+        };
+
+        SaveFile { path, clean, saved: Vec::new(), cycles_since_flush: 0 }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // A missing file is fine, the game just starts without a save.  Either way the RAM as
+    // loaded counts as saved, so the file is only written once the game changes it.
+    pub fn load(&mut self, ram: &mut [u8]) -> io::Result<()> {
+        if !self.clean {
+            match fs::read(&self.path) {
+                Ok(contents) => {
+                    let size = contents.len().min(ram.len());
+                    ram[..size].copy_from_slice(&contents[..size]);
+                },
+                Err(e) if e.kind() == ErrorKind::NotFound => {},
+                Err(e) => return Err(e)
+            }
+        }
+
+        self.mark_saved(ram);
+        Ok(())
+    }
+
+    // For RAM changed on purpose before the game runs, which doesn't need writing out
+    pub fn mark_saved(&mut self, ram: &[u8]) {
+        self.saved = ram.to_vec();
+    }
+
+    pub fn tick(&mut self, cycles: u8, ram: &[u8]) -> io::Result<()> {
+        self.cycles_since_flush += cycles as u64;
+
+        if self.cycles_since_flush >= FLUSH_INTERVAL_CYCLES {
+            self.cycles_since_flush = 0;
+            self.flush(ram)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn flush(&mut self, ram: &[u8]) -> io::Result<()> {
+        if self.saved == ram {
+            return Ok(());
+        }
+
+        fs::write(&self.path, ram)?;
+        self.saved = ram.to_vec();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+    use super::SaveFile;
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("nes_play_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn save_next_to_rom() {
+        let save = SaveFile::new("/games/zelda.nes", None, false);
+
+        assert_eq!(Path::new("/games/zelda.sav"), save.path());
+    }
+
+    #[test]
+    fn save_in_save_dir() {
+        let save = SaveFile::new("/games/zelda.nes", Some("/saves"), false);
+
+        assert_eq!(Path::new("/saves/zelda.sav"), save.path());
+    }
+
+    #[test]
+    fn flush_and_load() {
+        // Given
+        let dir = temp_dir("flush_and_load");
+        let mut save = SaveFile::new("game.nes", Some(&dir), false);
+
+        // When
+        save.flush(&[0x01, 0x02, 0x03]).unwrap();
+        let mut ram = [0; 3];
+        SaveFile::new("game.nes", Some(&dir), false).load(&mut ram).unwrap();
+
+        // Then
+        assert_eq!([0x01, 0x02, 0x03], ram);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_save_is_empty() {
+        // Given
+        let dir = temp_dir("missing_save");
+        let mut ram = [0xAA; 3];
+
+        // When
+        SaveFile::new("game.nes", Some(&dir), false).load(&mut ram).unwrap();
+
+        // Then
+        assert_eq!([0xAA; 3], ram);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clean_save_ignores_file() {
+        // Given
+        let dir = temp_dir("clean_save");
+        SaveFile::new("game.nes", Some(&dir), false).flush(&[0x01, 0x02, 0x03]).unwrap();
+        let mut ram = [0; 3];
+
+        // When
+        SaveFile::new("game.nes", Some(&dir), true).load(&mut ram).unwrap();
+
+        // Then
+        assert_eq!([0; 3], ram);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clean_save_keeps_file_while_ram_unchanged() {
+        // Given
+        let dir = temp_dir("clean_save_unchanged");
+        SaveFile::new("game.nes", Some(&dir), false).flush(&[0x01, 0x02, 0x03]).unwrap();
+        let mut save = SaveFile::new("game.nes", Some(&dir), true);
+        let mut ram = [0; 3];
+        save.load(&mut ram).unwrap();
+
+        // When
+        save.flush(&ram).unwrap();
+
+        // Then
+        assert_eq!(vec![0x01, 0x02, 0x03], fs::read(save.path()).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn untouched_ram_is_not_saved() {
+        // Given
+        let dir = temp_dir("untouched_ram");
+        let mut save = SaveFile::new("game.nes", Some(&dir), false);
+        let mut ram = [0; 3];
+        save.load(&mut ram).unwrap();
+
+        // When
+        save.flush(&ram).unwrap();
+
+        // Then
+        assert!(!save.path().exists());
+        ram[1] = 0x42;
+        save.flush(&ram).unwrap();
+        assert_eq!(vec![0x00, 0x42, 0x00], fs::read(save.path()).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn periodic_flush() {
+        // Given
+        let dir = temp_dir("periodic_flush");
+        let mut save = SaveFile::new("game.nes", Some(&dir), false);
+
+        // When
+        save.tick(7, &[0x05]).unwrap();
+
+        // Then
+        assert!(!save.path().exists());
+
+        // When
+        for _ in 0..(super::FLUSH_INTERVAL_CYCLES / 7) {
+            save.tick(7, &[0x05]).unwrap();
+        }
+
+        // Then
+        assert_eq!(vec![0x05], fs::read(save.path()).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+// Runs the community single step tests, where each case is one instruction with the
+// registers and RAM before and after, plus every bus access it makes.
+// See https://github.com/SingleStepTests/65x02
+use serde::Deserialize;
+use crate::bus::{Bus, BusAccess, RecordingBus};
+use crate::cpu::{AddressingMode, CPU};
+use crate::flat_ram::FlatRam;
+use crate::instructions::factory::fetch;
+
+#[derive(Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    pub initial: CpuState,
+    #[serde(rename = "final")]
+    pub expected: CpuState,
+    pub cycles: Vec<(u16, u8, String)>
+}
+
+#[derive(Deserialize)]
+pub struct CpuState {
+    pub pc: u16,
+    pub s: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub ram: Vec<(u16, u8)>
+}
+
+impl TestCase {
+    pub fn opcode(&self) -> u8 {
+        self.initial.ram.iter()
+            .find(|(address, _)| *address == self.initial.pc)
+            .map_or(0, |(_, value)| *value)
+    }
+
+    // Sets up a CPU from the initial state, runs one instruction and describes the first
+    // difference from the final state.  configure is applied before the registers are set.
+    pub fn run(&self, configure: impl Fn(&mut CPU), check_bus: bool) -> Result<(), String> {
+        let mut ram = FlatRam::new();
+        for &(address, value) in &self.initial.ram {
+            ram.write_mem8(address, value);
+        }
+        let bus = RecordingBus::new(Box::new(ram));
+        let accesses = bus.accesses();
+
+        let mut cpu = CPU::new(Box::new(bus));
+        configure(&mut cpu);
+        cpu.program_counter = self.initial.pc;
+        cpu.stack_pointer = self.initial.s;
+        cpu.accumulator = self.initial.a;
+        cpu.index_register_x = self.initial.x;
+        cpu.index_register_y = self.initial.y;
+        cpu.processor_status = self.initial.p;
+        accesses.borrow_mut().clear();
+
+        let fetched = fetch(&mut cpu);
+        let cycles = cpu.run_fetched(fetched);
+        let recorded = accesses.borrow().clone();
+
+        let expected = &self.expected;
+        compare("PC", 4, expected.pc, cpu.program_counter)?;
+        compare("SP", 2, expected.s as u16, cpu.stack_pointer as u16)?;
+        compare("A", 2, expected.a as u16, cpu.accumulator as u16)?;
+        compare("X", 2, expected.x as u16, cpu.index_register_x as u16)?;
+        compare("Y", 2, expected.y as u16, cpu.index_register_y as u16)?;
+        // Bits 4 and 5 only exist on the stack copy of P
+        compare("P", 2, (expected.p & 0xCF) as u16, (cpu.processor_status & 0xCF) as u16)?;
+
+        for &(address, value) in &expected.ram {
+            let actual = cpu.read(&AddressingMode::Absolute(address));
+            if actual != value {
+                return Err(format!("${:04X} expected {:02X} but was {:02X}", address, value, actual));
+            }
+        }
+
+        if cycles as usize != self.cycles.len() {
+            return Err(format!("Cycles expected {} but was {}", self.cycles.len(), cycles));
+        }
+
+        if check_bus {
+            let expected_accesses = self.cycles.iter().map(|(address, value, kind)| {
+                BusAccess { address: *address, value: *value, write: kind == "write" }
+            });
+            for (cycle, expected) in expected_accesses.enumerate() {
+                match recorded.get(cycle) {
+                    Some(actual) if *actual == expected => {}
+                    actual => return Err(format!("Cycle {} expected {} but was {}",
+                                                 cycle + 1, describe(Some(&expected)), describe(actual)))
+                }
+            }
+            if recorded.len() > self.cycles.len() {
+                return Err(format!("Cycle {} expected nothing but was {}",
+                                   self.cycles.len() + 1, describe(recorded.get(self.cycles.len()))));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn compare(register: &str, width: usize, expected: u16, actual: u16) -> Result<(), String> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!("{} expected {:0w$X} but was {:0w$X}", register, expected, actual, w = width))
+    }
+}
+
+fn describe(access: Option<&BusAccess>) -> String {
+    match access {
+        Some(access) => format!("{} ${:04X} = {:02X}",
+                                if access.write { "write" } else { "read" }, access.address, access.value),
+        None => "nothing".to_string()
+    }
+}
+
+// Pass and fail counts for one opcode, keeping the first failure
+#[derive(Default)]
+pub struct OpcodeResults {
+    pub passed: u32,
+    pub failed: u32,
+    pub first_failure: Option<String>
+}
+
+impl OpcodeResults {
+    pub fn record(&mut self, test: &TestCase, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.passed += 1,
+            Err(mismatch) => {
+                self.failed += 1;
+                if self.first_failure.is_none() {
+                    self.first_failure = Some(format!("{}:  {}", test.name, mismatch));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OpcodeResults, TestCase};
+
+    // LDA #$42 from the 65x02 format
+    const LDA_IMMEDIATE: &str = r#"{
+        "name": "a9 42 00",
+        "initial": { "pc": 512, "s": 253, "a": 0, "x": 0, "y": 0, "p": 36, "ram": [[512, 169], [513, 66]] },
+        "final": { "pc": 514, "s": 253, "a": 66, "x": 0, "y": 0, "p": 36, "ram": [[512, 169], [513, 66]] },
+        "cycles": [[512, 169, "read"], [513, 66, "read"]]
+    }"#;
+
+    #[test]
+    fn passing_test() {
+        // Given
+        let test: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+
+        // When
+        let result = test.run(|_| {}, true);
+
+        // Then
+        assert_eq!(0xA9, test.opcode());
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn register_mismatch() {
+        // Given
+        let mut test: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+        test.expected.a = 0x43;
+
+        // When
+        let result = test.run(|_| {}, false);
+
+        // Then
+        assert_eq!(Err("A expected 43 but was 42".to_string()), result);
+    }
+
+    #[test]
+    fn bus_mismatch() {
+        // Given
+        let mut test: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+        test.cycles[1] = (0x0201, 0x42, "write".to_string());
+
+        // When
+        let result = test.run(|_| {}, true);
+
+        // Then
+        assert_eq!(Err("Cycle 2 expected write $0201 = 42 but was read $0201 = 42".to_string()), result);
+    }
+
+    #[test]
+    fn results_keep_first_failure() {
+        // Given
+        let test: TestCase = serde_json::from_str(LDA_IMMEDIATE).unwrap();
+        let mut results = OpcodeResults::default();
+
+        // When
+        results.record(&test, Ok(()));
+        results.record(&test, Err("first".to_string()));
+        results.record(&test, Err("second".to_string()));
+
+        // Then
+        assert_eq!(1, results.passed);
+        assert_eq!(2, results.failed);
+        assert_eq!(Some("a9 42 00:  first".to_string()), results.first_failure);
+    }
+}
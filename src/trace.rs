@@ -0,0 +1,332 @@
+// Trace output for the log command.  The CPU fills in a TraceRecord before each
+// instruction runs and hands it to a TraceSink.  For text, a TraceFormatter lays it out
+// like a reference emulator does.  LogOptions decide which instructions get logged and
+// when to stop.
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::io::Write;
+use serde::Serialize;
+use crate::cpu::{AddressingMode, CPU};
+
+#[derive(Clone, Serialize)]
+pub struct TraceRecord {
+    pub pc: u16,
+    pub bytes: Vec<u8>,
+    pub instruction: String,    // Disassembly, with the memory values it touches
+    pub illegal: bool,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub scanline: u16,
+    pub dot: u16,
+    pub cycles: u64
+}
+
+impl TraceRecord {
+    fn bytes_string(&self) -> String {
+        self.bytes.iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    // NV-BDIZC, upper case when set:  nvUbdIzc
+    fn flags_string(&self) -> String {
+        "NVUBDIZC".chars().enumerate()
+            .map(|(bit, flag)| {
+                if self.p & (0x80 >> bit) != 0 { flag } else { flag.to_ascii_lowercase() }
+            })
+            .collect()
+    }
+}
+
+pub trait TraceSink {
+    // Called before the instruction runs
+    fn before(&mut self, record: &TraceRecord) -> io::Result<()>;
+
+    // Called once it has run, including instructions that were filtered out
+    fn after(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait TraceFormatter {
+    fn format(&self, record: &TraceRecord) -> String;
+}
+
+// One line per instruction, written before it runs so a panic leaves its line behind
+pub struct TextTrace<'a> {
+    log: &'a mut dyn Write,
+    formatter: &'a dyn TraceFormatter
+}
+
+impl<'a> TextTrace<'a> {
+    pub fn new(log: &'a mut dyn Write, formatter: &'a dyn TraceFormatter) -> Self {
+        TextTrace { log, formatter }
+    }
+}
+
+impl TraceSink for TextTrace<'_> {
+    fn before(&mut self, record: &TraceRecord) -> io::Result<()> {
+        writeln!(self.log, "{}", self.formatter.format(record))
+    }
+}
+
+pub fn formatter(name: &str) -> Option<Box<dyn TraceFormatter>> {
+    match name {
+        "nestest" => Some(Box::new(Nestest{})),
+        "mesen" => Some(Box::new(Mesen{})),
+        "fceux" => Some(Box::new(Fceux{})),
+        "jsonl" => Some(Box::new(JsonLines{})),
+        _ => None
+    }
+}
+
+// The layout of nestest.log
+// http://www.qmtpro.com/~nes/misc/nestest.log
+pub struct Nestest {}
+
+impl TraceFormatter for Nestest {
+    fn format(&self, record: &TraceRecord) -> String {
+        format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+                record.pc, record.bytes_string(), if record.illegal { "*" } else { " " }, record.instruction,
+                record.a, record.x, record.y, record.p, record.sp, record.scanline, record.dot, record.cycles)
+    }
+}
+
+// Mesen's default trace logger columns
+pub struct Mesen {}
+
+impl TraceFormatter for Mesen {
+    fn format(&self, record: &TraceRecord) -> String {
+        format!("{:04X}  {:<32} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{} V:{:<3} H:{:<3} Cycle:{}",
+                record.pc, record.instruction, record.a, record.x, record.y, record.sp,
+                record.flags_string(), record.scanline, record.dot, record.cycles)
+    }
+}
+
+// FCEUX's trace logger, with registers and flags but no timing
+pub struct Fceux {}
+
+impl TraceFormatter for Fceux {
+    fn format(&self, record: &TraceRecord) -> String {
+        format!("${:04X}:{:<9} {:<31} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{}",
+                record.pc, record.bytes_string(), record.instruction,
+                record.a, record.x, record.y, record.sp, record.flags_string())
+    }
+}
+
+// One JSON object per instruction, for tools
+pub struct JsonLines {}
+
+impl TraceFormatter for JsonLines {
+    fn format(&self, record: &TraceRecord) -> String {
+        serde_json::to_string(record).unwrap()
+    }
+}
+
+// Checked before each instruction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StopCondition {
+    Instructions(u64),
+    Cycles(u64),
+    ProgramCounter(u16),
+    MemoryValue(u16, u8)    // Peeked, so watching a PPU register doesn't disturb it
+}
+
+#[derive(PartialEq, Debug)]
+pub enum StopReason {
+    Instructions(u64),
+    Cycles(u64),
+    ProgramCounter(u16),
+    MemoryValue(u16, u8),
+    InfiniteLoop(u16),
+    Halted(u16)
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Instructions(count) => write!(f, "Stopped after {} instructions", count),
+            StopReason::Cycles(cycles) => write!(f, "Stopped at cycle {}", cycles),
+            StopReason::ProgramCounter(pc) => write!(f, "Stopped at ${:04X}", pc),
+            StopReason::MemoryValue(address, value) => write!(f, "Stopped when ${:04X} became {:02X}", address, value),
+            StopReason::InfiniteLoop(pc) => write!(f, "Stopped in an infinite loop at ${:04X}", pc),
+            StopReason::Halted(pc) => write!(f, "CPU halted by JAM at ${:04X}", pc),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LogOptions {
+    pub stop_conditions: Vec<StopCondition>,
+    pub stop_on_loop: bool,             // An instruction that jumps or branches to itself
+    pub range: Option<(u16, u16)>,      // Only log instructions in start..=end
+    pub trigger: Option<u16>            // Only log once PC has reached this address
+}
+
+impl LogOptions {
+    pub fn stop_reason(&self, cpu: &CPU, instructions: u64) -> Option<StopReason> {
+        self.stop_conditions.iter().find_map(|condition| match *condition {
+            StopCondition::Instructions(max) if instructions >= max => Some(StopReason::Instructions(instructions)),
+            StopCondition::Cycles(max) if cpu.cycles >= max => Some(StopReason::Cycles(cpu.cycles)),
+            StopCondition::ProgramCounter(pc) if cpu.program_counter == pc => Some(StopReason::ProgramCounter(pc)),
+            StopCondition::MemoryValue(address, value) if cpu.peek(&AddressingMode::Absolute(address)) == value => {
+                Some(StopReason::MemoryValue(address, value))
+            }
+            _ => None
+        })
+    }
+
+    pub fn in_range(&self, pc: u16) -> bool {
+        self.range.is_none_or(|(start, end)| (start..=end).contains(&pc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::CPU;
+    use crate::flat_ram::FlatRam;
+    use super::{formatter, Fceux, JsonLines, LogOptions, Mesen, Nestest, StopCondition, StopReason,
+                TextTrace, TraceFormatter, TraceRecord};
+
+    fn record() -> TraceRecord {
+        TraceRecord {
+            pc: 0xC72A,
+            bytes: vec![0xA9, 0x00],
+            instruction: "LDA #$00".to_string(),
+            illegal: false,
+            a: 0x00, x: 0x01, y: 0x02, p: 0x27, sp: 0xFB,
+            scanline: 1, dot: 5, cycles: 180
+        }
+    }
+
+    #[test]
+    fn nestest_format() {
+        assert_eq!("C72A  A9 00     LDA #$00                        A:00 X:01 Y:02 P:27 SP:FB PPU:  1,  5 CYC:180",
+                   Nestest{}.format(&record()));
+    }
+
+    #[test]
+    fn nestest_marks_illegal() {
+        let mut record = record();
+        record.illegal = true;
+
+        assert!(Nestest{}.format(&record).starts_with("C72A  A9 00    *LDA #$00"));
+    }
+
+    #[test]
+    fn mesen_format() {
+        assert_eq!("C72A  LDA #$00                         A:00 X:01 Y:02 S:FB P:nvUbdIZC V:1   H:5   Cycle:180",
+                   Mesen{}.format(&record()));
+    }
+
+    #[test]
+    fn fceux_format() {
+        assert_eq!("$C72A:A9 00     LDA #$00                        A:00 X:01 Y:02 S:FB P:nvUbdIZC",
+                   Fceux{}.format(&record()));
+    }
+
+    #[test]
+    fn json_lines_format() {
+        assert_eq!(r#"{"pc":50986,"bytes":[169,0],"instruction":"LDA #$00","illegal":false,"a":0,"x":1,"y":2,"p":39,"sp":251,"scanline":1,"dot":5,"cycles":180}"#,
+                   JsonLines{}.format(&record()));
+    }
+
+    #[test]
+    fn formatter_by_name() {
+        assert!(formatter("mesen").is_some());
+        assert!(formatter("bizhawk").is_none());
+    }
+
+    // Runs program from $0000 and returns the PCs that were logged
+    fn run_logged(program: &[u8], options: LogOptions) -> (Vec<String>, StopReason) {
+        let mut ram = FlatRam::new();
+        ram.load(0x0000, program).unwrap();
+        let mut cpu = CPU::new(Box::new(ram));
+        let mut log: Vec<u8> = Vec::new();
+
+        let reason = cpu.log_execution(&mut TextTrace::new(&mut log, &Nestest{}), &options).unwrap();
+
+        let lines = String::from_utf8(log).unwrap().lines().map(|l| l[..4].to_string()).collect();
+        (lines, reason)
+    }
+
+    // NOP, NOP, INX, JMP $0002
+    const LOOP: [u8; 6] = [0xEA, 0xEA, 0xE8, 0x4C, 0x02, 0x00];
+
+    #[test]
+    fn stop_after_instructions() {
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::Instructions(3)],
+            ..LogOptions::default()
+        };
+
+        assert_eq!((vec!["0000".to_string(), "0001".to_string(), "0002".to_string()], StopReason::Instructions(3)),
+                   run_logged(&LOOP, options));
+    }
+
+    #[test]
+    fn stop_at_cycle_and_pc() {
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::Cycles(1000), StopCondition::ProgramCounter(0x0003)],
+            ..LogOptions::default()
+        };
+
+        assert_eq!(StopReason::ProgramCounter(0x0003), run_logged(&LOOP, options).1);
+    }
+
+    #[test]
+    fn stop_on_memory_value() {
+        // INC $10, JMP $0000
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::MemoryValue(0x0010, 0x05)],
+            ..LogOptions::default()
+        };
+
+        let (lines, reason) = run_logged(&[0xE6, 0x10, 0x4C, 0x00, 0x00], options);
+
+        assert_eq!(StopReason::MemoryValue(0x0010, 0x05), reason);
+        assert_eq!(9, lines.len());
+    }
+
+    #[test]
+    fn stop_on_infinite_loop() {
+        // NOP, JMP $0001
+        let options = LogOptions { stop_on_loop: true, ..LogOptions::default() };
+
+        assert_eq!((vec!["0000".to_string(), "0001".to_string()], StopReason::InfiniteLoop(0x0001)),
+                   run_logged(&[0xEA, 0x4C, 0x01, 0x00], options));
+    }
+
+    #[test]
+    fn stop_on_halt() {
+        // NOP, JAM
+        assert_eq!(StopReason::Halted(0x0001), run_logged(&[0xEA, 0x02], LogOptions::default()).1);
+    }
+
+    #[test]
+    fn range_filter() {
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::Instructions(6)],
+            range: Some((0x0002, 0x0002)),
+            ..LogOptions::default()
+        };
+
+        assert_eq!(vec!["0002".to_string(); 2], run_logged(&LOOP, options).0);
+    }
+
+    #[test]
+    fn trigger_filter() {
+        let options = LogOptions {
+            stop_conditions: vec![StopCondition::Instructions(5)],
+            trigger: Some(0x0003),
+            ..LogOptions::default()
+        };
+
+        assert_eq!(vec!["0003".to_string(), "0002".to_string()], run_logged(&LOOP, options).0);
+    }
+}
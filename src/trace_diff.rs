@@ -0,0 +1,172 @@
+// Compares our trace lines with a golden log such as nestest.log, field by field, so a
+// divergence can be reported as "P differs" rather than as two long lines.
+// Whitespace is only significant between fields, and fields missing from the golden
+// log (older logs have no PPU or CYC) are not compared.
+
+// The parts of one nestest.log line
+#[derive(PartialEq, Debug)]
+pub struct TraceLine {
+    pub pc: String,
+    pub bytes: String,
+    pub instruction: String,
+    pub registers: Vec<(String, String)>
+}
+
+impl TraceLine {
+    pub fn parse(line: &str) -> Self {
+        let (prefix, suffix) = match line.find("A:") {
+            Some(index) => line.split_at(index),
+            None => (line, "")
+        };
+
+        let mut tokens = prefix.split_whitespace();
+        let pc = tokens.next().unwrap_or("").to_string();
+        let tokens: Vec<&str> = tokens.collect();
+        let byte_count = tokens.iter()
+            .take_while(|t| t.len() == 2 && t.chars().all(|c| c.is_ascii_hexdigit()))
+            .count();
+
+        TraceLine {
+            pc,
+            bytes: tokens[..byte_count].join(" "),
+            instruction: tokens[byte_count..].join(" "),
+            registers: parse_registers(suffix)
+        }
+    }
+
+    fn register(&self, name: &str) -> Option<&str> {
+        self.registers.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// A:00 X:00 ... PPU:  0, 21 CYC:7, where the PPU value has spaces of its own
+fn parse_registers(suffix: &str) -> Vec<(String, String)> {
+    let mut packed = String::new();
+    for c in suffix.chars() {
+        let after_separator = packed.ends_with(':') || packed.ends_with(',');
+        if !(c.is_whitespace() && after_separator) {
+            packed.push(c);
+        }
+    }
+
+    packed.split_whitespace()
+        .filter_map(|field| {
+            let mut parts = field.splitn(2, ':');
+            Some((parts.next()?.to_string(), parts.next()?.to_string()))
+        })
+        .collect()
+}
+
+// Describes the first field that differs, or None if the lines match
+pub fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    let expected = TraceLine::parse(expected);
+    let actual = TraceLine::parse(actual);
+
+    if expected.pc != actual.pc {
+        return Some(format!("PC expected {} but was {}", expected.pc, actual.pc));
+    }
+    if expected.bytes != actual.bytes {
+        return Some(format!("Opcode bytes expected {} but were {}", expected.bytes, actual.bytes));
+    }
+
+    for (name, expected_value) in &expected.registers {
+        let actual_value = actual.register(name).unwrap_or("missing");
+        if expected_value != actual_value {
+            let flags = if name == "P" { flag_difference(expected_value, actual_value) } else { String::new() };
+            let name = match name.as_str() {
+                "CYC" => "Cycle count",
+                "PPU" => "PPU position",
+                register => register
+            };
+            return Some(format!("{} expected {} but was {}{}", name, expected_value, actual_value, flags));
+        }
+    }
+
+    if expected.instruction != actual.instruction {
+        return Some(format!("Disassembly expected '{}' but was '{}'", expected.instruction, actual.instruction));
+    }
+
+    None
+}
+
+// Names the status flags that differ, e.g. " (Z set, C clear)"
+fn flag_difference(expected: &str, actual: &str) -> String {
+    let (expected, actual) = match (u8::from_str_radix(expected, 16), u8::from_str_radix(actual, 16)) {
+        (Ok(expected), Ok(actual)) => (expected, actual),
+        _ => return String::new()
+    };
+
+    let flags: Vec<String> = "NV-BDIZC".chars().enumerate()
+        .filter_map(|(bit, name)| {
+            let mask = 0x80 >> bit;
+            if (expected ^ actual) & mask == 0 {
+                None
+            } else if actual & mask != 0 {
+                Some(format!("{} set", name))
+            } else {
+                Some(format!("{} clear", name))
+            }
+        })
+        .collect();
+
+    format!(" ({})", flags.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{first_difference, TraceLine};
+
+    const GOLDEN: &str = "C72A  A9 00     LDA #$00                        A:00 X:00 Y:00 P:27 SP:FB PPU:  1,  5 CYC:180";
+
+    #[test]
+    fn parse_nestest_line() {
+        let line = TraceLine::parse(GOLDEN);
+
+        assert_eq!("C72A", line.pc);
+        assert_eq!("A9 00", line.bytes);
+        assert_eq!("LDA #$00", line.instruction);
+        assert_eq!(("PPU".to_string(), "1,5".to_string()), line.registers[5]);
+        assert_eq!(("CYC".to_string(), "180".to_string()), line.registers[6]);
+    }
+
+    #[test]
+    fn whitespace_is_ignored() {
+        let actual = "C72A A9 00 LDA #$00 A:00 X:00 Y:00 P:27 SP:FB PPU:1,5 CYC:180";
+
+        assert_eq!(None, first_difference(GOLDEN, actual));
+    }
+
+    #[test]
+    fn flag_difference() {
+        let actual = GOLDEN.replace("P:27", "P:A6");
+
+        assert_eq!(Some("P expected 27 but was A6 (N set, C clear)".to_string()),
+                   first_difference(GOLDEN, &actual));
+    }
+
+    #[test]
+    fn cycle_difference() {
+        let actual = GOLDEN.replace("CYC:180", "CYC:181");
+
+        assert_eq!(Some("Cycle count expected 180 but was 181".to_string()),
+                   first_difference(GOLDEN, &actual));
+    }
+
+    #[test]
+    fn missing_golden_fields_are_skipped() {
+        let golden = "C72A  A9 00     LDA #$00                        A:00 X:00 Y:00 P:27 SP:FB";
+
+        assert_eq!(None, first_difference(golden, GOLDEN));
+    }
+
+    #[test]
+    fn illegal_marker_is_part_of_disassembly() {
+        let golden = "C72A  04 A9    *NOP $A9 = 00                    A:00 X:00 Y:00 P:27 SP:FB";
+        let actual = "C72A  04 A9     NOP $A9 = 00                    A:00 X:00 Y:00 P:27 SP:FB";
+
+        assert_eq!(Some("Disassembly expected '*NOP $A9 = 00' but was 'NOP $A9 = 00'".to_string()),
+                   first_difference(golden, actual));
+    }
+}